@@ -0,0 +1,75 @@
+//! Edge lists.
+//!
+//! Each line is either an edge `a b` between the vertices named `a` and `b`,
+//! or a single vertex name `a` declaring a (possibly isolated) vertex.
+//! Vertex names are arbitrary tokens without whitespace.
+//! Lines starting with `#` are ignored.
+use crate::graph::Graph;
+use crate::Error;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Graph read from an edge list, with its vertex names.
+pub struct NamedGraph {
+	/// Vertex names, in order of first appearance.
+	pub names: Vec<String>,
+
+	pub graph: Graph,
+}
+
+pub fn parse(lines: &[String], directed: bool) -> Result<NamedGraph, Error> {
+	let mut names = Vec::new();
+	let mut indexes = HashMap::new();
+	let mut edges = Vec::new();
+
+	let mut vertex = |name: &str| -> usize {
+		*indexes.entry(name.to_string()).or_insert_with(|| {
+			names.push(name.to_string());
+			names.len() - 1
+		})
+	};
+
+	for line in lines {
+		let line = line.trim();
+		if line.starts_with('#') {
+			continue;
+		}
+
+		let tokens: Vec<_> = line.split_whitespace().collect();
+		match tokens.as_slice() {
+			[] => (),
+			[a] => {
+				vertex(a);
+			}
+			[a, b] => {
+				let a = vertex(a);
+				let b = vertex(b);
+				edges.push((a, b))
+			}
+			_ => return Err(Error::EdgeList(line.to_string())),
+		}
+	}
+
+	Ok(NamedGraph {
+		graph: Graph::new(names.len(), directed, edges),
+		names,
+	})
+}
+
+/// Writes the given graph as an edge list, using vertex indexes as names.
+pub fn write(graph: &Graph) -> String {
+	let mut result = String::new();
+	let mut isolated = vec![true; graph.vertex_count()];
+
+	for &(a, b) in graph.edges() {
+		isolated[a] = false;
+		isolated[b] = false;
+		writeln!(result, "{} {}", a, b).unwrap();
+	}
+
+	for (i, _) in isolated.into_iter().enumerate().filter(|(_, b)| *b) {
+		writeln!(result, "{}", i).unwrap();
+	}
+
+	result
+}
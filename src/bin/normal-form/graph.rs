@@ -0,0 +1,248 @@
+use normal_form::{Normalize, ReversibleColoring};
+use std::cmp::Ordering;
+
+/// Simple graph, possibly directed, with self loops.
+///
+/// Vertices are indexed from `0` to `vertex_count`.
+pub struct Graph {
+	vertex_count: usize,
+	directed: bool,
+
+	/// Sorted edges.
+	///
+	/// For undirected graphs, each edge `(a, b)` is such that `a <= b`.
+	edges: Vec<(usize, usize)>,
+}
+
+impl Graph {
+	pub fn new(
+		vertex_count: usize,
+		directed: bool,
+		edges: impl IntoIterator<Item = (usize, usize)>,
+	) -> Self {
+		let mut edges: Vec<_> = edges
+			.into_iter()
+			.map(|(a, b)| if directed || a <= b { (a, b) } else { (b, a) })
+			.collect();
+		edges.sort_unstable();
+		edges.dedup();
+
+		Self {
+			vertex_count,
+			directed,
+			edges,
+		}
+	}
+
+	pub fn vertex_count(&self) -> usize {
+		self.vertex_count
+	}
+
+	pub fn is_directed(&self) -> bool {
+		self.directed
+	}
+
+	pub fn edges(&self) -> &[(usize, usize)] {
+		&self.edges
+	}
+}
+
+pub struct Cache {
+	stack: Vec<usize>,
+	map: Vec<usize>,
+
+	/// Outgoing neighbors (all neighbors for undirected graphs).
+	successors: Vec<Vec<usize>>,
+
+	/// Incoming neighbors (empty for undirected graphs).
+	predecessors: Vec<Vec<usize>>,
+
+	/// Inverse of the compared morphism.
+	inverse: Vec<usize>,
+
+	/// Morphed edges targets of a single vertex.
+	targets: Vec<usize>,
+}
+
+impl Normalize for Graph {
+	type Elements = usize;
+	type Color = bool;
+	type Cache = Cache;
+	type Morphed = Self;
+
+	fn elements(&self) -> &usize {
+		&self.vertex_count
+	}
+
+	fn initialize_cache(&self) -> Cache {
+		let mut successors = vec![Vec::new(); self.vertex_count];
+		let mut predecessors = vec![Vec::new(); self.vertex_count];
+
+		for &(a, b) in &self.edges {
+			successors[a].push(b);
+			if self.directed {
+				predecessors[b].push(a)
+			} else if a != b {
+				successors[b].push(a)
+			}
+		}
+
+		Cache {
+			stack: Vec::new(),
+			map: vec![0; self.vertex_count],
+			successors,
+			predecessors,
+			inverse: vec![0; self.vertex_count],
+			targets: Vec::new(),
+		}
+	}
+
+	/// Vertices with a self loop are distinguished from the others.
+	fn initial_coloring(&self) -> Vec<bool> {
+		let mut colors = vec![false; self.vertex_count];
+		for &(a, b) in &self.edges {
+			if a == b {
+				colors[a] = true
+			}
+		}
+
+		colors
+	}
+
+	fn refine_coloring(&self, cache: &mut Cache, coloring: &mut ReversibleColoring<usize>) {
+		if self.directed {
+			loop {
+				let len = coloring.len();
+				coloring.make_equitable_with(&mut cache.stack, &mut cache.map, |i| {
+					&cache.successors[*i]
+				});
+				coloring.make_equitable_with(&mut cache.stack, &mut cache.map, |i| {
+					&cache.predecessors[*i]
+				});
+
+				if coloring.len() == len {
+					break;
+				}
+			}
+		} else {
+			coloring
+				.make_equitable_with(&mut cache.stack, &mut cache.map, |i| &cache.successors[*i])
+		}
+	}
+
+	fn apply_morphism<F>(&self, f: F) -> Self
+	where
+		F: Fn(&usize) -> usize,
+	{
+		Self::new(
+			self.vertex_count,
+			self.directed,
+			self.edges.iter().map(|(a, b)| (f(a), f(b))),
+		)
+	}
+
+	/// Morphed edges are produced in order, by source vertex, and compared
+	/// as they are produced.
+	fn cmp_morphism<F>(&self, cache: &mut Cache, f: F, other: &Self) -> Option<Ordering>
+	where
+		F: Fn(&usize) -> usize,
+	{
+		let ordering = self.vertex_count.cmp(&other.vertex_count);
+		if ordering.is_ne() {
+			return Some(ordering);
+		}
+
+		for a in 0..self.vertex_count {
+			cache.inverse[f(&a)] = a
+		}
+
+		let mut other_edges = other.edges.iter();
+		for a in 0..self.vertex_count {
+			cache.targets.clear();
+			cache.targets.extend(
+				cache.successors[cache.inverse[a]]
+					.iter()
+					.map(&f)
+					.filter(|&b| self.directed || a <= b),
+			);
+			cache.targets.sort_unstable();
+
+			for &b in &cache.targets {
+				match other_edges.next() {
+					Some(other_edge) => {
+						let ordering = (a, b).cmp(other_edge);
+						if ordering.is_ne() {
+							return Some(ordering);
+						}
+					}
+					None => return Some(Ordering::Greater),
+				}
+			}
+		}
+
+		if other_edges.next().is_some() {
+			Some(Ordering::Less)
+		} else {
+			Some(Ordering::Equal)
+		}
+	}
+}
+
+impl PartialEq for Graph {
+	fn eq(&self, other: &Self) -> bool {
+		self.vertex_count == other.vertex_count && self.edges == other.edges
+	}
+}
+
+impl Eq for Graph {}
+
+impl PartialOrd for Graph {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Graph {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.vertex_count
+			.cmp(&other.vertex_count)
+			.then_with(|| self.edges.cmp(&other.edges))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Graph;
+	use normal_form::Normalize;
+	use rand::{seq::SliceRandom, Rng};
+
+	#[test]
+	fn cmp_morphism() {
+		let mut rng = rand::thread_rng();
+		for directed in [false, true] {
+			for _ in 0..100 {
+				let n = 6;
+				let random_graph = |rng: &mut rand::rngs::ThreadRng| {
+					let edges: Vec<_> = (0..8)
+						.map(|_| (rng.gen_range(0..n), rng.gen_range(0..n)))
+						.collect();
+					Graph::new(n, directed, edges)
+				};
+
+				let graph = random_graph(&mut rng);
+				let other = random_graph(&mut rng);
+				let mut permutation: Vec<usize> = (0..n).collect();
+				permutation.shuffle(&mut rng);
+
+				let f = |i: &usize| permutation[*i];
+				let mut cache = graph.initialize_cache();
+				for other in [&other, &graph.apply_morphism(f)] {
+					assert_eq!(
+						graph.cmp_morphism(&mut cache, f, other),
+						Some(graph.apply_morphism(f).cmp(other))
+					)
+				}
+			}
+		}
+	}
+}
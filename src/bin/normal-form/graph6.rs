@@ -0,0 +1,118 @@
+//! [graph6](https://users.cecs.anu.edu.au/~bdm/data/formats.txt) format.
+use crate::graph::Graph;
+use crate::Error;
+
+/// Optional header of graph6 files.
+const HEADER: &str = ">>graph6<<";
+
+/// Parses a graph6 encoded undirected graph.
+pub fn parse(line: &str) -> Result<Graph, Error> {
+	let line = line.strip_prefix(HEADER).unwrap_or(line);
+	let mut bytes = line.bytes().map(|b| {
+		if (63..=126).contains(&b) {
+			Ok((b - 63) as usize)
+		} else {
+			Err(Error::Graph6("invalid character"))
+		}
+	});
+
+	let mut next = || bytes.next().unwrap_or(Err(Error::Graph6("unexpected end")));
+
+	let n = match next()? {
+		63 => {
+			let first = next()?;
+			let (len, init) = if first == 63 { (6, 0) } else { (2, first) };
+
+			let mut n = init;
+			for _ in 0..len {
+				n = (n << 6) | next()?
+			}
+			n
+		}
+		n => n,
+	};
+
+	let mut edges = Vec::new();
+	let mut buffer = 0;
+	let mut remaining_bits = 0;
+	for j in 1..n {
+		for i in 0..j {
+			if remaining_bits == 0 {
+				buffer = next()?;
+				remaining_bits = 6;
+			}
+
+			remaining_bits -= 1;
+			if buffer & (1 << remaining_bits) != 0 {
+				edges.push((i, j))
+			}
+		}
+	}
+
+	if bytes.next().is_some() {
+		return Err(Error::Graph6("trailing characters"));
+	}
+
+	Ok(Graph::new(n, false, edges))
+}
+
+/// Encodes the given undirected graph in graph6.
+pub fn write(graph: &Graph) -> String {
+	debug_assert!(!graph.is_directed());
+	let n = graph.vertex_count();
+	let mut bytes = Vec::new();
+
+	if n < 63 {
+		bytes.push(n as u8)
+	} else if n < 258048 {
+		bytes.push(63);
+		for k in (0..3).rev() {
+			bytes.push(((n >> (6 * k)) & 0x3f) as u8)
+		}
+	} else {
+		bytes.push(63);
+		bytes.push(63);
+		for k in (0..6).rev() {
+			bytes.push(((n >> (6 * k)) & 0x3f) as u8)
+		}
+	}
+
+	let mut adjacency = vec![false; n * n.saturating_sub(1) / 2];
+	for &(a, b) in graph.edges() {
+		if a != b {
+			// Index of the bit `(a, b)` with `a < b`.
+			adjacency[b * (b - 1) / 2 + a] = true
+		}
+	}
+
+	for chunk in adjacency.chunks(6) {
+		let mut byte = 0;
+		for (k, bit) in chunk.iter().enumerate() {
+			if *bit {
+				byte |= 1 << (5 - k)
+			}
+		}
+		bytes.push(byte)
+	}
+
+	bytes.into_iter().map(|b| (b + 63) as char).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn round_trip() {
+		for line in ["@", "A_", "Bw", "DQc", "I?h]@eOWG"] {
+			let graph = super::parse(line).unwrap();
+			assert_eq!(super::write(&graph), line)
+		}
+	}
+
+	#[test]
+	fn path() {
+		// Path 0 - 1 - 2.
+		let graph = super::parse("Bg").unwrap();
+		assert_eq!(graph.vertex_count(), 3);
+		assert_eq!(graph.edges(), [(0, 1), (1, 2)])
+	}
+}
@@ -0,0 +1,372 @@
+//! Command-line canonicalization tool.
+//!
+//! Reads graphs from files or from the standard input and outputs their
+//! canonical forms, canonical labelings, hashes, automorphism group sizes or
+//! isomorphism classes.
+use clap::{Parser, ValueEnum};
+use normal_form::{cnf, GroupOrder, Normalize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+mod edges;
+mod graph;
+mod graph6;
+mod rdf;
+
+/// Computes canonical forms of graphs.
+///
+/// In the graph6 format, each line is a graph. In the other formats, graphs
+/// are separated by blank lines.
+#[derive(Parser)]
+#[command(name = "normal-form", version)]
+struct Args {
+	/// What to output for each graph.
+	#[arg(value_enum)]
+	operation: Operation,
+
+	/// Input format.
+	///
+	/// By default the format is guessed from the file extension
+	/// (`.g6`, `.nt`, `.edges` or `.cnf`), and graph6 is assumed for the standard
+	/// input. It is required for other extensions, such as `.txt`.
+	#[arg(short, long, value_enum)]
+	format: Option<Format>,
+
+	/// Interpret edge lists as directed graphs.
+	#[arg(short, long)]
+	directed: bool,
+
+	/// Allow DIMACS variables to be negated, in addition to being renamed.
+	#[arg(short, long)]
+	negate: bool,
+
+	/// Input files. The standard input is read if none is given, or for `-`.
+	files: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Operation {
+	/// Canonical form of each graph, in the input format.
+	Canon,
+
+	/// Canonical labeling of each graph: the canonical index of each vertex
+	/// (or blank node), in input order.
+	Label,
+
+	/// SHA-256 hash of the canonical form of each graph.
+	Hash,
+
+	/// Order of the automorphism group of each graph.
+	Aut,
+
+	/// Isomorphism classes, one line per class listing the (0-based) indexes
+	/// of the graphs it contains, in order of first appearance.
+	Classes,
+
+	/// Canonical form of the first graph of each isomorphism class.
+	Unique,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+	/// graph6, one undirected graph per line.
+	Graph6,
+
+	/// Edge list, one `a b` edge per line.
+	Edges,
+
+	/// N-Triples, where blank nodes are the permuted elements.
+	Ntriples,
+
+	/// DIMACS CNF formula, where variables are renamed (and possibly negated
+	/// with `--negate`).
+	Dimacs,
+}
+
+impl Format {
+	fn from_path(path: &Path) -> Option<Self> {
+		match path.extension()?.to_str()? {
+			"g6" | "graph6" => Some(Self::Graph6),
+			"edges" | "el" => Some(Self::Edges),
+			"nt" => Some(Self::Ntriples),
+			"cnf" | "dimacs" => Some(Self::Dimacs),
+			_ => None,
+		}
+	}
+
+	/// Checks if every graph is written on a single line.
+	fn is_line_based(&self) -> bool {
+		matches!(self, Self::Graph6)
+	}
+}
+
+#[derive(Debug)]
+pub enum Error {
+	Io(io::Error),
+	UnknownFormat(PathBuf),
+	Graph6(&'static str),
+	EdgeList(String),
+	NTriples(&'static str, String),
+	Dimacs(cnf::ParseError),
+}
+
+impl From<io::Error> for Error {
+	fn from(e: io::Error) -> Self {
+		Self::Io(e)
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Io(e) => e.fmt(f),
+			Self::UnknownFormat(path) => write!(
+				f,
+				"unable to guess the format of `{}`, use `--format`",
+				path.display()
+			),
+			Self::Graph6(message) => write!(f, "invalid graph6: {}", message),
+			Self::EdgeList(line) => write!(f, "invalid edge list line `{}`", line),
+			Self::NTriples(message, line) => {
+				write!(f, "invalid N-Triples: {} in `{}`", message, line)
+			}
+			Self::Dimacs(e) => write!(f, "invalid DIMACS: {}", e),
+		}
+	}
+}
+
+/// Input graph.
+enum Input {
+	Graph6(graph::Graph),
+	Edges(edges::NamedGraph),
+	NTriples(rdf::Graph),
+	Cnf(cnf::Cnf),
+}
+
+/// Canonical form of an input graph.
+struct Canonical {
+	/// Canonical form, written in the input format.
+	///
+	/// Multi-line forms end with a new line, so that writing the form followed
+	/// by a new line separates it from the next one with a blank line.
+	form: String,
+
+	/// Name of each input vertex, with its canonical name.
+	labeling: Vec<(String, String)>,
+}
+
+impl Input {
+	fn parse(args: &Args, format: Format, lines: &[String]) -> Result<Self, Error> {
+		match format {
+			Format::Graph6 => Ok(Self::Graph6(graph6::parse(lines[0].trim())?)),
+			Format::Edges => Ok(Self::Edges(edges::parse(lines, args.directed)?)),
+			Format::Ntriples => Ok(Self::NTriples(rdf::Graph::parse(lines)?)),
+			Format::Dimacs => {
+				let cnf = cnf::Cnf::parse_dimacs(&lines.join("\n")).map_err(Error::Dimacs)?;
+				if args.negate {
+					Ok(Self::Cnf(cnf))
+				} else {
+					Ok(Self::Cnf(cnf.without_negation()))
+				}
+			}
+		}
+	}
+
+	fn canonical(&self) -> Canonical {
+		match self {
+			Self::Graph6(g) => {
+				let (form, permutation) = g.normalize();
+				Canonical {
+					form: graph6::write(&form),
+					labeling: permutation
+						.into_iter()
+						.enumerate()
+						.map(|(i, j)| (i.to_string(), j.to_string()))
+						.collect(),
+				}
+			}
+			Self::Edges(g) => {
+				let (form, permutation) = g.graph.normalize();
+				Canonical {
+					form: edges::write(&form),
+					labeling: g
+						.names
+						.iter()
+						.cloned()
+						.zip(permutation.iter().map(usize::to_string))
+						.collect(),
+				}
+			}
+			Self::NTriples(g) => {
+				let (form, permutation) = g.normalize();
+				Canonical {
+					form: g.write(&form),
+					labeling: g
+						.blank_labels()
+						.iter()
+						.map(|label| format!("_:{}", label))
+						.zip(permutation.iter().map(usize::to_string))
+						.collect(),
+				}
+			}
+			Self::Cnf(f) => {
+				// Each variable is labeled with its canonical literal.
+				let (form, renaming) = f.canonize();
+				Canonical {
+					form: form.to_dimacs(),
+					labeling: renaming
+						.into_iter()
+						.enumerate()
+						.map(|(v, l)| ((v + 1).to_string(), l.to_dimacs().to_string()))
+						.collect(),
+				}
+			}
+		}
+	}
+
+	fn automorphism_group_order(&self) -> GroupOrder {
+		match self {
+			Self::Graph6(g) => g.automorphism_group_order(),
+			Self::Edges(g) => g.graph.automorphism_group_order(),
+			Self::NTriples(g) => g.automorphism_group_order(),
+			Self::Cnf(f) => f.automorphism_group_order(),
+		}
+	}
+}
+
+/// Reads the graphs of the given input, calling `f` for each of them.
+fn read_graphs<F>(input: impl BufRead, args: &Args, format: Format, mut f: F) -> Result<(), Error>
+where
+	F: FnMut(Input) -> Result<(), Error>,
+{
+	let mut lines = Vec::new();
+
+	for line in input.lines() {
+		let line = line?;
+
+		if format.is_line_based() {
+			if !line.trim().is_empty() {
+				f(Input::parse(args, format, &[line])?)?
+			}
+		} else if line.trim().is_empty() {
+			if !lines.is_empty() {
+				f(Input::parse(args, format, &lines)?)?;
+				lines.clear()
+			}
+		} else {
+			lines.push(line)
+		}
+	}
+
+	if !lines.is_empty() {
+		f(Input::parse(args, format, &lines)?)?
+	}
+
+	Ok(())
+}
+
+fn run(args: Args) -> Result<(), Error> {
+	let stdout = io::stdout();
+	let mut output = io::BufWriter::new(stdout.lock());
+
+	let files = if args.files.is_empty() {
+		vec![PathBuf::from("-")]
+	} else {
+		args.files.clone()
+	};
+
+	// Graph indexes of each isomorphism class, and class of each canonical
+	// form hash.
+	let mut classes: Vec<Vec<usize>> = Vec::new();
+	let mut class_indexes: HashMap<[u8; 32], usize> = HashMap::new();
+	let mut count = 0;
+
+	for path in files {
+		let (input, format): (Box<dyn BufRead>, _) = if path == Path::new("-") {
+			(
+				Box::new(BufReader::new(io::stdin())),
+				args.format.unwrap_or(Format::Graph6),
+			)
+		} else {
+			let format = match args.format {
+				Some(format) => format,
+				None => {
+					Format::from_path(&path).ok_or_else(|| Error::UnknownFormat(path.clone()))?
+				}
+			};
+
+			(Box::new(BufReader::new(File::open(&path)?)), format)
+		};
+
+		read_graphs(input, &args, format, |graph| {
+			let index = count;
+			count += 1;
+
+			match args.operation {
+				Operation::Aut => writeln!(output, "{}", graph.automorphism_group_order())?,
+				Operation::Canon => writeln!(output, "{}", graph.canonical().form)?,
+				Operation::Label => {
+					let labeling = graph.canonical().labeling;
+					if format.is_line_based() {
+						let indexes: Vec<_> = labeling.into_iter().map(|(_, i)| i).collect();
+						writeln!(output, "{}", indexes.join(" "))?
+					} else {
+						for (name, i) in labeling {
+							writeln!(output, "{} {}", name, i)?
+						}
+						writeln!(output)?
+					}
+				}
+				Operation::Hash => {
+					let hash = Sha256::digest(graph.canonical().form.as_bytes());
+					writeln!(output, "{}", hex(&hash))?
+				}
+				Operation::Classes | Operation::Unique => {
+					let form = graph.canonical().form;
+					let hash: [u8; 32] = Sha256::digest(form.as_bytes()).into();
+					match class_indexes.get(&hash) {
+						Some(&c) => classes[c].push(index),
+						None => {
+							class_indexes.insert(hash, classes.len());
+							if args.operation == Operation::Unique {
+								writeln!(output, "{}", form)?
+							}
+							classes.push(vec![index])
+						}
+					}
+				}
+			}
+
+			Ok(())
+		})?
+	}
+
+	if args.operation == Operation::Classes {
+		for indexes in classes {
+			let indexes: Vec<_> = indexes.iter().map(usize::to_string).collect();
+			writeln!(output, "{}", indexes.join(" "))?
+		}
+	}
+
+	output.flush()?;
+	Ok(())
+}
+
+fn hex(bytes: &[u8]) -> String {
+	bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn main() -> ExitCode {
+	match run(Args::parse()) {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(e) => {
+			eprintln!("error: {}", e);
+			ExitCode::FAILURE
+		}
+	}
+}
@@ -0,0 +1,518 @@
+//! RDF graphs in the [N-Triples](https://www.w3.org/TR/n-triples/) format.
+//!
+//! Blank nodes are the permutable elements of the graph.
+//! IRIs and literals are compared by their N-Triples lexical representation.
+use crate::Error;
+use normal_form::{Normalize, ReversibleColoring};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Term {
+	/// IRI or literal, given by its index in the graph's value table.
+	Value(usize),
+
+	/// Blank node.
+	Blank(usize),
+}
+
+impl Term {
+	fn apply_morphism<F>(&self, f: F) -> Self
+	where
+		F: Fn(&usize) -> usize,
+	{
+		match self {
+			Self::Value(v) => Self::Value(*v),
+			Self::Blank(x) => Self::Blank(f(x)),
+		}
+	}
+
+	fn blank(&self) -> Option<usize> {
+		match self {
+			Self::Blank(x) => Some(*x),
+			Self::Value(_) => None,
+		}
+	}
+}
+
+pub type Triple = [Term; 3];
+
+/// RDF graph.
+pub struct Graph {
+	/// Blank node labels, in order of first appearance.
+	blank_labels: Vec<String>,
+
+	/// Number of blank nodes.
+	blank_count: usize,
+
+	/// IRIs and literals in N-Triples syntax, sorted.
+	///
+	/// Sorting the values ensures that two isomorphic graphs share the
+	/// same value indexes.
+	values: Vec<String>,
+
+	/// Sorted triples.
+	triples: Vec<Triple>,
+}
+
+/// Role of a term in a triple, relative to a given blank node.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Slot {
+	/// The blank node itself.
+	Itself,
+
+	/// Another blank node.
+	Blank,
+
+	/// IRI or literal.
+	Value(usize),
+}
+
+pub struct Cache {
+	stack: Vec<usize>,
+	map: Vec<usize>,
+	neighbors: Vec<BTreeSet<usize>>,
+
+	/// Indexes of the triples whose subject is the given blank node.
+	subjects: Vec<Vec<usize>>,
+
+	/// Inverse of the morphism being compared.
+	inverse: Vec<usize>,
+
+	/// Morphed triples buffer.
+	morphed: Vec<Triple>,
+}
+
+impl Graph {
+	/// Parses an N-Triples document.
+	pub fn parse(lines: &[String]) -> Result<Self, Error> {
+		let mut blank_labels = Vec::new();
+		let mut blank_indexes = HashMap::new();
+		let mut parsed_triples = Vec::new();
+
+		for line in lines {
+			let mut parser = Parser::new(line);
+			parser.skip_whitespace();
+			if parser.is_done() {
+				continue;
+			}
+
+			let triple = [parser.term()?, parser.term()?, parser.term()?];
+
+			parser.skip_whitespace();
+			if !parser.consume('.') {
+				return Err(parser.error("expected `.`"));
+			}
+
+			parser.skip_whitespace();
+			if !parser.is_done() {
+				return Err(parser.error("unexpected characters"));
+			}
+
+			parsed_triples.push(triple);
+		}
+
+		let values: BTreeSet<_> = parsed_triples
+			.iter()
+			.flatten()
+			.filter_map(|t| match t {
+				Lexical::Value(v) => Some(v.clone()),
+				Lexical::Blank(_) => None,
+			})
+			.collect();
+		let values: Vec<_> = values.into_iter().collect();
+
+		let mut triples: Vec<Triple> = parsed_triples
+			.into_iter()
+			.map(|triple| {
+				triple.map(|t| match t {
+					Lexical::Value(v) => Term::Value(values.binary_search(&v).unwrap()),
+					Lexical::Blank(label) => {
+						Term::Blank(*blank_indexes.entry(label.clone()).or_insert_with(|| {
+							blank_labels.push(label);
+							blank_labels.len() - 1
+						}))
+					}
+				})
+			})
+			.collect();
+		triples.sort_unstable();
+		triples.dedup();
+
+		Ok(Self {
+			blank_count: blank_labels.len(),
+			blank_labels,
+			values,
+			triples,
+		})
+	}
+
+	pub fn blank_labels(&self) -> &[String] {
+		&self.blank_labels
+	}
+
+	/// Writes the given triples of this graph in N-Triples, labeling the
+	/// blank node `i` as `_:bi`.
+	pub fn write(&self, triples: &[Triple]) -> String {
+		let mut result = String::new();
+
+		for triple in triples {
+			for term in triple {
+				match term {
+					Term::Value(v) => write!(result, "{} ", self.values[*v]).unwrap(),
+					Term::Blank(x) => write!(result, "_:b{} ", x).unwrap(),
+				}
+			}
+
+			result.push_str(".\n")
+		}
+
+		result
+	}
+}
+
+impl Normalize for Graph {
+	type Elements = usize;
+	type Color = Vec<[Slot; 3]>;
+	type Cache = Cache;
+	type Morphed = Vec<Triple>;
+
+	fn elements(&self) -> &usize {
+		&self.blank_count
+	}
+
+	fn initialize_cache(&self) -> Cache {
+		let n = self.blank_count;
+		let mut neighbors = vec![BTreeSet::new(); n];
+		let mut subjects = vec![Vec::new(); n];
+
+		for (i, triple) in self.triples.iter().enumerate() {
+			if let Term::Blank(x) = triple[0] {
+				subjects[x].push(i)
+			}
+
+			for a in triple.iter().filter_map(Term::blank) {
+				for b in triple.iter().filter_map(Term::blank) {
+					if a != b {
+						neighbors[a].insert(b);
+					}
+				}
+			}
+		}
+
+		Cache {
+			stack: Vec::new(),
+			map: vec![0; n],
+			neighbors,
+			subjects,
+			inverse: vec![0; n],
+			morphed: Vec::new(),
+		}
+	}
+
+	/// Each blank node is colored by the triples in which it appears.
+	fn initial_coloring(&self) -> Vec<Vec<[Slot; 3]>> {
+		let mut colors = vec![Vec::new(); self.blank_count];
+
+		for triple in &self.triples {
+			let blanks: BTreeSet<_> = triple.iter().filter_map(Term::blank).collect();
+			for x in blanks {
+				colors[x].push(triple.map(|t| match t {
+					Term::Blank(y) if y == x => Slot::Itself,
+					Term::Blank(_) => Slot::Blank,
+					Term::Value(v) => Slot::Value(v),
+				}))
+			}
+		}
+
+		for color in &mut colors {
+			color.sort_unstable()
+		}
+
+		colors
+	}
+
+	fn refine_coloring(&self, cache: &mut Cache, coloring: &mut ReversibleColoring<usize>) {
+		coloring.make_equitable_with(&mut cache.stack, &mut cache.map, |i| &cache.neighbors[*i])
+	}
+
+	fn apply_morphism<F>(&self, f: F) -> Vec<Triple>
+	where
+		F: Fn(&usize) -> usize,
+	{
+		let mut triples: Vec<_> = self
+			.triples
+			.iter()
+			.map(|triple| triple.map(|t| t.apply_morphism(&f)))
+			.collect();
+		triples.sort_unstable();
+		triples
+	}
+
+	/// Morphed triples are produced in order, by subject, and compared as
+	/// they are produced.
+	fn cmp_morphism<F>(&self, cache: &mut Cache, f: F, other: &Vec<Triple>) -> Option<Ordering>
+	where
+		F: Fn(&usize) -> usize,
+	{
+		let mut other_triples = other.iter();
+
+		// Values come before blank nodes, so the triples with a value subject
+		// come first, grouped by subject.
+		let len = self
+			.triples
+			.partition_point(|triple| matches!(triple[0], Term::Value(_)));
+		for group in self.triples[..len].chunk_by(|a, b| a[0] == b[0]) {
+			cache.morphed.clear();
+			cache.morphed.extend(
+				group
+					.iter()
+					.map(|triple| triple.map(|t| t.apply_morphism(&f))),
+			);
+			let ordering = cmp_next(&mut cache.morphed, &mut other_triples);
+			if ordering.is_ne() {
+				return Some(ordering);
+			}
+		}
+
+		for x in 0..self.blank_count {
+			cache.inverse[f(&x)] = x
+		}
+
+		for a in 0..self.blank_count {
+			let x = cache.inverse[a];
+			cache.morphed.clear();
+			cache.morphed.extend(
+				cache.subjects[x]
+					.iter()
+					.map(|&i| self.triples[i].map(|t| t.apply_morphism(&f))),
+			);
+			let ordering = cmp_next(&mut cache.morphed, &mut other_triples);
+			if ordering.is_ne() {
+				return Some(ordering);
+			}
+		}
+
+		if other_triples.next().is_some() {
+			Some(Ordering::Less)
+		} else {
+			Some(Ordering::Equal)
+		}
+	}
+}
+
+/// Sorts the given triples and compares them with the next triples of
+/// `other`.
+fn cmp_next(triples: &mut [Triple], other: &mut std::slice::Iter<Triple>) -> Ordering {
+	triples.sort_unstable();
+	for triple in triples.iter() {
+		match other.next() {
+			Some(other_triple) => {
+				let ordering = triple.cmp(other_triple);
+				if ordering.is_ne() {
+					return ordering;
+				}
+			}
+			None => return Ordering::Greater,
+		}
+	}
+
+	Ordering::Equal
+}
+
+/// Term as written in the document.
+enum Lexical {
+	Value(String),
+	Blank(String),
+}
+
+struct Parser<'a> {
+	line: &'a str,
+	offset: usize,
+}
+
+impl<'a> Parser<'a> {
+	fn new(line: &'a str) -> Self {
+		Self { line, offset: 0 }
+	}
+
+	fn error(&self, message: &'static str) -> Error {
+		Error::NTriples(message, self.line.to_string())
+	}
+
+	fn rest(&self) -> &'a str {
+		&self.line[self.offset..]
+	}
+
+	fn peek(&self) -> Option<char> {
+		self.rest().chars().next()
+	}
+
+	fn next(&mut self) -> Option<char> {
+		let c = self.peek()?;
+		self.offset += c.len_utf8();
+		Some(c)
+	}
+
+	fn consume(&mut self, c: char) -> bool {
+		if self.peek() == Some(c) {
+			self.offset += c.len_utf8();
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Skips whitespaces and comments.
+	fn skip_whitespace(&mut self) {
+		while let Some(c) = self.peek() {
+			if c == '#' {
+				self.offset = self.line.len()
+			} else if c.is_whitespace() {
+				self.offset += c.len_utf8()
+			} else {
+				break;
+			}
+		}
+	}
+
+	fn is_done(&self) -> bool {
+		self.offset >= self.line.len()
+	}
+
+	fn term(&mut self) -> Result<Lexical, Error> {
+		self.skip_whitespace();
+		let start = self.offset;
+		match self.next() {
+			Some('<') => {
+				self.iri()?;
+				Ok(Lexical::Value(self.line[start..self.offset].to_string()))
+			}
+			Some('_') => {
+				if !self.consume(':') {
+					return Err(self.error("expected `:`"));
+				}
+
+				let label_start = self.offset;
+				while let Some(c) = self.peek() {
+					if c.is_whitespace()
+						|| c == '<' || c == '"'
+						|| (c == '.' && self.is_label_end())
+					{
+						break;
+					}
+					self.offset += c.len_utf8();
+				}
+
+				if self.offset == label_start {
+					return Err(self.error("empty blank node label"));
+				}
+
+				Ok(Lexical::Blank(
+					self.line[label_start..self.offset].to_string(),
+				))
+			}
+			Some('"') => {
+				loop {
+					match self.next() {
+						Some('"') => break,
+						Some('\\') => {
+							self.next()
+								.ok_or_else(|| self.error("unterminated literal"))?;
+						}
+						Some(_) => (),
+						None => return Err(self.error("unterminated literal")),
+					}
+				}
+
+				if self.consume('@') {
+					while let Some(c) = self.peek() {
+						if c.is_ascii_alphanumeric() || c == '-' {
+							self.offset += 1
+						} else {
+							break;
+						}
+					}
+				} else if self.rest().starts_with("^^") {
+					self.offset += 2;
+					if !self.consume('<') {
+						return Err(self.error("expected datatype IRI"));
+					}
+					self.iri()?;
+				}
+
+				Ok(Lexical::Value(self.line[start..self.offset].to_string()))
+			}
+			_ => Err(self.error("expected term")),
+		}
+	}
+
+	/// Parses the rest of an IRI, after the opening `<`.
+	fn iri(&mut self) -> Result<(), Error> {
+		loop {
+			match self.next() {
+				Some('>') => break Ok(()),
+				Some(c) if c.is_whitespace() => break Err(self.error("invalid IRI")),
+				Some(_) => (),
+				None => break Err(self.error("unterminated IRI")),
+			}
+		}
+	}
+
+	/// Checks if a `.` at the current position ends a blank node label,
+	/// meaning it is not followed by another label character.
+	fn is_label_end(&self) -> bool {
+		match self.rest()[1..].chars().next() {
+			Some(c) => c.is_whitespace() || c == '#',
+			None => true,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Graph, Term};
+	use normal_form::Normalize;
+	use rand::{seq::SliceRandom, Rng};
+
+	#[test]
+	fn cmp_morphism() {
+		let mut rng = rand::thread_rng();
+		for _ in 0..100 {
+			let n = 6;
+			let random_graph = |rng: &mut rand::rngs::ThreadRng| {
+				let mut term = || {
+					if rng.gen() {
+						Term::Value(rng.gen_range(0..3))
+					} else {
+						Term::Blank(rng.gen_range(0..n))
+					}
+				};
+				let mut triples: Vec<_> = (0..10).map(|_| [term(), term(), term()]).collect();
+				triples.sort_unstable();
+				triples.dedup();
+				Graph {
+					blank_labels: (0..n).map(|x| x.to_string()).collect(),
+					blank_count: n,
+					values: (0..3).map(|v| v.to_string()).collect(),
+					triples,
+				}
+			};
+
+			let graph = random_graph(&mut rng);
+			let other = random_graph(&mut rng);
+			let mut permutation: Vec<usize> = (0..n).collect();
+			permutation.shuffle(&mut rng);
+
+			let f = |i: &usize| permutation[*i];
+			let mut cache = graph.initialize_cache();
+			for other in [&other.triples, &graph.apply_morphism(f)] {
+				assert_eq!(
+					graph.cmp_morphism(&mut cache, f, other),
+					Some(graph.apply_morphism(f).cmp(other))
+				)
+			}
+		}
+	}
+}
@@ -0,0 +1,320 @@
+//! Bipartite graphs.
+//!
+//! A [`BipartiteGraph`] has a left and a right side of vertices, which are
+//! kept apart by the initial coloring. This represents incidence structures
+//! like block designs (points and blocks), hypergraphs (vertices and
+//! hyperedges) or relation tables (values and rows).
+//!
+//! By default, isomorphisms preserve the sides. Sides can also be allowed to
+//! be swapped with [`BipartiteGraph::with_swappable_sides`], in which case
+//! the canonical form is the smallest canonical form of the graph and of its
+//! mirror. Automorphisms always preserve the sides, even when they are
+//! swappable, and [`BipartiteGraph::is_self_dual`] tells if the graph is
+//! isomorphic to its mirror.
+//!
+//! ```
+//! use normal_form::{bipartite::BipartiteGraph, Normalize};
+//!
+//! // A star, center on the left or on the right.
+//! let a = BipartiteGraph::new(1, 3, [(0, 0), (0, 1), (0, 2)]);
+//! let b = BipartiteGraph::new(3, 1, [(0, 0), (1, 0), (2, 0)]);
+//! assert_ne!(a.normal_form(), b.normal_form());
+//! let (a, b) = (a.with_swappable_sides(), b.with_swappable_sides());
+//! assert_eq!(a.normal_form(), b.normal_form());
+//! ```
+use crate::sides::{self, Sides};
+use crate::{Normalize, ReversibleColoring};
+
+/// Bipartite graph.
+///
+/// Vertices are numbered from `0` to `left_count + right_count`, the left
+/// vertices first.
+#[derive(Clone, Debug)]
+pub struct BipartiteGraph {
+	left_count: usize,
+	right_count: usize,
+
+	/// Number of vertices.
+	len: usize,
+
+	/// Sorted edges, from left vertex to right vertex indexes.
+	edges: Vec<(usize, usize)>,
+
+	/// Neighbors of each vertex.
+	neighbors: Vec<Vec<usize>>,
+
+	/// Isomorphisms may swap the sides.
+	swappable_sides: bool,
+}
+
+impl BipartiteGraph {
+	/// Creates a new bipartite graph, where each edge `(i, j)` connects the
+	/// `i`-th left vertex to the `j`-th right vertex.
+	///
+	/// # Panics
+	///
+	/// Panics if an edge refers to a vertex outside of its side.
+	pub fn new(
+		left_count: usize,
+		right_count: usize,
+		edges: impl IntoIterator<Item = (usize, usize)>,
+	) -> Self {
+		let mut edges: Vec<_> = edges.into_iter().collect();
+		edges.sort_unstable();
+		edges.dedup();
+
+		let len = left_count + right_count;
+		let mut neighbors = vec![Vec::new(); len];
+		for &(i, j) in &edges {
+			assert!(i < left_count && j < right_count, "invalid edge");
+			neighbors[i].push(left_count + j);
+			neighbors[left_count + j].push(i)
+		}
+
+		Self {
+			left_count,
+			right_count,
+			len,
+			edges,
+			neighbors,
+			swappable_sides: false,
+		}
+	}
+
+	/// Allows isomorphisms to swap the sides.
+	pub fn with_swappable_sides(mut self) -> Self {
+		self.swappable_sides = true;
+		self
+	}
+
+	pub fn left_count(&self) -> usize {
+		self.left_count
+	}
+
+	pub fn right_count(&self) -> usize {
+		self.right_count
+	}
+
+	pub fn edges(&self) -> &[(usize, usize)] {
+		&self.edges
+	}
+
+	pub fn has_swappable_sides(&self) -> bool {
+		self.swappable_sides
+	}
+
+	/// Returns the same graph with the sides swapped.
+	pub fn mirror(&self) -> Self {
+		let mut result = Self::new(
+			self.right_count,
+			self.left_count,
+			self.edges.iter().map(|&(i, j)| (j, i)),
+		);
+		result.swappable_sides = self.swappable_sides;
+		result
+	}
+
+	/// Checks if this graph is isomorphic to its mirror.
+	///
+	/// If so, and sides are swappable, the isomorphisms swapping the sides
+	/// double the automorphism group, which only contains the automorphisms
+	/// preserving the sides.
+	pub fn is_self_dual(&self) -> bool {
+		sides::is_self_dual(self)
+	}
+}
+
+/// Canonical form of a [`BipartiteGraph`].
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct MorphedBipartiteGraph {
+	pub left_count: usize,
+	pub right_count: usize,
+
+	/// Sorted edges, from left to right vertex, given by their index in the
+	/// whole graph.
+	pub edges: Vec<(usize, usize)>,
+}
+
+pub struct Cache {
+	stack: Vec<usize>,
+	map: Vec<usize>,
+}
+
+impl Normalize for BipartiteGraph {
+	type Elements = usize;
+
+	/// Side of the vertex, `false` for left.
+	type Color = bool;
+
+	type Cache = Cache;
+
+	type Morphed = MorphedBipartiteGraph;
+
+	fn initialize_cache(&self) -> Cache {
+		Cache {
+			stack: Vec::new(),
+			map: vec![0; self.len],
+		}
+	}
+
+	fn elements(&self) -> &usize {
+		&self.len
+	}
+
+	fn initial_coloring(&self) -> Vec<bool> {
+		(0..self.len).map(|x| x >= self.left_count).collect()
+	}
+
+	fn refine_coloring(&self, cache: &mut Cache, coloring: &mut ReversibleColoring<usize>) {
+		coloring.make_equitable_with(&mut cache.stack, &mut cache.map, |x| &self.neighbors[*x])
+	}
+
+	fn apply_morphism<F>(&self, f: F) -> MorphedBipartiteGraph
+	where
+		F: Fn(&usize) -> usize,
+	{
+		let swapped = self.swaps_sides(&f);
+		let mut edges: Vec<_> = self
+			.edges
+			.iter()
+			.map(|&(i, j)| {
+				let (a, b) = (f(&i), f(&(self.left_count + j)));
+				if swapped {
+					(b, a)
+				} else {
+					(a, b)
+				}
+			})
+			.collect();
+		edges.sort_unstable();
+
+		let (left_count, right_count) = if swapped {
+			(self.right_count, self.left_count)
+		} else {
+			(self.left_count, self.right_count)
+		};
+
+		MorphedBipartiteGraph {
+			left_count,
+			right_count,
+			edges,
+		}
+	}
+
+	/// If sides are swappable, this is the smallest canonical form of the
+	/// graph and of its mirror. In the latter case, the canonical
+	/// permutation maps left vertices after right vertices, and
+	/// [`Normalize::apply_morphism`] swaps the sides.
+	fn normalize(&self) -> (MorphedBipartiteGraph, Vec<usize>) {
+		sides::normalize(self)
+	}
+
+	fn normal_form(&self) -> MorphedBipartiteGraph {
+		self.normalize().0
+	}
+
+	fn canonical_permutation(&self) -> Vec<usize> {
+		self.normalize().1
+	}
+}
+
+impl Sides for BipartiteGraph {
+	fn first_side_len(&self) -> usize {
+		self.left_count
+	}
+
+	fn swappable_sides(&self) -> bool {
+		self.swappable_sides
+	}
+
+	fn swap_sides(&self) -> Self {
+		self.mirror()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::BipartiteGraph;
+	use crate::Normalize;
+
+	/// Incidence graph of the Fano plane.
+	fn fano() -> BipartiteGraph {
+		let lines = [
+			[0, 1, 2],
+			[0, 3, 4],
+			[0, 5, 6],
+			[1, 3, 5],
+			[1, 4, 6],
+			[2, 3, 6],
+			[2, 4, 5],
+		];
+		BipartiteGraph::new(
+			7,
+			7,
+			lines
+				.iter()
+				.enumerate()
+				.flat_map(|(l, points)| points.iter().map(move |&p| (p, l))),
+		)
+	}
+
+	/// Cycle of length `2n`, alternating sides.
+	fn cycle(n: usize) -> BipartiteGraph {
+		BipartiteGraph::new(n, n, (0..n).flat_map(|i| [(i, i), ((i + 1) % n, i)]))
+	}
+
+	#[test]
+	fn group_order() {
+		let complete = BipartiteGraph::new(2, 3, (0..2).flat_map(|i| (0..3).map(move |j| (i, j))));
+		assert_eq!(complete.automorphism_group_order().to_u128(), Some(12));
+		assert!(!complete.is_self_dual());
+		assert_eq!(
+			complete
+				.with_swappable_sides()
+				.automorphism_group_order()
+				.to_u128(),
+			Some(12)
+		);
+
+		// Automorphisms preserve the sides, even if they are swappable.
+		let cycle = cycle(3).with_swappable_sides();
+		assert_eq!(cycle.automorphism_group_order().to_u128(), Some(6));
+		assert_eq!(cycle.automorphism_group().order().to_u128(), Some(6));
+		assert!(cycle.is_self_dual());
+
+		let fano = fano().with_swappable_sides();
+		assert_eq!(fano.automorphism_group_order().to_u128(), Some(168));
+		assert_eq!(fano.automorphism_group().order().to_u128(), Some(168));
+		assert!(fano.is_self_dual());
+		assert!(!BipartiteGraph::new(2, 2, [(0, 0), (0, 1)]).is_self_dual());
+	}
+
+	#[test]
+	fn swappable_sides() {
+		// A path with one more vertex on the left.
+		let a = BipartiteGraph::new(2, 1, [(0, 0), (1, 0)]);
+		// Left degrees are 3, 1, 0 and right degrees 2, 1, 1.
+		let b = BipartiteGraph::new(3, 3, [(0, 0), (0, 1), (0, 2), (1, 0)]);
+		for g in [a, b] {
+			let mirror = g.mirror();
+			assert_ne!(g.normal_form(), mirror.normal_form());
+
+			let (g, mirror) = (g.with_swappable_sides(), mirror.with_swappable_sides());
+			let (form, permutation) = mirror.normalize();
+			assert_eq!(g.normal_form(), form);
+			assert_eq!(mirror.apply_morphism(|x| permutation[*x]), form);
+
+			let (form, permutation) = g.normalize();
+			assert_eq!(mirror.normal_form(), form);
+			assert_eq!(g.apply_morphism(|x| permutation[*x]), form)
+		}
+
+		// Vertices on one side only.
+		let g = BipartiteGraph::new(2, 0, []).with_swappable_sides();
+		let (form, permutation) = g.normalize();
+		assert_eq!((form.left_count, form.right_count), (0, 2));
+		assert_eq!(g.apply_morphism(|x| permutation[*x]), form);
+		assert_eq!(g.mirror().normal_form(), form);
+		assert_eq!(g.automorphism_group_order().to_u128(), Some(2))
+	}
+}
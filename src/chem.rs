@@ -0,0 +1,350 @@
+//! Molecules.
+//!
+//! A [`Molecule`] is a graph of atoms, colored by their element, charge,
+//! hydrogen count and aromaticity, linked by bonds labeled with their order.
+//! Hydrogens are implicit: they are counted on the atom they are bound to,
+//! instead of being atoms of their own.
+//!
+//! Molecules can be read from SMILES strings ([`Molecule::parse_smiles`]) or
+//! MDL molfiles ([`Molecule::parse_molfile`]), and written as canonical
+//! SMILES ([`Molecule::to_canonical_smiles`]): two molecules have the same
+//! canonical SMILES if and only if they are isomorphic.
+//!
+//! ```
+//! use normal_form::chem::Molecule;
+//!
+//! let a = Molecule::parse_smiles("OCC").unwrap();
+//! let b = Molecule::parse_smiles("C(C)O").unwrap();
+//! assert_eq!(a.to_canonical_smiles(), b.to_canonical_smiles());
+//! ```
+//!
+//! Stereochemistry and isotopes are not supported, and aromaticity is not
+//! perceived: the aromatic and Kekulé forms of the same molecule are
+//! different.
+use crate::{
+	multigraph::{MorphedMultigraph, Multigraph},
+	Normalize, ReversibleColoring,
+};
+use std::fmt;
+
+mod molfile;
+mod smiles;
+
+pub use molfile::MolfileError;
+pub use smiles::SmilesError;
+
+/// Element symbols, by atomic number.
+const SYMBOLS: [&str; 118] = [
+	"H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne", "Na", "Mg", "Al", "Si", "P", "S", "Cl",
+	"Ar", "K", "Ca", "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Ga", "Ge", "As",
+	"Se", "Br", "Kr", "Rb", "Sr", "Y", "Zr", "Nb", "Mo", "Tc", "Ru", "Rh", "Pd", "Ag", "Cd", "In",
+	"Sn", "Sb", "Te", "I", "Xe", "Cs", "Ba", "La", "Ce", "Pr", "Nd", "Pm", "Sm", "Eu", "Gd", "Tb",
+	"Dy", "Ho", "Er", "Tm", "Yb", "Lu", "Hf", "Ta", "W", "Re", "Os", "Ir", "Pt", "Au", "Hg", "Tl",
+	"Pb", "Bi", "Po", "At", "Rn", "Fr", "Ra", "Ac", "Th", "Pa", "U", "Np", "Pu", "Am", "Cm", "Bk",
+	"Cf", "Es", "Fm", "Md", "No", "Lr", "Rf", "Db", "Sg", "Bh", "Hs", "Mt", "Ds", "Rg", "Cn", "Nh",
+	"Fl", "Mc", "Lv", "Ts", "Og",
+];
+
+/// Chemical element, given by its atomic number.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Element(u8);
+
+impl Element {
+	pub const HYDROGEN: Self = Self(1);
+	pub const BORON: Self = Self(5);
+	pub const CARBON: Self = Self(6);
+	pub const NITROGEN: Self = Self(7);
+	pub const OXYGEN: Self = Self(8);
+	pub const FLUORINE: Self = Self(9);
+	pub const PHOSPHORUS: Self = Self(15);
+	pub const SULFUR: Self = Self(16);
+	pub const CHLORINE: Self = Self(17);
+	pub const ARSENIC: Self = Self(33);
+	pub const SELENIUM: Self = Self(34);
+	pub const BROMINE: Self = Self(35);
+	pub const IODINE: Self = Self(53);
+
+	/// Returns the element with the given atomic number, if any.
+	pub fn new(atomic_number: u8) -> Option<Self> {
+		if (1..=SYMBOLS.len()).contains(&(atomic_number as usize)) {
+			Some(Self(atomic_number))
+		} else {
+			None
+		}
+	}
+
+	/// Returns the element with the given symbol, if any.
+	pub fn from_symbol(symbol: &str) -> Option<Self> {
+		SYMBOLS
+			.iter()
+			.position(|s| *s == symbol)
+			.map(|i| Self(i as u8 + 1))
+	}
+
+	pub fn atomic_number(self) -> u8 {
+		self.0
+	}
+
+	pub fn symbol(self) -> &'static str {
+		SYMBOLS[self.0 as usize - 1]
+	}
+
+	/// Returns the normal valences of this element if it belongs to the
+	/// organic subset, whose atoms have implicit hydrogens.
+	fn normal_valences(self) -> Option<&'static [u8]> {
+		match self {
+			Self::BORON => Some(&[3]),
+			Self::CARBON => Some(&[4]),
+			Self::NITROGEN | Self::PHOSPHORUS => Some(&[3, 5]),
+			Self::OXYGEN => Some(&[2]),
+			Self::SULFUR => Some(&[2, 4, 6]),
+			Self::FLUORINE | Self::CHLORINE | Self::BROMINE | Self::IODINE => Some(&[1]),
+			_ => None,
+		}
+	}
+
+	/// Checks if this element belongs to the organic subset.
+	pub fn is_organic(self) -> bool {
+		self.normal_valences().is_some()
+	}
+
+	/// Checks if atoms of this element can be aromatic, which SMILES writes
+	/// with a lowercase symbol.
+	pub fn can_be_aromatic(self) -> bool {
+		matches!(
+			self,
+			Self::BORON
+				| Self::CARBON
+				| Self::NITROGEN
+				| Self::OXYGEN
+				| Self::PHOSPHORUS
+				| Self::SULFUR
+				| Self::ARSENIC
+				| Self::SELENIUM
+		)
+	}
+}
+
+impl fmt::Display for Element {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.symbol().fmt(f)
+	}
+}
+
+/// Atom.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Atom {
+	pub element: Element,
+	pub charge: i8,
+
+	/// Number of hydrogens bound to the atom.
+	pub hydrogens: u8,
+
+	pub aromatic: bool,
+}
+
+impl Atom {
+	pub fn new(element: Element) -> Self {
+		Self {
+			element,
+			charge: 0,
+			hydrogens: 0,
+			aromatic: false,
+		}
+	}
+
+	/// Returns the number of implicit hydrogens of this atom given the sum
+	/// of the orders of its bonds, following the normal valences of the
+	/// organic subset, or `None` if the element is not in the organic
+	/// subset.
+	///
+	/// An aromatic atom counts one more bond. A charge shifts the valences of
+	/// the element to those of its isoelectronic neighbor.
+	fn implicit_hydrogens(&self, bond_orders: usize) -> Option<u8> {
+		let valences = self.element.normal_valences()?;
+		let used = i16::try_from(bond_orders)
+			.unwrap_or(i16::MAX)
+			.saturating_add(self.aromatic as i16);
+		let charge = self.charge as i16;
+
+		Some(
+			valences
+				.iter()
+				.map(|&v| match self.element {
+					Element::BORON => v as i16 - charge,
+					Element::CARBON => v as i16 - charge.abs(),
+					_ => v as i16 + charge,
+				})
+				.find(|&v| v >= used)
+				.map(|v| (v - used) as u8)
+				.unwrap_or(0),
+		)
+	}
+}
+
+/// Bond.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Bond {
+	Single,
+	Double,
+	Triple,
+	Aromatic,
+}
+
+impl Bond {
+	/// Bond order used to count implicit hydrogens, where aromatic bonds
+	/// count as single bonds.
+	fn order(self) -> usize {
+		match self {
+			Self::Single | Self::Aromatic => 1,
+			Self::Double => 2,
+			Self::Triple => 3,
+		}
+	}
+}
+
+/// Molecule.
+///
+/// Atoms are the permutable elements.
+#[derive(Clone, Debug)]
+pub struct Molecule {
+	graph: Multigraph<Atom, Bond>,
+}
+
+impl Molecule {
+	/// Creates a new molecule from its atoms and bonds `(a, b, bond)`.
+	///
+	/// # Panics
+	///
+	/// Panics if a bond endpoint is not an atom, if a bond binds an atom to
+	/// itself, if two bonds bind the same atoms, or if an atom is aromatic
+	/// but its element [cannot be](Element::can_be_aromatic).
+	pub fn new(atoms: Vec<Atom>, bonds: impl IntoIterator<Item = (usize, usize, Bond)>) -> Self {
+		assert!(
+			atoms
+				.iter()
+				.all(|atom| !atom.aromatic || atom.element.can_be_aromatic()),
+			"invalid aromatic atom"
+		);
+		let graph = Multigraph::new(atoms, bonds);
+		assert!(
+			graph.edges().iter().all(|&(a, b, _)| a != b)
+				&& graph
+					.edges()
+					.windows(2)
+					.all(|w| w[0].0 != w[1].0 || w[0].1 != w[1].1),
+			"molecules must be simple graphs"
+		);
+
+		Self { graph }
+	}
+
+	/// Creates a new molecule where hydrogen atoms bound to a single other
+	/// atom are removed, and counted on this other atom.
+	///
+	/// Returns the index of the atom whose hydrogen count overflows, if
+	/// any.
+	fn with_implicit_hydrogens(
+		mut atoms: Vec<Atom>,
+		bonds: Vec<(usize, usize, Bond)>,
+	) -> Result<Self, usize> {
+		let mut degrees = vec![0; atoms.len()];
+		for &(a, b, _) in &bonds {
+			degrees[a] += 1;
+			degrees[b] += 1
+		}
+
+		let is_implicit = |atoms: &[Atom], a: usize, b: usize, bond: Bond| {
+			let atom = &atoms[a];
+			atom.element == Element::HYDROGEN
+				&& atom.charge == 0
+				&& atom.hydrogens == 0
+				&& degrees[a] == 1
+				&& bond == Bond::Single
+				&& atoms[b].element != Element::HYDROGEN
+		};
+
+		let mut removed = vec![false; atoms.len()];
+		for &(a, b, bond) in &bonds {
+			for (a, b) in [(a, b), (b, a)] {
+				if is_implicit(&atoms, a, b, bond) {
+					removed[a] = true;
+					atoms[b].hydrogens = atoms[b].hydrogens.checked_add(1).ok_or(b)?
+				}
+			}
+		}
+
+		let mut indexes = Vec::with_capacity(atoms.len());
+		let mut kept = Vec::new();
+		for (atom, removed) in atoms.into_iter().zip(&removed) {
+			indexes.push(kept.len());
+			if !removed {
+				kept.push(atom)
+			}
+		}
+
+		let bonds: Vec<_> = bonds
+			.into_iter()
+			.filter(|&(a, b, _)| !removed[a] && !removed[b])
+			.map(|(a, b, bond)| (indexes[a], indexes[b], bond))
+			.collect();
+
+		Ok(Self::new(kept, bonds))
+	}
+
+	pub fn len(&self) -> usize {
+		self.graph.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.graph.is_empty()
+	}
+
+	pub fn atoms(&self) -> &[Atom] {
+		self.graph.vertices()
+	}
+
+	/// Returns the sorted bonds, with the smallest atom index first.
+	pub fn bonds(&self) -> &[(usize, usize, Bond)] {
+		self.graph.edges()
+	}
+
+	/// Returns the neighbors of each atom, with the bonds to them.
+	fn neighbors(&self) -> Vec<Vec<(usize, Bond)>> {
+		let mut neighbors = vec![Vec::new(); self.len()];
+		for &(a, b, bond) in self.bonds() {
+			neighbors[a].push((b, bond));
+			neighbors[b].push((a, bond))
+		}
+
+		neighbors
+	}
+}
+
+impl Normalize for Molecule {
+	type Elements = usize;
+	type Color = <Multigraph<Atom, Bond> as Normalize>::Color;
+	type Cache = ();
+	type Morphed = MorphedMultigraph<Atom, Bond>;
+
+	fn initialize_cache(&self) {}
+
+	fn elements(&self) -> &usize {
+		self.graph.elements()
+	}
+
+	fn initial_coloring(&self) -> Vec<Self::Color> {
+		self.graph.initial_coloring()
+	}
+
+	fn refine_coloring(&self, cache: &mut (), coloring: &mut ReversibleColoring<usize>) {
+		self.graph.refine_coloring(cache, coloring)
+	}
+
+	fn apply_morphism<F>(&self, f: F) -> MorphedMultigraph<Atom, Bond>
+	where
+		F: Fn(&usize) -> usize,
+	{
+		self.graph.apply_morphism(f)
+	}
+}
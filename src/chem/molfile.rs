@@ -0,0 +1,315 @@
+use super::{Atom, Bond, Element, Molecule};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// MDL molfile parse error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MolfileError {
+	/// The file ends before the end of the connection table.
+	UnexpectedEnd,
+
+	/// The counts line is invalid.
+	InvalidCountsLine(String),
+
+	/// Only the V2000 format is supported.
+	UnsupportedVersion(String),
+
+	/// An atom line is invalid.
+	InvalidAtom(String),
+
+	/// Unknown element symbol.
+	UnknownElement(String),
+
+	/// A bond line is invalid.
+	InvalidBond(String),
+
+	/// A property line is invalid.
+	InvalidProperty(String),
+}
+
+impl fmt::Display for MolfileError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::UnexpectedEnd => write!(f, "unexpected end of file"),
+			Self::InvalidCountsLine(line) => write!(f, "invalid counts line `{}`", line),
+			Self::UnsupportedVersion(v) => write!(f, "unsupported version `{}`", v),
+			Self::InvalidAtom(line) => write!(f, "invalid atom `{}`", line),
+			Self::UnknownElement(symbol) => write!(f, "unknown element `{}`", symbol),
+			Self::InvalidBond(line) => write!(f, "invalid bond `{}`", line),
+			Self::InvalidProperty(line) => write!(f, "invalid property `{}`", line),
+		}
+	}
+}
+
+impl std::error::Error for MolfileError {}
+
+/// Returns the trimmed fixed-width field of the given line between the
+/// `start` and `end` columns, empty if the line is too short.
+fn field(line: &str, start: usize, end: usize) -> &str {
+	line.get(start..end.min(line.len()))
+		.unwrap_or_default()
+		.trim()
+}
+
+impl Molecule {
+	/// Parses the connection table of an MDL molfile in the V2000 format.
+	///
+	/// Charges are read from the atom block and from `M  CHG` properties.
+	/// Atoms of the organic subset get their implicit hydrogens, and
+	/// explicit hydrogen atoms bound to a single atom are counted on this
+	/// atom. Bonds of type 4 are aromatic, and so are their atoms, which must
+	/// [be able to](Element::can_be_aromatic). Other
+	/// properties, coordinates and stereo flags are ignored. Bonds binding
+	/// an atom to itself, or two atoms already bound, are invalid.
+	pub fn parse_molfile(input: &str) -> Result<Self, MolfileError> {
+		let mut lines = input.lines().skip(3);
+
+		let counts = lines.next().ok_or(MolfileError::UnexpectedEnd)?;
+		let invalid_counts = || MolfileError::InvalidCountsLine(counts.to_string());
+		let version = field(counts, 34, 39);
+		if !version.is_empty() && version != "V2000" {
+			return Err(MolfileError::UnsupportedVersion(version.to_string()));
+		}
+		let atom_count: usize = field(counts, 0, 3).parse().map_err(|_| invalid_counts())?;
+		let bond_count: usize = field(counts, 3, 6).parse().map_err(|_| invalid_counts())?;
+
+		let mut atoms = Vec::with_capacity(atom_count);
+		let mut atom_lines = Vec::with_capacity(atom_count);
+		for _ in 0..atom_count {
+			let line = lines.next().ok_or(MolfileError::UnexpectedEnd)?;
+			atom_lines.push(line);
+			let symbol = field(line, 31, 34);
+			if symbol.is_empty() {
+				return Err(MolfileError::InvalidAtom(line.to_string()));
+			}
+
+			let element = Element::from_symbol(symbol)
+				.ok_or_else(|| MolfileError::UnknownElement(symbol.to_string()))?;
+			let charge = match field(line, 36, 39) {
+				"" | "0" | "4" => 0,
+				"1" => 3,
+				"2" => 2,
+				"3" => 1,
+				"5" => -1,
+				"6" => -2,
+				"7" => -3,
+				_ => return Err(MolfileError::InvalidAtom(line.to_string())),
+			};
+
+			atoms.push(Atom {
+				charge,
+				..Atom::new(element)
+			})
+		}
+
+		let mut bonds = Vec::with_capacity(bond_count);
+		let mut bonded = BTreeSet::new();
+		for _ in 0..bond_count {
+			let line = lines.next().ok_or(MolfileError::UnexpectedEnd)?;
+			let invalid = || MolfileError::InvalidBond(line.to_string());
+			let atom = |start| match field(line, start, start + 3).parse::<usize>() {
+				Ok(a) if (1..=atom_count).contains(&a) => Ok(a - 1),
+				_ => Err(invalid()),
+			};
+
+			let (a, b) = (atom(0)?, atom(3)?);
+			if a == b || !bonded.insert((a.min(b), a.max(b))) {
+				return Err(invalid());
+			}
+
+			let bond = match field(line, 6, 9) {
+				"1" => Bond::Single,
+				"2" => Bond::Double,
+				"3" => Bond::Triple,
+				"4" => {
+					if !atoms[a].element.can_be_aromatic() || !atoms[b].element.can_be_aromatic() {
+						return Err(invalid());
+					}
+
+					atoms[a].aromatic = true;
+					atoms[b].aromatic = true;
+					Bond::Aromatic
+				}
+				_ => return Err(invalid()),
+			};
+
+			bonds.push((a, b, bond))
+		}
+
+		let mut charges_reset = false;
+		for line in lines {
+			if line.starts_with("M  END") {
+				break;
+			}
+
+			if let Some(rest) = line.strip_prefix("M  CHG") {
+				let invalid = || MolfileError::InvalidProperty(line.to_string());
+				let values = rest
+					.split_whitespace()
+					.map(|v| v.parse::<i64>().map_err(|_| invalid()))
+					.collect::<Result<Vec<_>, _>>()?;
+
+				let (&count, pairs) = values.split_first().ok_or_else(invalid)?;
+				let count = usize::try_from(count).map_err(|_| invalid())?;
+				if count.checked_mul(2) != Some(pairs.len()) {
+					return Err(invalid());
+				}
+
+				// Charge properties supersede the atom block charges.
+				if !charges_reset {
+					for atom in &mut atoms {
+						atom.charge = 0
+					}
+					charges_reset = true
+				}
+
+				for pair in pairs.chunks(2) {
+					let a = pair[0] as usize;
+					if !(1..=atom_count).contains(&a) {
+						return Err(invalid());
+					}
+					atoms[a - 1].charge = i8::try_from(pair[1]).map_err(|_| invalid())?
+				}
+			}
+		}
+
+		let mut bond_orders = vec![0; atom_count];
+		for &(a, b, bond) in &bonds {
+			bond_orders[a] += bond.order();
+			bond_orders[b] += bond.order()
+		}
+
+		for (atom, bond_orders) in atoms.iter_mut().zip(bond_orders) {
+			atom.hydrogens = atom.implicit_hydrogens(bond_orders).unwrap_or(0)
+		}
+
+		Self::with_implicit_hydrogens(atoms, bonds)
+			.map_err(|a| MolfileError::InvalidAtom(atom_lines[a].to_string()))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::MolfileError;
+	use crate::chem::Molecule;
+
+	/// Acetic acid, with explicit hydrogens on the carboxyl group.
+	const ACETIC_ACID: &str = "acetic acid
+  test
+
+  5  4  0  0  0  0  0  0  0  0999 V2000
+    0.0000    0.0000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    1.2990    0.7500    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    1.2990    2.2500    0.0000 O   0  0  0  0  0  0  0  0  0  0  0  0
+    2.5981    0.0000    0.0000 O   0  0  0  0  0  0  0  0  0  0  0  0
+    3.5000    0.5000    0.0000 H   0  0  0  0  0  0  0  0  0  0  0  0
+  1  2  1  0
+  2  3  2  0
+  2  4  1  0
+  4  5  1  0
+M  END
+";
+
+	/// Ammonium chloride, with charges in properties.
+	const AMMONIUM_CHLORIDE: &str = "
+  test
+
+  2  0  0  0  0  0  0  0  0  0999 V2000
+    0.0000    0.0000    0.0000 N   0  0  0  0  0  0  0  0  0  0  0  0
+    2.0000    0.0000    0.0000 Cl  0  0  0  0  0  0  0  0  0  0  0  0
+M  CHG  2   1   1   2  -1
+M  END
+";
+
+	#[test]
+	fn parse() {
+		let m = Molecule::parse_molfile(ACETIC_ACID).unwrap();
+		assert_eq!(m.len(), 4);
+		assert_eq!(
+			m.to_canonical_smiles(),
+			Molecule::parse_smiles("CC(=O)O")
+				.unwrap()
+				.to_canonical_smiles()
+		);
+
+		let m = Molecule::parse_molfile(AMMONIUM_CHLORIDE).unwrap();
+		assert_eq!(
+			m.to_canonical_smiles(),
+			Molecule::parse_smiles("[Cl-].[NH4+]")
+				.unwrap()
+				.to_canonical_smiles()
+		);
+	}
+
+	#[test]
+	fn errors() {
+		let truncated: String = ACETIC_ACID.lines().take(6).collect::<Vec<_>>().join("\n");
+		assert_eq!(
+			Molecule::parse_molfile(&truncated).unwrap_err(),
+			MolfileError::UnexpectedEnd
+		);
+
+		let unknown = ACETIC_ACID.replace(" O   0", " Xx  0");
+		assert_eq!(
+			Molecule::parse_molfile(&unknown).unwrap_err(),
+			MolfileError::UnknownElement("Xx".to_string())
+		);
+
+		let v3000 = ACETIC_ACID.replace("V2000", "V3000");
+		assert_eq!(
+			Molecule::parse_molfile(&v3000).unwrap_err(),
+			MolfileError::UnsupportedVersion("V3000".to_string())
+		);
+
+		let bond = ACETIC_ACID.replace("  4  5  1  0", "  4  9  1  0");
+		assert!(matches!(
+			Molecule::parse_molfile(&bond).unwrap_err(),
+			MolfileError::InvalidBond(_)
+		));
+
+		let self_loop = ACETIC_ACID.replace("  4  5  1  0", "  4  4  1  0");
+		assert_eq!(
+			Molecule::parse_molfile(&self_loop).unwrap_err(),
+			MolfileError::InvalidBond("  4  4  1  0".to_string())
+		);
+
+		let parallel = ACETIC_ACID.replace("  4  5  1  0", "  2  1  1  0");
+		assert_eq!(
+			Molecule::parse_molfile(&parallel).unwrap_err(),
+			MolfileError::InvalidBond("  2  1  1  0".to_string())
+		);
+
+		let iron = ACETIC_ACID
+			.replace(" H   0", " Fe  0")
+			.replace("  4  5  1  0", "  4  5  4  0");
+		assert_eq!(
+			Molecule::parse_molfile(&iron).unwrap_err(),
+			MolfileError::InvalidBond("  4  5  4  0".to_string())
+		);
+
+		let charges = ACETIC_ACID.replace("M  END", "M  CHG -1   1   1\nM  END");
+		assert_eq!(
+			Molecule::parse_molfile(&charges).unwrap_err(),
+			MolfileError::InvalidProperty("M  CHG -1   1   1".to_string())
+		);
+
+		// A nitrogen with too many hydrogen atoms.
+		let nitrogen = "    0.0000    0.0000    0.0000 N   0  0  0  0  0  0  0  0  0  0  0  0";
+		let hydrogen = "    0.0000    0.0000    0.0000 H   0  0  0  0  0  0  0  0  0  0  0  0";
+		let mut hydrogens = "\n  test\n\n257256  0  0  0  0  0  0  0  0999 V2000\n".to_string();
+		hydrogens.push_str(nitrogen);
+		hydrogens.push('\n');
+		for _ in 0..256 {
+			hydrogens.push_str(hydrogen);
+			hydrogens.push('\n')
+		}
+		for h in 2..258 {
+			hydrogens.push_str(&format!("{:3}{:3}  1  0\n", 1, h))
+		}
+		hydrogens.push_str("M  END\n");
+		assert_eq!(
+			Molecule::parse_molfile(&hydrogens).unwrap_err(),
+			MolfileError::InvalidAtom(nitrogen.to_string())
+		);
+	}
+}
@@ -0,0 +1,760 @@
+use super::{Atom, Bond, Element, Molecule};
+use crate::Normalize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+
+/// SMILES parse error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SmilesError {
+	/// Unexpected character, at the given byte offset.
+	UnexpectedCharacter(usize, char),
+
+	/// Unsupported feature (stereochemistry, isotopes, atom classes), at the
+	/// given byte offset.
+	Unsupported(usize, char),
+
+	/// Unknown element symbol.
+	UnknownElement(String),
+
+	/// The input ends in the middle of an atom or after a bond.
+	UnexpectedEnd,
+
+	/// A ring bond is not closed.
+	UnclosedRing(u8),
+
+	/// A branch is not closed.
+	UnclosedBranch,
+
+	/// A hydrogen count or charge is out of range, at the given byte
+	/// offset, or the hydrogen count of the atom at the given byte offset
+	/// overflows when counting its hydrogen atoms.
+	OutOfRange(usize),
+}
+
+impl fmt::Display for SmilesError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::UnexpectedCharacter(i, c) => write!(f, "unexpected `{}` at offset {}", c, i),
+			Self::Unsupported(i, c) => write!(f, "unsupported `{}` at offset {}", c, i),
+			Self::UnknownElement(symbol) => write!(f, "unknown element `{}`", symbol),
+			Self::UnexpectedEnd => write!(f, "unexpected end of input"),
+			Self::UnclosedRing(n) => write!(f, "unclosed ring bond {}", n),
+			Self::UnclosedBranch => write!(f, "unclosed branch"),
+			Self::OutOfRange(i) => write!(f, "out of range number at offset {}", i),
+		}
+	}
+}
+
+impl std::error::Error for SmilesError {}
+
+/// Default bond between two atoms, when no bond symbol is given.
+fn default_bond(a: &Atom, b: &Atom) -> Bond {
+	if a.aromatic && b.aromatic {
+		Bond::Aromatic
+	} else {
+		Bond::Single
+	}
+}
+
+/// SMILES parser.
+struct Parser<'a> {
+	input: &'a str,
+	offset: usize,
+	atoms: Vec<Atom>,
+
+	/// Atoms written without brackets, whose hydrogens are implicit.
+	organic: Vec<bool>,
+
+	/// Byte offset of each atom.
+	offsets: Vec<usize>,
+
+	bonds: Vec<(usize, usize, Bond)>,
+
+	/// Bonded atom pairs, smallest atom first.
+	bonded: BTreeSet<(usize, usize)>,
+}
+
+impl<'a> Parser<'a> {
+	fn peek(&self) -> Option<char> {
+		self.input[self.offset..].chars().next()
+	}
+
+	fn next(&mut self) -> Option<char> {
+		let c = self.peek()?;
+		self.offset += c.len_utf8();
+		Some(c)
+	}
+
+	/// Parses the given element symbol, with the aromatic lowercase symbols
+	/// allowed in brackets if `bracket` is `true`.
+	fn element(symbol: &str, bracket: bool) -> Option<(Element, bool)> {
+		if symbol.starts_with(|c: char| c.is_ascii_lowercase()) {
+			if !bracket && symbol.len() > 1 {
+				return None;
+			}
+
+			let mut capitalized = symbol.to_ascii_uppercase();
+			capitalized.truncate(1);
+			capitalized.push_str(&symbol[1..]);
+			Element::from_symbol(&capitalized)
+				.filter(|e| e.can_be_aromatic())
+				.map(|e| (e, true))
+		} else {
+			Element::from_symbol(symbol).map(|e| (e, false))
+		}
+	}
+
+	/// Parses an atom of the organic subset, starting with `c`.
+	fn organic_atom(&mut self, c: char) -> Result<Atom, SmilesError> {
+		let mut symbol = c.to_string();
+		if matches!((c, self.peek()), ('B', Some('r')) | ('C', Some('l'))) {
+			symbol.extend(self.next())
+		}
+
+		match Self::element(&symbol, false) {
+			Some((element, aromatic)) if element.is_organic() => Ok(Atom {
+				aromatic,
+				..Atom::new(element)
+			}),
+			_ => Err(SmilesError::UnknownElement(symbol)),
+		}
+	}
+
+	/// Parses the number following the current offset, if any.
+	///
+	/// The digits are only consumed if they form a valid number.
+	fn number(&mut self) -> Result<Option<u8>, SmilesError> {
+		let start = self.offset;
+		let len = self.input[start..]
+			.bytes()
+			.take_while(u8::is_ascii_digit)
+			.count();
+		if len == 0 {
+			return Ok(None);
+		}
+
+		let n = self.input[start..(start + len)]
+			.parse()
+			.map_err(|_| SmilesError::OutOfRange(start))?;
+		self.offset += len;
+		Ok(Some(n))
+	}
+
+	/// Parses a bracket atom, after the opening bracket.
+	fn bracket_atom(&mut self) -> Result<Atom, SmilesError> {
+		let unsupported = |p: &Self| Err(SmilesError::Unsupported(p.offset, p.peek().unwrap()));
+
+		match self.peek() {
+			Some(c) if c.is_ascii_digit() => return unsupported(self),
+			None => return Err(SmilesError::UnexpectedEnd),
+			_ => (),
+		}
+
+		// Symbol, trying two letters first.
+		let start = self.offset;
+		self.next();
+		if self.peek().is_some_and(|c| c.is_ascii_lowercase())
+			&& Self::element(&self.input[start..self.offset + 1], true).is_some()
+		{
+			self.offset += 1
+		}
+		let symbol = &self.input[start..self.offset];
+		let (element, aromatic) = Self::element(symbol, true).ok_or_else(|| {
+			let letters = self.input[self.offset..]
+				.chars()
+				.take_while(|c| c.is_ascii_lowercase())
+				.count();
+			SmilesError::UnknownElement(self.input[start..self.offset + letters].to_string())
+		})?;
+		let mut atom = Atom {
+			aromatic,
+			..Atom::new(element)
+		};
+
+		if self.peek() == Some('@') {
+			return unsupported(self);
+		}
+
+		if self.peek() == Some('H') {
+			self.offset += 1;
+			atom.hydrogens = self.number()?.unwrap_or(1)
+		}
+
+		if let Some(sign @ ('+' | '-')) = self.peek() {
+			let start = self.offset;
+			self.offset += 1;
+			let mut charge: i8 = 1;
+			match self.number()? {
+				Some(n) => {
+					charge = i8::try_from(n).map_err(|_| SmilesError::OutOfRange(start + 1))?
+				}
+				None => {
+					while self.peek() == Some(sign) {
+						self.offset += 1;
+						charge = charge
+							.checked_add(1)
+							.ok_or(SmilesError::OutOfRange(start))?
+					}
+				}
+			}
+
+			atom.charge = if sign == '-' { -charge } else { charge }
+		}
+
+		match self.next() {
+			Some(']') => Ok(atom),
+			Some(':') => Err(SmilesError::Unsupported(self.offset - 1, ':')),
+			Some(c) => Err(SmilesError::UnexpectedCharacter(self.offset - 1, c)),
+			None => Err(SmilesError::UnexpectedEnd),
+		}
+	}
+
+	/// Parses a ring bond number, after `c`.
+	fn ring_number(&mut self, c: char) -> Result<u8, SmilesError> {
+		if c == '%' {
+			let start = self.offset;
+			for _ in 0..2 {
+				match self.next() {
+					Some(d) if d.is_ascii_digit() => (),
+					Some(d) => return Err(SmilesError::UnexpectedCharacter(self.offset - 1, d)),
+					None => return Err(SmilesError::UnexpectedEnd),
+				}
+			}
+
+			Ok(self.input[start..self.offset].parse().unwrap())
+		} else {
+			Ok(c as u8 - b'0')
+		}
+	}
+
+	fn parse(mut self) -> Result<Molecule, SmilesError> {
+		let mut previous: Option<usize> = None;
+		let mut branches = Vec::new();
+		let mut bond: Option<Bond> = None;
+		let mut rings: BTreeMap<u8, (usize, Option<Bond>)> = BTreeMap::new();
+
+		while let Some(c) = self.next() {
+			let offset = self.offset - 1;
+			let unexpected = Err(SmilesError::UnexpectedCharacter(offset, c));
+
+			let atom = match c {
+				'(' => match previous {
+					Some(a) if bond.is_none() => {
+						branches.push(a);
+						continue;
+					}
+					_ => return unexpected,
+				},
+				')' => match branches.pop() {
+					Some(a) if bond.is_none() => {
+						previous = Some(a);
+						continue;
+					}
+					_ => return unexpected,
+				},
+				'-' | '=' | '#' | ':' if previous.is_some() && bond.is_none() => {
+					bond = Some(match c {
+						'-' => Bond::Single,
+						'=' => Bond::Double,
+						'#' => Bond::Triple,
+						_ => Bond::Aromatic,
+					});
+					continue;
+				}
+				'.' if previous.is_some() && bond.is_none() => {
+					previous = None;
+					continue;
+				}
+				'0'..='9' | '%' if previous.is_some() => {
+					let a = previous.unwrap();
+					let n = self.ring_number(c)?;
+					match rings.remove(&n) {
+						Some((b, opening_bond)) => {
+							// Ring bonds cannot be self loops or parallel
+							// bonds.
+							if b == a || !self.bonded.insert((b.min(a), b.max(a))) {
+								return unexpected;
+							}
+
+							let bond = match (bond.take(), opening_bond) {
+								(Some(x), Some(y)) if x != y => return unexpected,
+								(Some(x), _) | (None, Some(x)) => x,
+								(None, None) => default_bond(&self.atoms[a], &self.atoms[b]),
+							};
+
+							self.bonds.push((b, a, bond))
+						}
+						None => {
+							rings.insert(n, (a, bond.take()));
+						}
+					}
+
+					continue;
+				}
+				'/' | '\\' => return Err(SmilesError::Unsupported(offset, c)),
+				'[' => self.bracket_atom()?,
+				c if c.is_ascii_alphabetic() => self.organic_atom(c)?,
+				_ => return unexpected,
+			};
+
+			let a = self.atoms.len();
+			self.organic.push(c != '[');
+			self.offsets.push(offset);
+			self.atoms.push(atom);
+
+			if let Some(b) = previous {
+				let bond = bond
+					.take()
+					.unwrap_or_else(|| default_bond(&self.atoms[a], &self.atoms[b]));
+				self.bonded.insert((b, a));
+				self.bonds.push((b, a, bond))
+			}
+
+			previous = Some(a)
+		}
+
+		if bond.is_some() {
+			return Err(SmilesError::UnexpectedEnd);
+		}
+
+		if let Some(&n) = rings.keys().next() {
+			return Err(SmilesError::UnclosedRing(n));
+		}
+
+		if !branches.is_empty() {
+			return Err(SmilesError::UnclosedBranch);
+		}
+
+		let mut bond_orders = vec![0; self.atoms.len()];
+		for &(a, b, bond) in &self.bonds {
+			bond_orders[a] += bond.order();
+			bond_orders[b] += bond.order()
+		}
+
+		for (a, atom) in self.atoms.iter_mut().enumerate() {
+			if self.organic[a] {
+				atom.hydrogens = atom.implicit_hydrogens(bond_orders[a]).unwrap()
+			}
+		}
+
+		let offsets = self.offsets;
+		Molecule::with_implicit_hydrogens(self.atoms, self.bonds)
+			.map_err(|a| SmilesError::OutOfRange(offsets[a]))
+	}
+}
+
+/// Ring bond digits in use.
+#[derive(Default)]
+struct RingNumbers(BTreeSet<usize>);
+
+impl RingNumbers {
+	fn allocate(&mut self) -> usize {
+		let n = (1..).find(|n| !self.0.contains(n)).unwrap();
+		self.0.insert(n);
+		n
+	}
+
+	fn release(&mut self, n: usize) {
+		self.0.remove(&n);
+	}
+}
+
+/// Step of the canonical SMILES writer.
+enum Step {
+	/// Writes an atom and pushes its children.
+	Atom(usize),
+
+	/// Writes the bond from a parent to its child, opening a branch if
+	/// needed.
+	Bond(usize, usize, Bond, bool),
+
+	/// Closes a branch.
+	CloseBranch,
+}
+
+/// Canonical SMILES writer.
+struct Writer<'a> {
+	molecule: &'a Molecule,
+
+	/// Neighbors of each atom, by increasing canonical rank.
+	neighbors: Vec<Vec<(usize, Bond)>>,
+
+	/// Canonical rank of each atom.
+	ranks: Vec<usize>,
+
+	visited: Vec<bool>,
+
+	/// Depth-first spanning forest children of each atom.
+	children: Vec<Vec<(usize, Bond)>>,
+
+	/// Ring bonds of each atom, to atoms written after it.
+	ring_openings: Vec<Vec<(usize, Bond)>>,
+
+	/// Ring bond number of the ring bonds opened at each atom, by other end.
+	ring_numbers: Vec<BTreeMap<usize, usize>>,
+
+	/// Ring bonds already assigned.
+	ring_bonds: BTreeSet<(usize, usize)>,
+
+	numbers: RingNumbers,
+	output: String,
+}
+
+impl<'a> Writer<'a> {
+	fn new(molecule: &'a Molecule) -> Self {
+		let ranks = molecule.canonical_permutation();
+		let mut neighbors = molecule.neighbors();
+		for n in &mut neighbors {
+			n.sort_unstable_by_key(|(b, _)| ranks[*b])
+		}
+
+		let len = molecule.len();
+		Self {
+			molecule,
+			neighbors,
+			ranks,
+			visited: vec![false; len],
+			children: vec![Vec::new(); len],
+			ring_openings: vec![Vec::new(); len],
+			ring_numbers: vec![BTreeMap::new(); len],
+			ring_bonds: BTreeSet::new(),
+			numbers: RingNumbers::default(),
+			output: String::new(),
+		}
+	}
+
+	/// Builds the depth-first spanning tree rooted in `root`, where
+	/// neighbors are visited by increasing canonical rank.
+	///
+	/// The search uses an explicit stack of atoms, with their parent and
+	/// the index of their next neighbor, so that long chains do not
+	/// overflow the call stack.
+	fn visit(&mut self, root: usize) {
+		self.visited[root] = true;
+		let mut stack = vec![(root, None, 0)];
+		while let Some((a, parent, next)) = stack.last_mut() {
+			let (a, parent) = (*a, *parent);
+			let Some(&(b, bond)) = self.neighbors[a].get(*next) else {
+				stack.pop();
+				continue;
+			};
+			*next += 1;
+
+			if Some(b) == parent {
+				continue;
+			}
+
+			if self.visited[b] {
+				if self.ring_bonds.insert((a.min(b), a.max(b))) {
+					self.ring_openings[b].push((a, bond))
+				}
+			} else {
+				self.visited[b] = true;
+				self.children[a].push((b, bond));
+				stack.push((b, Some(a), 0))
+			}
+		}
+	}
+
+	fn write_atom(&mut self, atom: &Atom, bond_orders: usize) {
+		let symbol = atom.element.symbol();
+		let symbol = if atom.aromatic {
+			symbol.to_ascii_lowercase()
+		} else {
+			symbol.to_string()
+		};
+
+		if atom.charge == 0 && atom.implicit_hydrogens(bond_orders) == Some(atom.hydrogens) {
+			self.output.push_str(&symbol);
+			return;
+		}
+
+		write!(self.output, "[{}", symbol).unwrap();
+		match atom.hydrogens {
+			0 => (),
+			1 => self.output.push('H'),
+			h => write!(self.output, "H{}", h).unwrap(),
+		}
+		match atom.charge {
+			0 => (),
+			1 => self.output.push('+'),
+			-1 => self.output.push('-'),
+			c => write!(self.output, "{:+}", c).unwrap(),
+		}
+		self.output.push(']')
+	}
+
+	fn write_bond(&mut self, a: usize, b: usize, bond: Bond) {
+		let atoms = self.molecule.atoms();
+		if bond != default_bond(&atoms[a], &atoms[b]) {
+			self.output.push(match bond {
+				Bond::Single => '-',
+				Bond::Double => '=',
+				Bond::Triple => '#',
+				Bond::Aromatic => ':',
+			})
+		}
+	}
+
+	fn write_ring_number(&mut self, n: usize) {
+		if n < 10 {
+			write!(self.output, "{}", n).unwrap()
+		} else {
+			write!(self.output, "%{:02}", n).unwrap()
+		}
+	}
+
+	/// Writes the atom `a` with its ring bonds.
+	fn write_ring_atom(&mut self, a: usize) {
+		let atom = self.molecule.atoms()[a];
+		let bond_orders = self.neighbors[a].iter().map(|(_, bond)| bond.order()).sum();
+		self.write_atom(&atom, bond_orders);
+
+		// Ring bonds closed here.
+		let mut closed = Vec::new();
+		for i in 0..self.neighbors[a].len() {
+			let (b, _) = self.neighbors[a][i];
+			if let Some(n) = self.ring_numbers[b].remove(&a) {
+				self.write_ring_number(n);
+				closed.push(n)
+			}
+		}
+
+		// Ring bonds opened here.
+		let mut openings = std::mem::take(&mut self.ring_openings[a]);
+		openings.sort_unstable_by_key(|(b, _)| self.ranks[*b]);
+		for (b, bond) in openings {
+			let n = self.numbers.allocate();
+			self.ring_numbers[a].insert(b, n);
+			self.write_bond(a, b, bond);
+			self.write_ring_number(n)
+		}
+
+		for n in closed {
+			self.numbers.release(n)
+		}
+	}
+
+	/// Writes the tree rooted in `root`, built by [`Self::visit`].
+	///
+	/// Every child but the last is written in a branch. Like the search,
+	/// the writer uses an explicit stack instead of recursion.
+	fn write(&mut self, root: usize) {
+		let mut stack = vec![Step::Atom(root)];
+		while let Some(step) = stack.pop() {
+			match step {
+				Step::Atom(a) => {
+					self.write_ring_atom(a);
+
+					let children = std::mem::take(&mut self.children[a]);
+					let last = children.len().saturating_sub(1);
+					for (i, (b, bond)) in children.into_iter().enumerate().rev() {
+						let branch = i < last;
+						if branch {
+							stack.push(Step::CloseBranch)
+						}
+
+						stack.push(Step::Atom(b));
+						stack.push(Step::Bond(a, b, bond, branch))
+					}
+				}
+				Step::Bond(a, b, bond, branch) => {
+					if branch {
+						self.output.push('(')
+					}
+
+					self.write_bond(a, b, bond)
+				}
+				Step::CloseBranch => self.output.push(')'),
+			}
+		}
+	}
+
+	fn finish(mut self) -> String {
+		let mut atoms: Vec<_> = (0..self.molecule.len()).collect();
+		atoms.sort_unstable_by_key(|a| self.ranks[*a]);
+
+		for a in atoms {
+			if !self.visited[a] {
+				if !self.output.is_empty() {
+					self.output.push('.')
+				}
+
+				self.visit(a);
+				self.write(a)
+			}
+		}
+
+		self.output
+	}
+}
+
+impl Molecule {
+	/// Parses a SMILES string.
+	///
+	/// Atoms of the organic subset written without brackets get their
+	/// implicit hydrogens, and explicit hydrogen atoms bound to a single atom
+	/// are counted on this atom. Stereochemistry, isotopes and atom classes
+	/// are not supported.
+	pub fn parse_smiles(input: &str) -> Result<Self, SmilesError> {
+		Parser {
+			input: input.trim(),
+			offset: 0,
+			atoms: Vec::new(),
+			organic: Vec::new(),
+			offsets: Vec::new(),
+			bonds: Vec::new(),
+			bonded: BTreeSet::new(),
+		}
+		.parse()
+	}
+
+	/// Returns the canonical SMILES of this molecule.
+	///
+	/// Atoms are written in depth-first order following the canonical
+	/// permutation, starting from the atom of lowest canonical rank in each
+	/// connected component.
+	pub fn to_canonical_smiles(&self) -> String {
+		Writer::new(self).finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::SmilesError;
+	use crate::{
+		chem::{Bond, Element, Molecule},
+		Normalize,
+	};
+
+	fn canonical(smiles: &str) -> String {
+		Molecule::parse_smiles(smiles)
+			.unwrap()
+			.to_canonical_smiles()
+	}
+
+	#[test]
+	fn parse() {
+		let m = Molecule::parse_smiles("C1=CC=CC=C1C(=O)[O-].[Na+]").unwrap();
+		assert_eq!(m.len(), 10);
+		assert_eq!(
+			m.atoms()
+				.iter()
+				.map(|a| a.hydrogens as usize)
+				.sum::<usize>(),
+			5
+		);
+		assert_eq!(
+			m.atoms().iter().map(|a| a.charge as isize).sum::<isize>(),
+			0
+		);
+		assert_eq!(m.bonds().len(), 9);
+
+		let m = Molecule::parse_smiles("[NH4+]").unwrap();
+		assert_eq!((m.atoms()[0].hydrogens, m.atoms()[0].charge), (4, 1));
+
+		let m = Molecule::parse_smiles("c1cc[nH]c1").unwrap();
+		assert!(m.bonds().iter().all(|(_, _, b)| *b == Bond::Aromatic));
+		assert_eq!(m.atoms().iter().filter(|a| a.hydrogens == 1).count(), 5);
+
+		// Explicit hydrogens.
+		let m = Molecule::parse_smiles("[H]C([H])([H])[H]").unwrap();
+		assert_eq!(m.len(), 1);
+		assert_eq!(m.atoms()[0].hydrogens, 4);
+
+		let m = Molecule::parse_smiles("ClCBr").unwrap();
+		let elements: Vec<_> = m.atoms().iter().map(|a| a.element).collect();
+		assert_eq!(
+			elements,
+			[Element::CHLORINE, Element::CARBON, Element::BROMINE]
+		);
+	}
+
+	#[test]
+	fn errors() {
+		let error = |s: &str| Molecule::parse_smiles(s).unwrap_err();
+		assert_eq!(error("C1CC"), SmilesError::UnclosedRing(1));
+		assert_eq!(error("C(C"), SmilesError::UnclosedBranch);
+		assert_eq!(error("CC="), SmilesError::UnexpectedEnd);
+		assert_eq!(error("C[C@H](O)N"), SmilesError::Unsupported(3, '@'));
+		assert_eq!(error("[13CH4]"), SmilesError::Unsupported(1, '1'));
+		assert_eq!(error("[Xy]"), SmilesError::UnknownElement("Xy".to_string()));
+		assert_eq!(error("Na"), SmilesError::UnknownElement("a".to_string()));
+		assert_eq!(error("[fe]"), SmilesError::UnknownElement("fe".to_string()));
+		assert_eq!(error("C)"), SmilesError::UnexpectedCharacter(1, ')'));
+		assert_eq!(error("[CH300]"), SmilesError::OutOfRange(3));
+		assert_eq!(error("[C+200]"), SmilesError::OutOfRange(3));
+		assert_eq!(
+			error(&format!("[C{}]", "+".repeat(128))),
+			SmilesError::OutOfRange(2)
+		);
+		assert_eq!(canonical(&format!("[C{}]", "-".repeat(127))), "[C-127]");
+		assert_eq!(error("C1C1"), SmilesError::UnexpectedCharacter(3, '1'));
+		assert_eq!(error("C12CC12"), SmilesError::UnexpectedCharacter(6, '2'));
+		assert_eq!(error("[CH255][H]"), SmilesError::OutOfRange(0));
+		assert_eq!(error("C[NH255+127][H]"), SmilesError::OutOfRange(1));
+
+		// Bond orders larger than a byte.
+		let m = Molecule::parse_smiles(&format!("C{}", "(C)".repeat(300))).unwrap();
+		assert_eq!(m.atoms()[0].hydrogens, 0);
+		assert!(m.atoms()[1..].iter().all(|atom| atom.hydrogens == 3));
+	}
+
+	#[test]
+	fn canonical_smiles() {
+		let classes: &[&[&str]] = &[
+			&["CCO", "OCC", "C(O)C", "[CH3][CH2][OH]"],
+			&["CC(=O)O", "OC(C)=O", "O=C(O)C", "C(C)(O)=O"],
+			&["c1ccccc1C", "Cc1ccccc1", "c1cc(C)ccc1"],
+			&["C1CC1C2CC2", "C1CC1C1CC1", "C2CC2C1CC1"],
+			&["[NH4+].[Cl-]", "[Cl-].[NH4+]"],
+			&["C1CCC2CCCCC2C1", "C1CCCC2C1CCCC2"],
+			&["C#N", "N#C"],
+			&["C", "[H]C([H])([H])[H]", "[CH4]"],
+		];
+
+		let mut forms = Vec::new();
+		for class in classes {
+			let form = canonical(class[0]);
+			for smiles in &class[1..] {
+				assert_eq!(canonical(smiles), form, "{}", smiles)
+			}
+
+			// The canonical SMILES is stable.
+			assert_eq!(canonical(&form), form);
+			forms.push(form)
+		}
+
+		forms.sort_unstable();
+		forms.dedup();
+		assert_eq!(forms.len(), classes.len());
+	}
+
+	#[test]
+	fn round_trip() {
+		for smiles in [
+			"C1CC2CCC1CC2",
+			"C12C3C4C1C5C2C3C45",
+			"c1ccc2ccccc2c1",
+			"OC(=O)C(N)Cc1c[nH]c2ccccc12",
+			"[O-][N+](=O)c1ccccc1",
+			"C=CC=C.C#CC#C",
+			"S(=O)(=O)(O)O",
+			"c1cc[se]c1",
+		] {
+			let m = Molecule::parse_smiles(smiles).unwrap();
+			let form = m.to_canonical_smiles();
+			let n = Molecule::parse_smiles(&form).unwrap();
+			assert_eq!(m.normal_form(), n.normal_form(), "{} -> {}", smiles, form)
+		}
+	}
+
+	#[test]
+	fn long_chain() {
+		// Deep enough to overflow the test thread stack with recursion.
+		let chain = "C".repeat(30_000);
+		let form = canonical(&chain);
+		assert_eq!(form.matches('C').count(), 30_000);
+		assert_eq!(canonical(&form), form)
+	}
+}
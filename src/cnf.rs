@@ -0,0 +1,532 @@
+//! Boolean formulas in conjunctive normal form.
+//!
+//! The permutable elements of a [`Cnf`] formula are its literals, so that
+//! automorphisms may map a variable to the negation of another, unless the
+//! formula is restricted to variable renamings with [`Cnf::without_negation`].
+//! The coloring is refined using the incidence between clauses and literals.
+//!
+//! Automorphisms can be used to add lex-leader symmetry-breaking clauses to
+//! the formula before solving it, with [`Cnf::break_symmetries`].
+//!
+//! Formulas can be read from and written to the
+//! [DIMACS](https://www.satcompetition.org/2009/format-benchmarks2009.html)
+//! format, and canonically renamed with [`Cnf::canonize`] so that formulas
+//! equal up to variable renaming (and negation, if allowed) are written
+//! identically.
+use crate::{set::Map, Normalize, ReversibleColoring};
+
+mod dimacs;
+
+pub use dimacs::ParseError;
+
+/// Literal, a variable or its negation.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Literal(usize);
+
+impl Literal {
+	pub fn new(variable: usize, negative: bool) -> Self {
+		Self(variable * 2 + negative as usize)
+	}
+
+	pub fn positive(variable: usize) -> Self {
+		Self::new(variable, false)
+	}
+
+	pub fn negative(variable: usize) -> Self {
+		Self::new(variable, true)
+	}
+
+	/// Creates a literal from its index, `2v` for the variable `v` and
+	/// `2v + 1` for its negation.
+	pub fn from_index(index: usize) -> Self {
+		Self(index)
+	}
+
+	pub fn index(self) -> usize {
+		self.0
+	}
+
+	pub fn variable(self) -> usize {
+		self.0 / 2
+	}
+
+	pub fn is_negative(self) -> bool {
+		self.0 % 2 == 1
+	}
+
+	/// Returns the negation of this literal.
+	pub fn negate(self) -> Self {
+		Self(self.0 ^ 1)
+	}
+
+	/// Returns the value of this literal under the given assignment.
+	pub fn value(self, assignment: &[bool]) -> bool {
+		assignment[self.variable()] != self.is_negative()
+	}
+}
+
+pub type Clause = Vec<Literal>;
+
+/// Formula in conjunctive normal form.
+///
+/// Literals of each clause are sorted and deduplicated, and so are the
+/// clauses.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Cnf {
+	variable_count: usize,
+
+	/// Twice the number of variables.
+	literal_count: usize,
+
+	clauses: Vec<Clause>,
+
+	/// Isomorphisms may negate variables.
+	negation: bool,
+}
+
+impl Cnf {
+	/// Creates a new formula over the variables `0..variable_count`.
+	///
+	/// # Panics
+	///
+	/// Panics if a literal refers to a variable greater or equal to
+	/// `variable_count`, or if the number of literals `2 * variable_count`
+	/// overflows.
+	pub fn new(variable_count: usize, clauses: impl IntoIterator<Item = Clause>) -> Self {
+		let literal_count = variable_count.checked_mul(2).expect("too many variables");
+		let mut clauses: Vec<_> = clauses
+			.into_iter()
+			.map(|mut clause| {
+				assert!(clause.iter().all(|l| l.variable() < variable_count));
+				clause.sort_unstable();
+				clause.dedup();
+				clause
+			})
+			.collect();
+		clauses.sort_unstable();
+		clauses.dedup();
+
+		Self {
+			variable_count,
+			literal_count,
+			clauses,
+			negation: true,
+		}
+	}
+
+	/// Restricts isomorphisms to variable renamings, without negations.
+	pub fn without_negation(mut self) -> Self {
+		self.negation = false;
+		self
+	}
+
+	/// Checks if isomorphisms may negate variables, which is the default.
+	pub fn allows_negation(&self) -> bool {
+		self.negation
+	}
+
+	pub fn variable_count(&self) -> usize {
+		self.variable_count
+	}
+
+	pub fn clauses(&self) -> &[Clause] {
+		&self.clauses
+	}
+
+	/// Checks if the given assignment of the variables satisfies the formula.
+	pub fn is_satisfied_by(&self, assignment: &[bool]) -> bool {
+		self.clauses
+			.iter()
+			.all(|clause| clause.iter().any(|l| l.value(assignment)))
+	}
+
+	/// Returns lex-leader symmetry-breaking clauses for the given literal
+	/// permutations, which must be automorphisms of this formula.
+	///
+	/// For each permutation `σ`, the clauses ensure that the assignment of
+	/// the variables, in order, is lexicographically smaller than or equal to
+	/// its image by `σ` (with `false < true`). Each permutation introduces
+	/// auxiliary variables, numbered from `variable_count`, and the total
+	/// number of variables is returned with the clauses. Every solution of
+	/// the formula has a symmetric solution satisfying those clauses.
+	pub fn lex_leader<P>(&self, permutations: &[P]) -> (usize, Vec<Clause>)
+	where
+		P: Map<usize, usize>,
+	{
+		let mut variable_count = self.variable_count;
+		let mut clauses = Vec::new();
+
+		for sigma in permutations {
+			let image = |l: Literal| Literal(*sigma.get(&l.index()).unwrap());
+
+			// Variables moved by the permutation, with their images.
+			let support: Vec<_> = (0..self.variable_count)
+				.map(Literal::positive)
+				.map(|x| (x, image(x)))
+				.filter(|(x, s)| x != s)
+				.collect();
+
+			// Literal meaning that the previous variables are equal to their
+			// images, or `None` for the first variable.
+			let mut equal: Option<Literal> = None;
+			for (i, &(x, s)) in support.iter().enumerate() {
+				let prefix: Vec<_> = equal.map(Literal::negate).into_iter().collect();
+
+				// Equal so far implies `x <= s`.
+				clauses.push(with(&prefix, [x.negate(), s]));
+
+				if s == x.negate() || i + 1 == support.len() {
+					// `x` and `s` cannot be equal, or this is the last variable.
+					break;
+				}
+
+				// Equal so far and `x = s` implies equal up to `x`.
+				let next = Literal::positive(variable_count);
+				variable_count += 1;
+				clauses.push(with(&prefix, [x.negate(), next]));
+				clauses.push(with(&prefix, [s, next]));
+				equal = Some(next)
+			}
+		}
+
+		(variable_count, clauses)
+	}
+
+	/// Computes the canonical form of this formula, with the canonical
+	/// literal of each variable.
+	///
+	/// Renaming each variable `v` with `renaming[v]` in this formula gives
+	/// the canonical formula. Canonical literals are all positive if the
+	/// formula does not allow negation.
+	pub fn canonize(&self) -> (Self, Vec<Literal>) {
+		let (morphed, permutation) = self.normalize();
+		let literals = morphed.literals();
+		let renaming = (0..self.variable_count)
+			.map(|v| literals[permutation[Literal::positive(v).index()]])
+			.collect();
+		let mut canonical = morphed.to_cnf();
+		canonical.negation = self.negation;
+		(canonical, renaming)
+	}
+
+	/// Adds lex-leader symmetry-breaking clauses for the automorphisms of
+	/// this formula found while computing its canonical form.
+	///
+	/// The resulting formula is satisfiable if and only if this formula is,
+	/// and its solutions restricted to the original variables are solutions
+	/// of this formula.
+	pub fn break_symmetries(&self) -> Self {
+		let (variable_count, clauses) = self.lex_leader(&self.automorphisms());
+		let mut result = Self::new(variable_count, self.clauses.iter().cloned().chain(clauses));
+		result.negation = self.negation;
+		result
+	}
+}
+
+/// Builds a clause from the given literals.
+fn with<const N: usize>(prefix: &[Literal], literals: [Literal; N]) -> Clause {
+	prefix.iter().copied().chain(literals).collect()
+}
+
+/// Canonical form of a [`Cnf`] formula.
+///
+/// Literals are identified by their canonical index, and the canonical
+/// indexes of each literal and its negation are given by `complements`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct MorphedCnf {
+	/// Sorted pairs of complementary literals.
+	pub complements: Vec<(usize, usize)>,
+
+	/// Sorted clauses.
+	pub clauses: Vec<Vec<usize>>,
+}
+
+impl MorphedCnf {
+	/// Converts this canonical form into a formula.
+	///
+	/// The `i`-th complement pair becomes the `i`-th variable, positive for
+	/// the smallest literal index of the pair.
+	pub fn to_cnf(&self) -> Cnf {
+		let literals = self.literals();
+		Cnf::new(
+			self.complements.len(),
+			self.clauses
+				.iter()
+				.map(|clause| clause.iter().map(|&l| literals[l]).collect()),
+		)
+	}
+
+	/// Returns the literal of [`Self::to_cnf`] for each canonical literal
+	/// index.
+	fn literals(&self) -> Vec<Literal> {
+		let mut literals = vec![Literal(0); self.complements.len() * 2];
+		for (v, &(a, b)) in self.complements.iter().enumerate() {
+			literals[a] = Literal::positive(v);
+			literals[b] = Literal::negative(v)
+		}
+		literals
+	}
+}
+
+pub struct Cache {
+	/// Clauses containing each literal.
+	occurrences: Vec<Vec<usize>>,
+}
+
+impl Normalize for Cnf {
+	type Elements = usize;
+
+	/// Sign of each literal if negation is not allowed, and number of
+	/// occurrences.
+	type Color = (bool, usize);
+
+	type Cache = Cache;
+
+	type Morphed = MorphedCnf;
+
+	fn initialize_cache(&self) -> Cache {
+		let mut occurrences = vec![Vec::new(); self.literal_count];
+		for (i, clause) in self.clauses.iter().enumerate() {
+			for l in clause {
+				occurrences[l.index()].push(i)
+			}
+		}
+
+		Cache { occurrences }
+	}
+
+	fn elements(&self) -> &usize {
+		&self.literal_count
+	}
+
+	/// Without negation, positive literals come first so that they keep
+	/// the smallest canonical index of their variable, and stay positive in
+	/// the canonical formula.
+	fn initial_coloring(&self) -> Vec<(bool, usize)> {
+		let mut colors: Vec<_> = (0..self.literal_count)
+			.map(|l| (!self.negation && Literal::from_index(l).is_negative(), 0))
+			.collect();
+		for l in self.clauses.iter().flatten() {
+			colors[l.index()].1 += 1
+		}
+		colors
+	}
+
+	/// Refines the color of each literal by the color of its negation and the
+	/// colors of the clauses containing it, where the color of a clause is
+	/// the multiset of the colors of its literals, until it is stable.
+	fn refine_coloring(&self, cache: &mut Cache, coloring: &mut ReversibleColoring<usize>) {
+		loop {
+			let clause_colors: Vec<Vec<usize>> = self
+				.clauses
+				.iter()
+				.map(|clause| {
+					let mut colors: Vec<_> = clause
+						.iter()
+						.map(|l| coloring.cell_start(&l.index()).unwrap())
+						.collect();
+					colors.sort_unstable();
+					colors
+				})
+				.collect();
+
+			let complements: Vec<_> = (0..self.literal_count)
+				.map(|l| coloring.cell_start(&(l ^ 1)).unwrap())
+				.collect();
+
+			let refined = coloring.refine(|&l| {
+				let mut colors: Vec<_> = cache.occurrences[l]
+					.iter()
+					.map(|&c| &clause_colors[c])
+					.collect();
+				colors.sort_unstable();
+				(complements[l], colors)
+			});
+
+			if !refined {
+				break;
+			}
+		}
+	}
+
+	fn apply_morphism<F>(&self, f: F) -> MorphedCnf
+	where
+		F: Fn(&usize) -> usize,
+	{
+		let mut complements: Vec<_> = (0..self.variable_count)
+			.map(|v| {
+				let (a, b) = (f(&(v * 2)), f(&(v * 2 + 1)));
+				(a.min(b), a.max(b))
+			})
+			.collect();
+		complements.sort_unstable();
+
+		let mut clauses: Vec<Vec<_>> = self
+			.clauses
+			.iter()
+			.map(|clause| {
+				let mut clause: Vec<_> = clause.iter().map(|l| f(&l.index())).collect();
+				clause.sort_unstable();
+				clause
+			})
+			.collect();
+		clauses.sort_unstable();
+
+		MorphedCnf {
+			complements,
+			clauses,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Clause, Cnf, Literal};
+	use crate::Normalize;
+
+	/// Pigeonhole formula: each pigeon is in a hole, and no two pigeons are
+	/// in the same hole. Variable `p * holes + h` means that the pigeon `p`
+	/// is in the hole `h`.
+	fn pigeonhole(pigeons: usize, holes: usize) -> Cnf {
+		let var = |p: usize, h: usize| p * holes + h;
+		let mut clauses: Vec<Clause> = (0..pigeons)
+			.map(|p| (0..holes).map(|h| Literal::positive(var(p, h))).collect())
+			.collect();
+		for h in 0..holes {
+			for p in 0..pigeons {
+				for q in (p + 1)..pigeons {
+					clauses.push(vec![
+						Literal::negative(var(p, h)),
+						Literal::negative(var(q, h)),
+					])
+				}
+			}
+		}
+
+		Cnf::new(pigeons * holes, clauses)
+	}
+
+	#[test]
+	fn group_order() {
+		assert_eq!(
+			pigeonhole(3, 2).automorphism_group_order().to_u128(),
+			Some(12)
+		);
+
+		// Swapping the variables, and negating both.
+		let cnf = Cnf::new(
+			2,
+			[
+				vec![Literal::positive(0), Literal::positive(1)],
+				vec![Literal::negative(0), Literal::negative(1)],
+			],
+		);
+		assert_eq!(cnf.automorphism_group_order().to_u128(), Some(4));
+		assert_eq!(
+			cnf.without_negation().automorphism_group_order().to_u128(),
+			Some(2)
+		)
+	}
+
+	#[test]
+	fn normal_form() {
+		let a = pigeonhole(3, 3);
+
+		// Renames variables and flips some of them.
+		let rename = |l: Literal| {
+			Literal::new(
+				(l.variable() * 4) % 9,
+				l.is_negative() != (l.variable() < 4),
+			)
+		};
+		let b = Cnf::new(
+			9,
+			a.clauses()
+				.iter()
+				.map(|clause| clause.iter().map(|&l| rename(l)).collect()),
+		);
+
+		assert_ne!(a, b);
+		assert_eq!(a.normal_form(), b.normal_form());
+		assert_eq!(a.normal_form().to_cnf().normal_form(), a.normal_form());
+		assert_ne!(a.normal_form(), pigeonhole(3, 2).normal_form());
+
+		// Flipped variables are not renamings.
+		let (a, b) = (a.without_negation(), b.without_negation());
+		assert_ne!(a.normal_form(), b.normal_form());
+		let (canonical, renaming) = b.canonize();
+		assert!(renaming.iter().all(|l| !l.is_negative()));
+		assert!(!canonical.allows_negation());
+		let negative = |cnf: &Cnf| {
+			cnf.clauses()
+				.iter()
+				.flatten()
+				.filter(|l| l.is_negative())
+				.count()
+		};
+		assert_eq!(negative(&canonical), negative(&b))
+	}
+
+	/// Checks if the given assignment of the original variables can be
+	/// extended to the auxiliary variables of the lex-leader clauses.
+	///
+	/// Auxiliary variables only appear positively in clauses forcing them,
+	/// so it is enough to check the least assignment satisfying those.
+	fn is_extensible(cnf: &Cnf, assignment: &[bool]) -> bool {
+		let mut assignment = assignment.to_vec();
+		let original_count = assignment.len();
+		assignment.resize(cnf.variable_count(), false);
+
+		loop {
+			let forced = cnf.clauses().iter().find_map(|clause| {
+				if clause.iter().any(|l| l.value(&assignment)) {
+					None
+				} else {
+					clause
+						.iter()
+						.find(|l| !l.is_negative() && l.variable() >= original_count)
+				}
+			});
+
+			match forced {
+				Some(l) => assignment[l.variable()] = true,
+				None => break cnf.is_satisfied_by(&assignment),
+			}
+		}
+	}
+
+	#[test]
+	fn lex_leader() {
+		for cnf in [pigeonhole(3, 3), pigeonhole(2, 3)] {
+			let n = cnf.variable_count();
+			let broken = cnf.break_symmetries();
+			let group = cnf.automorphism_group();
+
+			let mut solutions = 0;
+			let mut broken_solutions = 0;
+			for bits in 0..(1u32 << n) {
+				let s: Vec<_> = (0..n).map(|v| bits & (1 << v) != 0).collect();
+				if is_extensible(&broken, &s) {
+					assert!(cnf.is_satisfied_by(&s));
+					broken_solutions += 1
+				}
+
+				if cnf.is_satisfied_by(&s) {
+					solutions += 1;
+
+					// Some symmetric solution satisfies the lex-leader clauses.
+					assert!(group.elements().any(|g| {
+						let mut t = vec![false; n];
+						for (v, &value) in s.iter().enumerate() {
+							let l = Literal::from_index(g[Literal::positive(v).index()]);
+							t[l.variable()] = value != l.is_negative()
+						}
+						is_extensible(&broken, &t)
+					}))
+				}
+			}
+
+			assert!(broken_solutions < solutions)
+		}
+	}
+}
@@ -0,0 +1,237 @@
+use super::{Clause, Cnf, Literal};
+use std::fmt::{self, Write};
+
+/// DIMACS parse error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+	/// The `p cnf <variables> <clauses>` header is missing.
+	MissingHeader,
+
+	/// The header is invalid.
+	InvalidHeader(String),
+
+	/// A literal is not an integer.
+	InvalidLiteral(String),
+
+	/// A literal refers to a variable not declared in the header.
+	VariableOutOfRange(i64),
+
+	/// The last clause is not terminated by `0`.
+	UnterminatedClause,
+
+	/// The number of clauses differs from the header.
+	ClauseCount { expected: usize, found: usize },
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::MissingHeader => write!(f, "missing `p cnf` header"),
+			Self::InvalidHeader(line) => write!(f, "invalid header `{}`", line),
+			Self::InvalidLiteral(token) => write!(f, "invalid literal `{}`", token),
+			Self::VariableOutOfRange(l) => write!(f, "literal `{}` out of range", l),
+			Self::UnterminatedClause => write!(f, "unterminated clause"),
+			Self::ClauseCount { expected, found } => {
+				write!(f, "expected {} clauses, found {}", expected, found)
+			}
+		}
+	}
+}
+
+impl std::error::Error for ParseError {}
+
+impl Literal {
+	/// Returns the DIMACS representation of this literal, the 1-based
+	/// variable index, negated for negative literals.
+	pub fn to_dimacs(self) -> i64 {
+		let v = self.variable() as i64 + 1;
+		if self.is_negative() {
+			-v
+		} else {
+			v
+		}
+	}
+}
+
+impl Cnf {
+	/// Largest variable count accepted in a DIMACS header.
+	///
+	/// Every literal of the formula is an element of the search, even when
+	/// it occurs in no clause, so larger headers are rejected before
+	/// allocating anything for them.
+	pub const MAX_DIMACS_VARIABLE_COUNT: usize = 1 << 24;
+
+	/// Parses a formula in the DIMACS format.
+	///
+	/// Comment lines start with `c`, and a line starting with `%` ends the
+	/// formula. The clause count of the header is the number of clauses
+	/// listed, including duplicates, which are then removed. Variable counts
+	/// greater than [`Self::MAX_DIMACS_VARIABLE_COUNT`] are rejected as
+	/// invalid headers.
+	pub fn parse_dimacs(input: &str) -> Result<Self, ParseError> {
+		let mut header = None;
+		let mut clauses = Vec::new();
+		let mut clause: Clause = Vec::new();
+
+		for line in input.lines() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('c') {
+				continue;
+			}
+
+			if line.starts_with('%') {
+				break;
+			}
+
+			if line.starts_with('p') {
+				let invalid = || ParseError::InvalidHeader(line.to_string());
+				let fields: Vec<_> = line.split_whitespace().collect();
+				match fields.as_slice() {
+					["p", "cnf", v, c] if header.is_none() => {
+						let v: usize = v.parse().map_err(|_| invalid())?;
+						if v > Self::MAX_DIMACS_VARIABLE_COUNT {
+							return Err(invalid());
+						}
+
+						let c: usize = c.parse().map_err(|_| invalid())?;
+						header = Some((v, c))
+					}
+					_ => return Err(invalid()),
+				}
+
+				continue;
+			}
+
+			let (variable_count, _) = header.ok_or(ParseError::MissingHeader)?;
+			for token in line.split_whitespace() {
+				let l: i64 = token
+					.parse()
+					.map_err(|_| ParseError::InvalidLiteral(token.to_string()))?;
+
+				if l == 0 {
+					clauses.push(std::mem::take(&mut clause))
+				} else {
+					let v = l.unsigned_abs() as usize;
+					if v > variable_count {
+						return Err(ParseError::VariableOutOfRange(l));
+					}
+
+					clause.push(Literal::new(v - 1, l < 0))
+				}
+			}
+		}
+
+		let (variable_count, clause_count) = header.ok_or(ParseError::MissingHeader)?;
+		if !clause.is_empty() {
+			return Err(ParseError::UnterminatedClause);
+		}
+
+		if clauses.len() != clause_count {
+			return Err(ParseError::ClauseCount {
+				expected: clause_count,
+				found: clauses.len(),
+			});
+		}
+
+		Ok(Self::new(variable_count, clauses))
+	}
+
+	/// Writes this formula in the DIMACS format.
+	///
+	/// Clauses are listed without duplicates, and the header gives their
+	/// number, so that the result parses back to the same formula.
+	pub fn to_dimacs(&self) -> String {
+		let mut result = format!("p cnf {} {}\n", self.variable_count, self.clauses.len());
+		for clause in &self.clauses {
+			for l in clause {
+				write!(result, "{} ", l.to_dimacs()).unwrap()
+			}
+
+			result.push_str("0\n")
+		}
+
+		result
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Cnf, ParseError};
+
+	#[test]
+	fn parse() {
+		let cnf = Cnf::parse_dimacs("c example\np cnf 3 2\n1 -3 0\n2 3\n-1 0\n").unwrap();
+		assert_eq!(cnf.variable_count(), 3);
+		assert_eq!(cnf.to_dimacs(), "p cnf 3 2\n1 -3 0\n-1 2 3 0\n");
+		assert_eq!(Cnf::parse_dimacs(&cnf.to_dimacs()), Ok(cnf));
+
+		// Duplicate clauses are counted by the header, but not written.
+		let cnf = Cnf::parse_dimacs("p cnf 2 3\n1 2 0\n2 1 0\n-1 0\n").unwrap();
+		assert_eq!(cnf.to_dimacs(), "p cnf 2 2\n1 2 0\n-1 0\n");
+		assert_eq!(Cnf::parse_dimacs(&cnf.to_dimacs()), Ok(cnf));
+	}
+
+	#[test]
+	fn errors() {
+		assert_eq!(Cnf::parse_dimacs("1 2 0\n"), Err(ParseError::MissingHeader));
+		assert_eq!(
+			Cnf::parse_dimacs("p cnf 2 1\n1 3 0\n"),
+			Err(ParseError::VariableOutOfRange(3))
+		);
+		assert_eq!(
+			Cnf::parse_dimacs("p cnf 2 1\n1 2\n"),
+			Err(ParseError::UnterminatedClause)
+		);
+		assert_eq!(
+			Cnf::parse_dimacs("p cnf 2 2\n1 2 0\n"),
+			Err(ParseError::ClauseCount {
+				expected: 2,
+				found: 1
+			})
+		);
+		assert_eq!(
+			Cnf::parse_dimacs("p cnf 18446744073709551615 0\n"),
+			Err(ParseError::InvalidHeader(
+				"p cnf 18446744073709551615 0".to_string()
+			))
+		);
+		assert_eq!(
+			Cnf::parse_dimacs("p cnf 1000000000000 0\n"),
+			Err(ParseError::InvalidHeader(
+				"p cnf 1000000000000 0".to_string()
+			))
+		);
+	}
+
+	#[test]
+	fn canonical_renaming() {
+		let a = Cnf::parse_dimacs("p cnf 3 3\n1 2 0\n-2 3 0\n-1 -3 0\n").unwrap();
+		let b = Cnf::parse_dimacs("p cnf 3 3\n-3 1 0\n-1 -2 0\n3 2 0\n").unwrap();
+		let (canonical, renaming) = a.canonize();
+		assert_eq!(canonical.to_dimacs(), b.canonize().0.to_dimacs());
+
+		let renamed = Cnf::new(
+			3,
+			a.clauses().iter().map(|clause| {
+				clause
+					.iter()
+					.map(|l| {
+						let r = renaming[l.variable()];
+						if l.is_negative() {
+							r.negate()
+						} else {
+							r
+						}
+					})
+					.collect()
+			}),
+		);
+		assert_eq!(renamed, canonical);
+
+		// `b` is also a renaming of `a`, without negation.
+		let (a, b) = (a.without_negation(), b.without_negation());
+		let (canonical, renaming) = a.canonize();
+		assert_eq!(canonical.to_dimacs(), b.canonize().0.to_dimacs());
+		assert!(renaming.iter().all(|l| !l.is_negative()))
+	}
+}
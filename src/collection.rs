@@ -0,0 +1,328 @@
+//! Canonical forms of collections of structures.
+//!
+//! A collection of structures can be canonized in two ways:
+//! - independently, when each structure has its own elements, like the
+//!   connected components of a graph. The collection is then a multiset, and
+//!   its canonical form is the sorted list of the canonical forms of its
+//!   structures, computed one by one with [`normal_form`] or [`normalize`].
+//!   This is much faster than canonizing the whole collection at once, since
+//!   the search trees of the structures are not multiplied together.
+//! - simultaneously, when the structures share the same elements, permuted
+//!   by the same permutation. The collection is then a tuple, canonized with
+//!   the [`Simultaneous`] wrapper.
+//!
+//! Structures whose elements are related by a neighbor relation can be
+//! automatically split into connected components canonized independently,
+//! by implementing the [`Decompose`] trait.
+use crate::{find, union, GroupOrder, Map, Normalize, ReversibleColoring, Set};
+
+/// Computes the canonical form of a multiset of structures.
+///
+/// This is the sorted list of the canonical forms of the structures.
+pub fn normal_form<T: Normalize>(items: &[T]) -> Vec<T::Morphed>
+where
+	<T::Elements as Set>::Map<usize>: Clone,
+{
+	normalize(items).0
+}
+
+/// Index of a structure in the canonical form of a multiset, with its
+/// canonical permutation.
+pub type Labeling<T> = (usize, <<T as Normalize>::Elements as Set>::Map<usize>);
+
+/// Computes the canonical form of a multiset of structures, with the
+/// associated permutations.
+///
+/// For each input structure, returns its index in the canonical form and its
+/// canonical permutation. Isomorphic structures keep their input order.
+pub fn normalize<T: Normalize>(items: &[T]) -> (Vec<T::Morphed>, Vec<Labeling<T>>)
+where
+	<T::Elements as Set>::Map<usize>: Clone,
+{
+	let mut normalized: Vec<_> = items
+		.iter()
+		.enumerate()
+		.map(|(i, item)| {
+			let (morphed, permutation) = item.normalize();
+			(morphed, i, permutation)
+		})
+		.collect();
+	normalized.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+
+	let mut permutations: Vec<_> = (0..items.len()).map(|_| None).collect();
+	let mut normal_forms = Vec::with_capacity(items.len());
+	for (index, (morphed, i, permutation)) in normalized.into_iter().enumerate() {
+		normal_forms.push(morphed);
+		permutations[i] = Some((index, permutation))
+	}
+
+	(
+		normal_forms,
+		permutations.into_iter().map(Option::unwrap).collect(),
+	)
+}
+
+/// Computes the order of the automorphism group of a multiset of structures.
+///
+/// It includes the automorphisms of each structure, and the permutations of
+/// isomorphic structures.
+pub fn automorphism_group_order<T: Normalize>(items: &[T]) -> GroupOrder
+where
+	<T::Elements as Set>::Map<usize>: Clone,
+{
+	let mut normal_forms: Vec<_> = items
+		.iter()
+		.enumerate()
+		.map(|(i, item)| (item.normal_form(), i))
+		.collect();
+	normal_forms.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+	let mut order = GroupOrder::trivial();
+	let mut start = 0;
+	while start < normal_forms.len() {
+		let (normal_form, representative) = &normal_forms[start];
+		let end = start
+			+ normal_forms[start..]
+				.iter()
+				.take_while(|(morphed, _)| morphed == normal_form)
+				.count();
+
+		// Isomorphic structures have the same automorphism group order.
+		let class_order = items[*representative].automorphism_group_order();
+		for _ in start..end {
+			for &f in class_order.factors() {
+				order.push(f)
+			}
+		}
+
+		// Isomorphic structures can be permuted in `(end - start)!` ways.
+		for f in 2..=(end - start) {
+			order.push(f)
+		}
+
+		start = end
+	}
+
+	order
+}
+
+/// Structure that can be split into connected components.
+///
+/// When the neighbor relation of a structure is disconnected, its
+/// automorphism group is a product and its canonical form is the multiset
+/// of the canonical forms of its components. Canonizing the components
+/// independently avoids multiplying their search trees, in particular for
+/// isomorphic components.
+pub trait Decompose: Normalize {
+	/// Returns the neighbors of the given element.
+	///
+	/// This is typically the relation used to make the coloring equitable in
+	/// [`Normalize::refine_coloring`]. Elements that are not (transitively)
+	/// related must be independent in the structure.
+	fn neighbors(&self, item: &<Self::Elements as Set>::Item)
+		-> Vec<<Self::Elements as Set>::Item>;
+
+	/// Returns the sub-structure induced by the given elements, sorted.
+	///
+	/// The elements of the sub-structure, in iteration order, must
+	/// correspond to `items`, in order.
+	fn component(&self, items: &[<Self::Elements as Set>::Item]) -> Self;
+
+	/// Returns the connected components of this structure, each one given by
+	/// its sorted elements, ordered by their first element.
+	fn components(&self) -> Vec<Vec<<Self::Elements as Set>::Item>> {
+		let elements = self.elements();
+		let mut forest = elements.map(|x| x.clone());
+		for x in elements.iter() {
+			for y in self.neighbors(&x) {
+				union::<Self::Elements>(&mut forest, &x, &y)
+			}
+		}
+
+		// Roots are the smallest elements of their component.
+		let mut components: Vec<Vec<_>> = Vec::new();
+		let mut indexes = elements.map(|_| usize::MAX);
+		for x in elements.iter() {
+			let root = find::<Self::Elements>(&mut forest, &x);
+			if root == x {
+				indexes.set(&x, components.len());
+				components.push(vec![x])
+			} else {
+				components[*indexes.get(&root).unwrap()].push(x)
+			}
+		}
+
+		components
+	}
+
+	/// Computes the canonical form of this structure, as the sorted canonical
+	/// forms of its components.
+	fn decomposed_normal_form(&self) -> Vec<Self::Morphed>
+	where
+		<Self::Elements as Set>::Map<usize>: Clone,
+	{
+		normal_form(&self.component_structures())
+	}
+
+	/// Computes the canonical form of this structure, as the sorted canonical
+	/// forms of its components, with the associated permutation.
+	///
+	/// Each element is mapped to its canonical index in its component, offset
+	/// by the size of the previous components in the canonical form.
+	fn normalize_decomposed(&self) -> (Vec<Self::Morphed>, <Self::Elements as Set>::Map<usize>)
+	where
+		<Self::Elements as Set>::Map<usize>: Clone,
+	{
+		let components = self.components();
+		let structures: Vec<_> = components.iter().map(|c| self.component(c)).collect();
+		let (normal_forms, labelings) = normalize(&structures);
+
+		let mut offsets = vec![0; components.len()];
+		for (component, (index, _)) in components.iter().zip(&labelings) {
+			if index + 1 < offsets.len() {
+				offsets[index + 1] = component.len()
+			}
+		}
+		for i in 1..offsets.len() {
+			offsets[i] += offsets[i - 1]
+		}
+
+		let mut permutation = self.elements().map(|_| 0);
+		for ((component, structure), (index, labeling)) in
+			components.iter().zip(&structures).zip(&labelings)
+		{
+			for (x, y) in component.iter().zip(structure.elements().iter()) {
+				permutation.set(x, offsets[*index] + labeling.get(&y).unwrap())
+			}
+		}
+
+		(normal_forms, permutation)
+	}
+
+	/// Computes the order of the automorphism group of this structure from
+	/// its components.
+	fn decomposed_automorphism_group_order(&self) -> GroupOrder
+	where
+		<Self::Elements as Set>::Map<usize>: Clone,
+	{
+		automorphism_group_order(&self.component_structures())
+	}
+
+	/// Returns the sub-structure of each connected component.
+	fn component_structures(&self) -> Vec<Self> {
+		self.components()
+			.iter()
+			.map(|component| self.component(component))
+			.collect()
+	}
+}
+
+/// Tuple of structures sharing the same elements, permuted simultaneously.
+///
+/// The canonical form is the list of the canonical forms of the structures,
+/// in order, under the same permutation.
+pub struct Simultaneous<T: Normalize> {
+	elements: T::Elements,
+	items: Vec<T>,
+}
+
+impl<T: Normalize> Simultaneous<T> {
+	/// Creates a new tuple of structures.
+	///
+	/// Every structure must have the given `elements`.
+	pub fn new(elements: T::Elements, items: Vec<T>) -> Self {
+		Self { elements, items }
+	}
+
+	pub fn items(&self) -> &[T] {
+		&self.items
+	}
+
+	pub fn into_items(self) -> Vec<T> {
+		self.items
+	}
+}
+
+impl<T: Normalize> Normalize for Simultaneous<T>
+where
+	T::Color: Clone,
+{
+	type Elements = T::Elements;
+
+	/// Initial color of the element in each structure.
+	type Color = Vec<T::Color>;
+
+	type Cache = Vec<T::Cache>;
+
+	type Morphed = Vec<T::Morphed>;
+
+	fn initialize_cache(&self) -> Self::Cache {
+		self.items.iter().map(T::initialize_cache).collect()
+	}
+
+	fn elements(&self) -> &Self::Elements {
+		&self.elements
+	}
+
+	fn initial_coloring(&self) -> <Self::Elements as Set>::Map<Self::Color> {
+		let colorings: Vec<_> = self.items.iter().map(T::initial_coloring).collect();
+		self.elements.map(|x| {
+			colorings
+				.iter()
+				.map(|coloring| coloring.get(x).unwrap().clone())
+				.collect()
+		})
+	}
+
+	/// Refines the coloring with each structure until it is stable.
+	fn refine_coloring(
+		&self,
+		cache: &mut Self::Cache,
+		coloring: &mut ReversibleColoring<Self::Elements>,
+	) {
+		loop {
+			let len = coloring.len();
+			for (item, cache) in self.items.iter().zip(cache.iter_mut()) {
+				item.refine_coloring(cache, coloring)
+			}
+
+			if coloring.len() == len || self.items.len() < 2 {
+				break;
+			}
+		}
+	}
+
+	fn apply_morphism<F>(&self, morphism: F) -> Self::Morphed
+	where
+		F: Fn(&<Self::Elements as Set>::Item) -> usize,
+	{
+		self.items
+			.iter()
+			.map(|item| item.apply_morphism(&morphism))
+			.collect()
+	}
+
+	/// Compares the structures one by one, stopping at the first difference.
+	/// Only the structures compared without [`Normalize::cmp_morphism`] are
+	/// built.
+	fn cmp_morphism<F>(
+		&self,
+		cache: &mut Self::Cache,
+		morphism: F,
+		other: &Self::Morphed,
+	) -> Option<std::cmp::Ordering>
+	where
+		F: Fn(&<Self::Elements as Set>::Item) -> usize,
+	{
+		for ((item, cache), other) in self.items.iter().zip(cache.iter_mut()).zip(other) {
+			let ordering = item
+				.cmp_morphism(cache, &morphism, other)
+				.unwrap_or_else(|| item.apply_morphism(&morphism).cmp(other));
+			if ordering.is_ne() {
+				return Some(ordering);
+			}
+		}
+
+		Some(self.items.len().cmp(&other.len()))
+	}
+}
@@ -3,182 +3,154 @@ use derivative::Derivative;
 use std::fmt;
 use std::ops::Deref;
 
-#[derive(Clone, Copy)]
-pub struct Bound {
-	pub offset: usize,
-	pub depth: usize,
-}
-
-impl PartialEq for Bound {
-	fn eq(&self, other: &Self) -> bool {
-		self.offset == other.offset
-	}
-}
-
-impl Eq for Bound {}
-
-impl Bound {
-	fn new(offset: usize) -> Self {
-		Self { offset, depth: 0 }
-	}
-}
+#[cfg(feature = "serde")]
+mod serde;
 
 /// Coloring.
 ///
 /// A coloring for the set `S` is a list `(W_1, W_2, ..., W_m)` such
 /// that `{ W_1, W_2, ..., W_m }` is a coloring of `S`.
 /// Each `W_i` is called a *cell* of the coloring.
+///
+/// Cells are stored contiguously in a single list of elements, and each cell
+/// is identified by its *start*, the position of its first element in this
+/// list. The cell starts are ordered like the cells.
 #[derive(Derivative)]
 #[derivative(Clone(bound = ""), PartialEq(bound = ""), Eq(bound = ""))]
 pub struct Coloring<S: Set + ?Sized> {
 	/// Elements of the partitioned set,
-	/// ordered by cell index first, `S::Item::cmp` second (in each cell).
-	elements: Vec<S::Item>,
+	/// ordered by cell first, `S::Item::cmp` second (in each cell).
+	elements: S::List<S::Item>,
 
-	/// Bounds of the coloring.
-	///
-	/// If the partitioned set is { e_1, ..., e_n } then
-	/// the list of bounds [ i_1, ..., i_k ] produces the coloring
-	/// { { e_0, ..., e_{i_0} }, { e_{i_0 + 1}, .., e_{i_1}  }, ..., { e_{i_k + 1}, .. e_n } }
+	/// Cell ends.
 	///
-	/// The resulting coloring contains `k` members, the length of the list.
-	bounds: Vec<Bound>,
+	/// For each position `p` starting a cell, `ends[p]` is the position
+	/// following the last element of the cell.
+	/// For any other position, `ends[p]` is `0`.
+	ends: S::List<usize>,
+
+	/// Number of cells.
+	len: usize,
 }
 
 impl<S: Set + ?Sized> Coloring<S> {
 	/// Creates a new unit coloring of the input `set`.
 	pub fn new(set: &S) -> Self {
-		let mut elements: Vec<_> = set.iter().collect();
-		elements.sort_unstable();
+		let mut elements: S::List<S::Item> = set.iter().collect();
+		elements.as_mut().sort_unstable();
+		Self::from_sorted_cells(elements, |_, _| false)
+	}
+
+	/// Creates a new coloring from its elements, sorted in each cell.
+	///
+	/// A new cell starts at each position `p > 0` such that
+	/// `is_start(elements, p)` is `true`.
+	fn from_sorted_cells<F>(elements: S::List<S::Item>, is_start: F) -> Self
+	where
+		F: Fn(&[S::Item], usize) -> bool,
+	{
+		let n = elements.as_ref().len();
+		let mut ends: S::List<usize> = (0..n).map(|_| 0).collect();
+		let mut len = 0;
+
+		let mut start = 0;
+		for p in 1..=n {
+			if p == n || is_start(elements.as_ref(), p) {
+				ends.as_mut()[start] = p;
+				start = p;
+				len += 1
+			}
+		}
+
 		Self {
 			elements,
-			bounds: Vec::new(),
+			ends,
+			len,
 		}
 	}
 
 	#[cfg(test)]
-	fn from_parts(elements: Vec<S::Item>, bounds: Vec<Bound>) -> Self {
-		Self { elements, bounds }
+	fn from_parts(elements: Vec<S::Item>, bounds: Vec<usize>) -> Self {
+		Self::from_sorted_cells(elements.into_iter().collect(), |_, p| bounds.contains(&p))
 	}
 
 	/// Creates a new coloring from a map associating each item to an initial
 	/// color of type `C`.
 	pub fn from_map<C: Ord>(set: &S, map: &S::Map<C>) -> Self {
 		// Sort elements by color.
-		let mut elements: Vec<_> = set.iter().collect();
-		elements.sort_unstable_by_key(|e| map.get(e).unwrap());
+		let mut elements: S::List<S::Item> = set.iter().collect();
+		elements
+			.as_mut()
+			.sort_unstable_by(|a, b| map.get(a).unwrap().cmp(map.get(b).unwrap()).then(a.cmp(b)));
 
 		// Create colors.
-		let mut bounds = Vec::new();
-		for (i, w) in elements.windows(2).enumerate() {
-			if map.get(&w[0]) != map.get(&w[1]) {
-				bounds.push(Bound::new(i + 1))
-			}
-		}
-
-		let mut result = Self { elements, bounds };
-
-		result.sort_cells();
-
-		result
+		Self::from_sorted_cells(elements, |elements, p| {
+			map.get(&elements[p - 1]) != map.get(&elements[p])
+		})
 	}
 
-	fn reset_bounds(&mut self) {
-		for b in &mut self.bounds {
-			b.depth = 0
-		}
+	/// Returns the elements of the partitioned set, ordered by cell first,
+	/// `S::Item::cmp` second (in each cell).
+	pub fn elements(&self) -> &[S::Item] {
+		self.elements.as_ref()
 	}
 
-	/// Sort the elements in each cell.
-	///
-	/// This invariant should always be preserved,
-	/// so this function is called to enforce it whenever an operation
-	/// may have broken it.
-	fn sort_cells(&mut self) {
-		let mut start = 0;
-		for &end in &self.bounds {
-			self.elements[start..end.offset].sort_unstable();
-			start = end.offset
+	/// Returns the end of the cell starting at position `start`, if any.
+	pub fn cell_end(&self, start: usize) -> Option<usize> {
+		match self.ends.as_ref().get(start) {
+			Some(0) | None => None,
+			Some(end) => Some(*end),
 		}
-		self.elements[start..].sort_unstable()
 	}
 
-	pub fn color_index_of(&self, item: &S::Item) -> Option<usize> {
-		for (i, color) in self.colors().enumerate() {
-			if color.contains(item) {
-				return Some(i);
-			}
-		}
-
-		None
+	/// Returns the start of the cell containing the given item.
+	///
+	/// This requires a linear scan of the elements.
+	/// Use [`ReversibleColoring::cell_start`] for constant time lookup.
+	pub fn cell_start(&self, item: &S::Item) -> Option<usize> {
+		let p = self.elements().iter().position(|e| e == item)?;
+		(0..=p).rev().find(|&start| self.ends.as_ref()[start] != 0)
 	}
 
-	pub fn colors(&self) -> Colors<S> {
+	/// Returns an iterator over the cells of the coloring.
+	pub fn colors(&self) -> Colors<'_, S> {
 		Colors {
 			coloring: self,
-			i: 0,
+			start: 0,
 		}
 	}
 
 	/// Returns the number of colors in the coloring.
 	pub fn len(&self) -> usize {
-		if self.is_empty() {
-			0
-		} else {
-			self.bounds.len() + 1
-		}
+		self.len
 	}
 
 	pub fn is_empty(&self) -> bool {
-		self.elements.is_empty()
+		self.elements().is_empty()
 	}
 
 	/// Checks if the coloring is a unit coloring.
 	pub fn is_unit(&self) -> bool {
-		self.bounds.is_empty()
+		self.len <= 1
 	}
 
 	pub fn is_discrete(&self) -> bool {
-		debug_assert!(self.len() <= self.elements.len());
-		self.len() == self.elements.len()
-	}
-
-	pub fn color_start(&self, i: usize) -> Option<usize> {
-		if i == 0 {
-			Some(0)
-		} else {
-			Some(self.bounds.get(&(i - 1))?.offset)
-		}
+		debug_assert!(self.len() <= self.elements().len());
+		self.len() == self.elements().len()
 	}
 
-	/// Returns the `i`th cell of the coloring, partitioning the given
-	/// input `set`.
-	pub fn get(&self, i: usize) -> Option<&[S::Item]> {
-		let start = if i == 0 {
-			0
-		} else {
-			self.bounds.get(&(i - 1))?.offset
-		};
-
-		match self.bounds.get(&i) {
-			Some(end) => Some(&self.elements[start..end.offset]),
-			None => Some(&self.elements[start..]),
-		}
+	/// Returns the cell starting at position `start`, if any.
+	pub fn cell_at(&self, start: usize) -> Option<&[S::Item]> {
+		let end = self.cell_end(start)?;
+		Some(&self.elements()[start..end])
 	}
 
-	/// Returns the `i`th cell of the coloring, partitioning the given
-	/// input `set`.
-	fn get_mut(&mut self, i: usize) -> Option<&mut [S::Item]> {
-		let start = if i == 0 {
-			0
-		} else {
-			self.bounds.get(&(i - 1))?.offset
-		};
-
-		match self.bounds.get(&i) {
-			Some(end) => Some(&mut self.elements[start..end.offset]),
-			None => Some(&mut self.elements[start..]),
-		}
+	/// Returns the cell containing the given item.
+	///
+	/// This requires a linear scan of the elements.
+	/// Use [`ReversibleColoring::cell_of`] for constant time lookup.
+	pub fn cell_of(&self, item: &S::Item) -> Option<&[S::Item]> {
+		self.cell_at(self.cell_start(item)?)
 	}
 }
 
@@ -203,56 +175,104 @@ where
 
 pub struct Colors<'a, S: Set + ?Sized> {
 	coloring: &'a Coloring<S>,
-	i: usize,
+	start: usize,
 }
 
 impl<'a, S: Set + ?Sized> Iterator for Colors<'a, S> {
 	type Item = &'a [S::Item];
 
 	fn next(&mut self) -> Option<Self::Item> {
-		if self.i < self.coloring.len() {
-			let cell = self.coloring.get(self.i).unwrap();
-			self.i += 1;
-			Some(cell)
-		} else {
-			None
-		}
-	}
-}
-
-trait StartedRange {
-	fn start(&self) -> usize;
-}
-
-impl StartedRange for std::ops::Range<usize> {
-	fn start(&self) -> usize {
-		self.start
-	}
-}
-
-impl StartedRange for std::ops::RangeInclusive<usize> {
-	fn start(&self) -> usize {
-		*self.start()
+		let end = self.coloring.cell_end(self.start)?;
+		let cell = &self.coloring.elements()[self.start..end];
+		self.start = end;
+		Some(cell)
 	}
 }
 
-impl StartedRange for std::ops::RangeFrom<usize> {
-	fn start(&self) -> usize {
-		self.start
-	}
+/// Cell split, recorded so it can be undone.
+///
+/// The sequence of splits performed at a given level of the search tree is
+/// the *trace* of this level. Since it only depends on cell positions and
+/// refinement keys, it is invariant under isomorphism and can be used to
+/// compare search tree nodes before reaching a leaf.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub(crate) struct Split {
+	/// Start of the split cell.
+	start: usize,
+
+	/// Start of the new cell, split from the end of the cell `start`.
+	at: usize,
+
+	/// Refinement key of the new cell.
+	///
+	/// This is the neighbor count for equitable refinement, the rank of the
+	/// new cell among the fragments of the split cell for other
+	/// refinements, and `0` for individualization.
+	key: usize,
 }
 
 /// A reversible ordered coloring.
+///
+/// In addition to the coloring, each element is bound to the start of its
+/// cell and to its position, so that individualization and refinement
+/// only visit the affected cells. Every cell split is recorded so that it
+/// can be undone when backtracking.
 #[derive(Derivative)]
 #[derivative(Clone(bound = "S::Map<usize>: Clone"))]
 pub struct ReversibleColoring<S: Set + ?Sized> {
 	/// Coloring.
 	coloring: Coloring<S>,
 
-	/// Associates each element to the index of its cell in the coloring.
+	/// Associates each element to the start of its cell in the coloring.
 	reverse: S::Map<usize>,
 
-	depth: usize,
+	/// Associates each element to its position in the coloring.
+	positions: S::Map<usize>,
+
+	/// Splits performed on the coloring, in order.
+	splits: Vec<Split>,
+
+	/// Number of splits performed before each level.
+	levels: Vec<usize>,
+
+	/// Buffers of the equitable refinement.
+	#[derivative(Clone(clone_with = "Buffers::fresh"))]
+	buffers: Buffers<S>,
+}
+
+/// Buffers reused by successive equitable refinements of a coloring.
+///
+/// They are left empty (and `pending` all `false`) between refinements.
+struct Buffers<S: Set + ?Sized> {
+	/// Cells waiting in the stack, indexed by start.
+	pending: Vec<bool>,
+
+	/// Starts of the cells left unsorted by a split.
+	unsorted: Vec<usize>,
+
+	/// Elements counted by the splitting cell.
+	touched: Vec<S::Item>,
+
+	/// Starts of the fragments of a split cell.
+	fragments: Vec<usize>,
+}
+
+impl<S: Set + ?Sized> Buffers<S> {
+	fn fresh(_: &Self) -> Self {
+		Self::default()
+	}
+}
+
+impl<S: Set + ?Sized> Default for Buffers<S> {
+	fn default() -> Self {
+		Self {
+			pending: Vec::new(),
+			unsorted: Vec::new(),
+			touched: Vec::new(),
+			fragments: Vec::new(),
+		}
+	}
 }
 
 impl<S: Set + ?Sized> PartialEq for ReversibleColoring<S> {
@@ -275,43 +295,62 @@ where
 impl<S: Set + ?Sized> ReversibleColoring<S> {
 	/// Creates a new reversible coloring.
 	pub fn new(set: &S) -> Self {
-		Self {
-			coloring: Coloring::new(set),
-			reverse: set.map(|_| 0),
-			depth: 0,
-		}
+		Self::from_coloring(set, Coloring::new(set))
 	}
 
-	pub fn from_coloring(set: &S, mut coloring: Coloring<S>) -> Self {
-		coloring.reset_bounds();
-		let reverse = set.map(|item| coloring.color_index_of(item).unwrap());
-		Self {
+	pub fn from_coloring(set: &S, coloring: Coloring<S>) -> Self {
+		let mut result = Self {
+			reverse: set.map(|_| 0),
+			positions: set.map(|_| 0),
 			coloring,
-			reverse,
-			depth: 0,
+			splits: Vec::new(),
+			levels: Vec::new(),
+			buffers: Buffers::default(),
+		};
+
+		let mut start = 0;
+		while let Some(end) = result.coloring.cell_end(start) {
+			result.assign(start, start..end);
+			start = end
 		}
+
+		result
 	}
 
-	pub fn color_index_of(&self, item: &S::Item) -> Option<usize> {
+	/// Returns the start of the cell containing the given item.
+	pub fn cell_start(&self, item: &S::Item) -> Option<usize> {
 		self.reverse.get(item).cloned()
 	}
 
+	/// Returns the cell containing the given item.
+	pub fn cell_of(&self, item: &S::Item) -> Option<&[S::Item]> {
+		self.coloring.cell_at(self.cell_start(item)?)
+	}
+
+	/// Returns the position of the given item in the coloring elements.
+	pub fn position_of(&self, item: &S::Item) -> Option<usize> {
+		self.positions.get(item).cloned()
+	}
+
 	fn check(&self) -> bool {
-		for (i, color) in self.colors().enumerate() {
-			if color.windows(2).any(|a| a[0] > a[1]) {
+		let mut start = 0;
+		while let Some(end) = self.coloring.cell_end(start) {
+			let color = &self.elements()[start..end];
+			if color.windows(2).any(|a| a[0] >= a[1]) {
 				// not sorted
 				return false;
 			}
 
-			for t in color {
-				let reverse = self.color_index_of(t);
-				if reverse != Some(i) {
+			for (i, t) in color.iter().enumerate() {
+				if self.cell_start(t) != Some(start) || self.position_of(t) != Some(start + i) {
 					return false;
 				}
 			}
+
+			start = end
 		}
 
-		true
+		start == self.elements().len()
 	}
 
 	/// Returns the permutation represented by this coloring, if any.
@@ -328,33 +367,68 @@ impl<S: Set + ?Sized> ReversibleColoring<S> {
 		}
 	}
 
+	/// Binds the elements at the given `positions` to the cell `start`, and to
+	/// their position.
+	fn assign(&mut self, start: usize, positions: std::ops::Range<usize>) {
+		for p in positions {
+			let item = &self.coloring.elements.as_ref()[p];
+			self.reverse.set(item, start);
+			self.positions.set(item, p)
+		}
+	}
+
+	/// Splits the end of the cell `start`, from position `at`, into a new
+	/// cell.
+	///
+	/// Elements of the new cell are bound to it, but not their positions.
+	fn split(&mut self, start: usize, at: usize, key: usize) {
+		let end = self.coloring.ends.as_ref()[start];
+		debug_assert!(start < at && at < end);
+		self.coloring.ends.as_mut()[start] = at;
+		self.coloring.ends.as_mut()[at] = end;
+		self.coloring.len += 1;
+		for p in at..end {
+			self.reverse.set(&self.coloring.elements.as_ref()[p], at)
+		}
+		self.splits.push(Split { start, at, key })
+	}
+
+	/// Merges the cell `at` into the cell `start` preceding it.
+	///
+	/// The merged cell is not sorted.
+	fn merge(&mut self, start: usize, at: usize) {
+		let end = self.coloring.ends.as_ref()[at];
+		debug_assert_eq!(self.coloring.ends.as_ref()[start], at);
+		self.coloring.ends.as_mut()[start] = end;
+		self.coloring.ends.as_mut()[at] = 0;
+		self.coloring.len -= 1;
+		for p in at..end {
+			self.reverse.set(&self.coloring.elements.as_ref()[p], start)
+		}
+	}
+
+	/// Sorts the cell `start`, and updates the positions of its elements.
+	fn sort_cell(&mut self, start: usize) {
+		let end = self.coloring.ends.as_ref()[start];
+		self.coloring.elements.as_mut()[start..end].sort_unstable();
+		self.assign(start, start..end)
+	}
+
 	/// Refine the coloring such that `item` is in its own cell of size 1.
 	///
 	/// Returns `true` if the individualization succeeded,
 	/// or `false` if `item` was already individualized.
 	pub fn individualize(&mut self, item: &S::Item) -> bool {
-		let color_index = self.color_index_of(item).unwrap();
-		let color_start = self.color_start(color_index).unwrap();
-		let color = self.coloring.get_mut(color_index).unwrap();
-
-		if color.len() > 1 {
-			let i = color.binary_search(item).unwrap();
-			color.swap(0, i);
-			color[1..].sort_unstable();
-			self.coloring.bounds.insert(
-				color_index,
-				Bound {
-					offset: color_start + 1,
-					depth: self.depth,
-				},
-			);
-			self.reverse.map(|t, c| {
-				if c < color_index || t == item {
-					c
-				} else {
-					c + 1
-				}
-			});
+		let start = self.cell_start(item).unwrap();
+		let end = self.coloring.ends.as_ref()[start];
+
+		if end - start > 1 {
+			// Move the item in front of its cell, preserving the order of the
+			// other elements.
+			let p = self.position_of(item).unwrap();
+			self.coloring.elements.as_mut()[start..=p].rotate_right(1);
+			self.assign(start, start..(p + 1));
+			self.split(start, start + 1, 0);
 
 			debug_assert!(self.check());
 			true
@@ -364,14 +438,28 @@ impl<S: Set + ?Sized> ReversibleColoring<S> {
 	}
 
 	pub fn deindividualize(&mut self, item: &S::Item) -> bool {
-		let color_index = self.color_index_of(item).unwrap();
-		let color = self.coloring.get_mut(color_index).unwrap();
-
-		if color.len() == 1 && color_index < self.coloring.bounds.len() {
-			self.coloring.bounds.remove(color_index);
-			self.coloring.get_mut(color_index).unwrap().sort_unstable();
-			self.reverse
-				.map(|_, c| if c <= color_index { c } else { c - 1 });
+		let start = self.cell_start(item).unwrap();
+		let at = start + 1;
+
+		if self.coloring.ends.as_ref()[start] == at && at < self.elements().len() {
+			self.merge(start, at);
+			self.sort_cell(start);
+
+			// Forget the split, if it was recorded.
+			if let Some(i) = self
+				.splits
+				.iter()
+				.rposition(|s| s.start == start && s.at == at)
+			{
+				self.splits.remove(i);
+				for level in &mut self.levels {
+					if *level > i {
+						*level -= 1
+					}
+				}
+			}
+
+			debug_assert!(self.check());
 			true
 		} else {
 			false
@@ -379,38 +467,64 @@ impl<S: Set + ?Sized> ReversibleColoring<S> {
 	}
 
 	pub(crate) fn begin(&mut self) {
-		self.depth += 1;
+		self.levels.push(self.splits.len())
 	}
 
+	/// Undoes every split performed in the last `n` levels.
 	pub(crate) fn restore(&mut self, n: usize) {
-		let restored_depth = self.depth - n;
-		self.retain_bounds(|b| b.depth <= restored_depth);
-		self.depth = restored_depth;
+		if n == 0 {
+			return;
+		}
+
+		let restored_len = self.levels[self.levels.len() - n];
+		self.levels.truncate(self.levels.len() - n);
+
+		let mut merged = Vec::new();
+		while self.splits.len() > restored_len {
+			let split = self.splits.pop().unwrap();
+			self.merge(split.start, split.at);
+			merged.push(split.start)
+		}
+
+		for start in merged {
+			// Cells may be merged more than once, but are sorted only once.
+			if self.coloring.ends.as_ref()[start] != 0 && !self.is_cell_sorted(start) {
+				self.sort_cell(start)
+			}
+		}
+
+		debug_assert!(self.check());
 	}
 
-	pub(crate) fn depth(&self) -> usize {
-		self.depth
+	/// Checks that the cell `start` is sorted.
+	fn is_cell_sorted(&self, start: usize) -> bool {
+		let end = self.coloring.ends.as_ref()[start];
+		self.elements()[start..end].windows(2).all(|w| w[0] < w[1])
 	}
 
-	fn retain_bounds<P>(&mut self, predicate: P) -> bool
-	where
-		P: FnMut(&Bound) -> bool,
-	{
-		let len = self.len();
-		self.coloring.bounds.retain(predicate);
-		if self.len() != len {
-			self.coloring.sort_cells();
-			for (c, color) in self.coloring.colors().enumerate() {
-				for i in color {
-					self.reverse.set(i, c);
-				}
-			}
+	/// Returns the trace of the given level, the splits performed while
+	/// individualizing and refining at this level.
+	pub(crate) fn trace(&self, level: usize) -> &[Split] {
+		let end = self
+			.levels
+			.as_slice()
+			.get(level + 1)
+			.copied()
+			.unwrap_or(self.splits.len());
+		&self.splits[self.levels[level]..end]
+	}
 
-			debug_assert!(self.check());
-			true
-		} else {
-			false
-		}
+	/// Returns the traces of every level, from the first.
+	pub(crate) fn traces(&self) -> impl Iterator<Item = &[Split]> {
+		(0..self.depth()).map(|level| self.trace(level))
+	}
+
+	/// Returns the number of individualizations applied to this coloring,
+	/// which is the depth of the search tree node it belongs to.
+	///
+	/// This can be used to apply expensive refinements at the root only.
+	pub fn depth(&self) -> usize {
+		self.levels.len()
 	}
 
 	/// Checks if `self` is a finer than or equal to `other`, assuming they
@@ -420,7 +534,7 @@ impl<S: Set + ?Sized> ReversibleColoring<S> {
 
 		for cell in self.coloring.colors() {
 			let (first_item, cell_rest) = cell.split_first().unwrap();
-			let index = other.color_index_of(first_item).unwrap();
+			let index = other.cell_start(first_item).unwrap();
 
 			if index < last_index {
 				return false;
@@ -428,7 +542,7 @@ impl<S: Set + ?Sized> ReversibleColoring<S> {
 
 			if !cell_rest
 				.iter()
-				.all(|item| other.color_index_of(item).unwrap() == index)
+				.all(|item| other.cell_start(item).unwrap() == index)
 			{
 				return false;
 			}
@@ -443,112 +557,84 @@ impl<S: Set + ?Sized> ReversibleColoring<S> {
 	where
 		F: Fn(&S::Item) -> C,
 	{
-		self.refine_with(&mut Vec::new(), f)
+		self.refine_with_cells(&mut Vec::new(), f)
 	}
 
 	/// Refine this coloring using the given sub-coloring.
 	///
-	/// The `refined_colors` array will be expended to include the newly added colors indexes.
-	/// Colors that have not been refined are not added to the array, even if their index changes.
-	/// If the array already contains old color indexes, they will be updated in place to
-	/// the new color index.
-	pub fn refine_with<F, C: Ord>(&mut self, refined_colors: &mut Vec<usize>, f: F) -> bool
+	/// Each cell is split according to the value of `f` on its elements,
+	/// the first fragment keeping the start of the original cell.
+	///
+	/// The `refined_cells` array will be expended to include the starts of
+	/// the newly added cells, and of the refined cells that it did not
+	/// already contain. Cells that have not been refined are not added to
+	/// the array.
+	pub fn refine_with_cells<F, C: Ord>(&mut self, refined_cells: &mut Vec<usize>, f: F) -> bool
 	where
 		F: Fn(&S::Item) -> C,
 	{
-		#[allow(clippy::too_many_arguments)]
-		fn refine_color<S: Set + ?Sized, F, C: Ord, R>(
-			coloring: &mut Coloring<S>,
-			reverse: &mut S::Map<usize>,
-			depth: usize,
-			refined_colors: &mut Vec<usize>,
-			already_refined_len: usize,
-			f: F,
-			range: R,
-			old_color_index: usize,
-			mut new_color_index: usize,
-		) -> usize
-		where
-			F: Fn(&S::Item) -> C,
-			R: StartedRange + Clone,
-			Vec<S::Item>: std::ops::IndexMut<R, Output = [S::Item]>
-				+ std::ops::IndexMut<usize, Output = S::Item>,
-		{
-			coloring.elements[range.clone()].sort_unstable_by_key(|i| f(i));
-			reverse.set(&coloring.elements[range.start()], new_color_index);
-			for (i, w) in coloring.elements[range.clone()].windows(2).enumerate() {
-				if f(&w[0]) != f(&w[1]) {
-					refined_colors.push(new_color_index);
-					new_color_index += 1;
-					coloring.bounds.push(Bound {
-						offset: range.start() + i + 1,
-						depth,
-					})
-				}
-				reverse.set(&w[1], new_color_index);
-			}
+		let already_refined_len = refined_cells.len();
+		let len = self.len();
 
-			if old_color_index != new_color_index {
-				let mut present = false;
-				for c in &mut refined_colors[..already_refined_len] {
-					if *c == old_color_index {
-						*c = new_color_index;
-						present = true;
-						break;
+		let mut start = 0;
+		while let Some(end) = self.coloring.cell_end(start) {
+			if end - start > 1 {
+				let fragments_len = self.refine_cell(start, end, &f);
+				if fragments_len > 0 {
+					if !refined_cells[..already_refined_len].contains(&start) {
+						refined_cells.push(start)
 					}
-				}
 
-				if !present {
-					refined_colors.push(new_color_index);
+					refined_cells.extend(
+						self.splits[(self.splits.len() - fragments_len)..]
+							.iter()
+							.map(|s| s.at),
+					)
 				}
 			}
 
-			new_color_index
+			start = end
 		}
 
-		let already_refined_len = refined_colors.len();
-		let len = self.len();
-		let mut old_bounds = Vec::new();
-		std::mem::swap(&mut old_bounds, &mut self.coloring.bounds);
-		let mut start = 0;
-		let mut old_color_index = 0;
-		let mut new_color_index = 0;
-		for end in old_bounds {
-			new_color_index = refine_color(
-				&mut self.coloring,
-				&mut self.reverse,
-				self.depth,
-				refined_colors,
-				already_refined_len,
-				&f,
-				start..end.offset,
-				old_color_index,
-				new_color_index,
-			);
-
-			self.coloring.bounds.push(end);
-			old_color_index += 1;
-			new_color_index += 1;
-			start = end.offset
-		}
-
-		refine_color(
-			&mut self.coloring,
-			&mut self.reverse,
-			self.depth,
-			refined_colors,
-			already_refined_len,
-			&f,
-			start..,
-			old_color_index,
-			new_color_index,
-		);
-
-		self.coloring.sort_cells();
 		debug_assert!(self.check());
 		self.len() != len
 	}
 
+	/// Refines the cell `start` (ending at `end`) using the given
+	/// sub-coloring.
+	///
+	/// Returns the number of new cells.
+	fn refine_cell<F, C: Ord>(&mut self, start: usize, end: usize, f: &F) -> usize
+	where
+		F: Fn(&S::Item) -> C,
+	{
+		let cell = &mut self.coloring.elements.as_mut()[start..end];
+		let first = f(&cell[0]);
+		if cell[1..].iter().all(|item| f(item) == first) {
+			return 0;
+		}
+
+		// The sort is stable so that each fragment stays sorted.
+		cell.sort_by_key(f);
+
+		let mut count = 0;
+		let mut fragment_start = start;
+		for p in (start + 1)..end {
+			let elements = self.coloring.elements.as_ref();
+			if f(&elements[p - 1]) != f(&elements[p]) {
+				self.split(fragment_start, p, count + 1);
+				fragment_start = p;
+				count += 1
+			}
+		}
+
+		for p in start..end {
+			self.positions.set(&self.coloring.elements.as_ref()[p], p)
+		}
+
+		count
+	}
+
 	pub fn make_equitable<'i, F, I>(&mut self, set: &S, neighbors: F)
 	where
 		F: Fn(&S::Item) -> I,
@@ -561,11 +647,58 @@ impl<S: Set + ?Sized> ReversibleColoring<S> {
 		self.make_equitable_with(&mut stack, &mut map, neighbors)
 	}
 
+	/// Makes this coloring equitable for a labeled relation.
+	///
+	/// The coloring is first made equitable for the unlabeled `neighbors`
+	/// relation with [`Self::make_equitable_with`]. Then each cell is split
+	/// by the `histogram` of its elements, computed from the colors of the
+	/// other elements (given by the start of their cell), and the split
+	/// cells are used to refine the coloring again, until it is stable.
+	///
+	/// The histogram of an element should only depend on its relations to
+	/// the other elements, and on their colors.
+	pub fn make_equitable_by<'i, F, I, H, C>(&mut self, set: &S, neighbors: F, histogram: H)
+	where
+		F: Fn(&S::Item) -> I,
+		I: IntoIterator<Item = &'i S::Item>,
+		S::Item: 'i,
+		H: Fn(&S::Item, &S::Map<usize>) -> C,
+		C: Ord,
+	{
+		let mut stack = Vec::new();
+		let mut map = set.map(|_| 0);
+
+		loop {
+			self.make_equitable_with(&mut stack, &mut map, &neighbors);
+
+			let colors = set.map(|x| self.cell_start(x).unwrap());
+			if !self.refine_with_cells(&mut stack, |x| histogram(x, &colors)) {
+				break;
+			}
+		}
+	}
+
 	/// Make this coloring equitable.
 	///
+	/// Cells are used in turn to split the other cells: each element is
+	/// counted the number of times it is a neighbor of an element of the
+	/// splitting cell, and cells are split by count. For a symmetric
+	/// `neighbors` relation, this is the number of neighbors of the element
+	/// in the splitting cell.
+	///
+	/// The first splitting cells are the cells of the given stack. If it is
+	/// empty, they are the cells split since the beginning of the current
+	/// search level (such as an individualized cell), assuming the coloring
+	/// was equitable before, or every cell outside of the search.
+	///
+	/// Only the neighbors of the splitting cell are visited, and when a cell
+	/// that is not waiting to be used is split, its largest fragment is not
+	/// used as a splitting cell (Hopcroft's rule). This gives an
+	/// `O((n + m) log n)` refinement. Split cells are only sorted once the
+	/// coloring is equitable.
+	///
 	/// The stack is empty when this function returns.
-	/// The map must be initialized so that each element is mapped (the value
-	/// does not matter).
+	/// The map must bind each element to `0`, and is left this way.
 	pub fn make_equitable_with<'i, F, I>(
 		&mut self,
 		stack: &mut Vec<usize>,
@@ -577,32 +710,161 @@ impl<S: Set + ?Sized> ReversibleColoring<S> {
 		S::Item: 'i,
 	{
 		debug_assert_eq!(map.len(), self.reverse.len());
-		stack.clear();
-		stack.extend(0..self.len());
-
-		while !stack.is_empty() && !self.is_discrete() {
-			let color = stack.pop().unwrap();
-
-			// For the given color, we associate for each element
-			// the number of edges that connects to a element of
-			// this color.
-			map.map(|i, _| {
-				let mut count = 0;
-				for j in neighbors(i) {
-					let j_color = self.color_index_of(j).unwrap();
-					if j_color == color {
-						count += 1
+		let mut buffers = std::mem::take(&mut self.buffers);
+		let Buffers {
+			pending,
+			unsorted,
+			touched,
+			fragments,
+		} = &mut buffers;
+		pending.resize(self.elements().len(), false);
+
+		if stack.is_empty() {
+			match self.levels.last() {
+				Some(&level) => {
+					for split in &self.splits[level..] {
+						stack.push(split.start);
+						stack.push(split.at)
+					}
+				}
+				None => {
+					let mut start = 0;
+					while let Some(end) = self.coloring.cell_end(start) {
+						stack.push(start);
+						start = end
+					}
+				}
+			}
+		}
+
+		stack.retain(|&s| !std::mem::replace(&mut pending[s], true));
+
+		while !self.is_discrete() {
+			let Some(color) = stack.pop() else { break };
+			pending[color] = false;
+
+			// For each element connected to the color, count the number of
+			// edges connecting it to the color.
+			let end = self.coloring.ends.as_ref()[color];
+			for p in color..end {
+				for j in neighbors(&self.elements()[p]) {
+					let count = *map.get(j).unwrap();
+					if count == 0 {
+						touched.push(j.clone())
+					}
+
+					map.set(j, count + 1)
+				}
+			}
+
+			// Split the cells of the counted elements.
+			touched.sort_unstable_by_key(|j| self.cell_start(j).unwrap());
+			let mut i = 0;
+			while i < touched.len() {
+				let cell = self.cell_start(&touched[i]).unwrap();
+				let len = touched[i..]
+					.iter()
+					.position(|j| self.cell_start(j).unwrap() != cell)
+					.unwrap_or(touched.len() - i);
+
+				self.split_by_count(cell, &touched[i..(i + len)], map, fragments);
+
+				if !fragments.is_empty() {
+					unsorted.push(cell);
+					unsorted.extend_from_slice(fragments);
+
+					// Hopcroft's rule: if the cell is not pending, one of its
+					// largest fragments is not needed to refine the coloring.
+					let new_cells = || std::iter::once(cell).chain(fragments.iter().rev().copied());
+					let size = |s: usize| self.coloring.ends.as_ref()[s] - s;
+					let mut skipped = None;
+					if !pending[cell] {
+						for s in new_cells() {
+							if skipped.is_none_or(|l| size(s) > size(l)) {
+								skipped = Some(s)
+							}
+						}
+					}
+
+					for s in new_cells() {
+						if Some(s) != skipped && !pending[s] {
+							pending[s] = true;
+							stack.push(s)
+						}
 					}
 				}
-				count
-			});
 
-			// Refine the partition using the computed information.
-			// This will also update the stack with the new colors.
-			self.refine_with(stack, |i| map.get(i));
+				i += len
+			}
+
+			for j in touched.drain(..) {
+				map.set(&j, 0)
+			}
+		}
+
+		for s in stack.drain(..) {
+			pending[s] = false
+		}
+
+		unsorted.sort_unstable();
+		unsorted.dedup();
+		for start in unsorted.drain(..) {
+			self.sort_cell(start)
+		}
+
+		self.buffers = buffers;
+		debug_assert!(self.check())
+	}
+
+	/// Splits the cell `start` according to the given counts, where
+	/// `touched` are the elements of the cell with a non-zero count.
+	///
+	/// Elements with a zero count stay in the cell `start`, followed by the
+	/// others, by increasing count. Only the touched elements are moved,
+	/// hence the resulting cells are not sorted. The starts of the new cells
+	/// are written in `fragments`, in decreasing order.
+	fn split_by_count(
+		&mut self,
+		start: usize,
+		touched: &[S::Item],
+		map: &S::Map<usize>,
+		fragments: &mut Vec<usize>,
+	) {
+		fragments.clear();
+		let end = self.coloring.ends.as_ref()[start];
+		let count = |j: &S::Item| *map.get(j).unwrap();
+
+		let first_count = count(&touched[0]);
+		if touched.len() == end - start && touched.iter().all(|j| count(j) == first_count) {
+			return;
+		}
+
+		// Move the touched elements to the end of the cell.
+		let tail = end - touched.len();
+		for (i, j) in touched.iter().enumerate() {
+			let p = self.position_of(j).unwrap();
+			let q = end - 1 - i;
+			self.coloring.elements.as_mut().swap(p, q);
+			let elements = self.coloring.elements.as_ref();
+			self.positions.set(&elements[p], p);
+			self.positions.set(&elements[q], q)
+		}
+
+		self.coloring.elements.as_mut()[tail..end].sort_unstable_by_key(count);
+		self.assign(start, tail..end);
+
+		// Split from the end, so that each element is bound to its cell once.
+		let elements = self.coloring.elements.as_ref();
+		for p in ((start + 1)..end).rev() {
+			if p >= tail && count(&elements[p - 1]) != count(&elements[p]) {
+				fragments.push(p)
+			}
 		}
 
-		stack.clear()
+		for &p in &*fragments {
+			let key = count(&self.elements()[p]);
+			self.split(start, p, key)
+		}
 	}
 }
 
@@ -624,7 +886,7 @@ mod tests {
 
 	// 			$(
 	// 				if !elements.is_empty() {
-	// 					bounds.push($crate::coloring::Bound::new(elements.len()));
+	// 					bounds.push(elements.len());
 	// 				}
 
 	// 				$(
@@ -648,7 +910,7 @@ mod tests {
 
 				$(
 					if !elements.is_empty() {
-						bounds.push($crate::coloring::Bound::new(elements.len()));
+						bounds.push(elements.len());
 					}
 
 					$(
@@ -827,4 +1089,137 @@ mod tests {
 
 		assert_eq!(coloring, rcoloring! { 3 : [ 0 ], [ 2 ], [ 1 ] })
 	}
+
+	#[test]
+	fn make_equitable_02() {
+		use rand::Rng;
+		let mut rng = rand::thread_rng();
+
+		for _ in 0..50 {
+			let n = 12;
+			let mut neighbors = vec![Vec::new(); n];
+			for _ in 0..15 {
+				let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+				neighbors[a].push(b);
+				if a != b {
+					neighbors[b].push(a)
+				}
+			}
+
+			let mut coloring = super::ReversibleColoring::new(&n);
+			coloring.make_equitable(&n, |i| &neighbors[*i]);
+
+			// Naive refinement: split by the number of neighbors in each
+			// cell until stable.
+			let mut classes = vec![0; n];
+			loop {
+				let signatures: Vec<_> = (0..n)
+					.map(|i| {
+						let mut counts = vec![0; n];
+						for &j in &neighbors[i] {
+							counts[classes[j]] += 1
+						}
+						(classes[i], counts)
+					})
+					.collect();
+				let mut sorted = signatures.clone();
+				sorted.sort_unstable();
+				sorted.dedup();
+				let new_classes: Vec<_> = signatures
+					.iter()
+					.map(|s| sorted.binary_search(s).unwrap())
+					.collect();
+				if new_classes == classes {
+					break;
+				}
+				classes = new_classes
+			}
+
+			for i in 0..n {
+				for j in 0..n {
+					assert_eq!(
+						classes[i] == classes[j],
+						coloring.cell_start(&i) == coloring.cell_start(&j)
+					)
+				}
+			}
+		}
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn serde_01() {
+		let mut coloring = rcoloring! { 4usize : [ 0, 1 ], [ 2, 3 ] };
+		coloring.begin();
+		coloring.individualize(&3);
+		let json = serde_json::to_string(&coloring).unwrap();
+		let mut deserialized: super::ReversibleColoring<usize> =
+			serde_json::from_str(&json).unwrap();
+		assert_eq!(coloring, deserialized);
+		assert_eq!(coloring.reverse, deserialized.reverse);
+		assert_eq!(coloring.depth(), deserialized.depth());
+
+		deserialized.restore(1);
+		assert_eq!(deserialized, rcoloring! { 4usize : [ 0, 1 ], [ 2, 3 ] })
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn serde_02() {
+		// Unsorted cell.
+		let json = r#"{"elements":[1,0,2],"bounds":[2]}"#;
+		assert!(serde_json::from_str::<super::Coloring<usize>>(json).is_err());
+
+		// Empty cell.
+		let json = r#"{"elements":[0,1,2],"bounds":[1,1]}"#;
+		assert!(serde_json::from_str::<super::Coloring<usize>>(json).is_err());
+
+		// Duplicate element.
+		let json = r#"{"elements":[0,1,1],"bounds":[2]}"#;
+		assert!(serde_json::from_str::<super::Coloring<usize>>(json).is_err());
+
+		// More elements than an inline list holds.
+		let elements: Vec<_> = (0..65).collect();
+		let json = format!(r#"{{"elements":{:?},"bounds":[]}}"#, elements);
+		assert!(serde_json::from_str::<super::Coloring<crate::set::BitSet64>>(&json).is_err())
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn serde_03() {
+		let coloring = r#"{"elements":[0,1,2],"bounds":[1]}"#;
+		let valid = format!(
+			r#"{{"coloring":{},"reverse":[0,1,1],"positions":[0,1,2],"splits":[{{"start":0,"at":1,"key":0}}],"levels":[0]}}"#,
+			coloring
+		);
+		assert!(serde_json::from_str::<super::ReversibleColoring<usize>>(&valid).is_ok());
+
+		// Inconsistent reverse map.
+		let json = valid.replace("[0,1,1]", "[0,0,1]");
+		assert!(serde_json::from_str::<super::ReversibleColoring<usize>>(&json).is_err());
+
+		// Missing position entry.
+		let json = valid.replace(r#""positions":[0,1,2]"#, r#""positions":[0,1]"#);
+		assert!(serde_json::from_str::<super::ReversibleColoring<usize>>(&json).is_err());
+
+		// Positions that are not a permutation.
+		let json = valid.replace(r#""positions":[0,1,2]"#, r#""positions":[0,1,1]"#);
+		assert!(serde_json::from_str::<super::ReversibleColoring<usize>>(&json).is_err());
+
+		// Permutation that does not match the coloring elements.
+		let json = valid.replace(r#""positions":[0,1,2]"#, r#""positions":[0,2,1]"#);
+		assert!(serde_json::from_str::<super::ReversibleColoring<usize>>(&json).is_err());
+
+		// Elements outside of the set.
+		let json = valid.replace(r#""elements":[0,1,2]"#, r#""elements":[0,1,5]"#);
+		assert!(serde_json::from_str::<super::ReversibleColoring<usize>>(&json).is_err());
+
+		// Split that does not match the coloring.
+		let json = valid.replace(r#""at":1"#, r#""at":2"#);
+		assert!(serde_json::from_str::<super::ReversibleColoring<usize>>(&json).is_err());
+
+		// Level beyond the recorded splits.
+		let json = valid.replace(r#""levels":[0]"#, r#""levels":[2]"#);
+		assert!(serde_json::from_str::<super::ReversibleColoring<usize>>(&json).is_err())
+	}
 }
@@ -0,0 +1,190 @@
+use super::{Coloring, ReversibleColoring, Split};
+use crate::{set::Map, Set};
+use ::serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Serialize)]
+#[serde(rename = "Coloring")]
+struct ColoringRef<'a, T> {
+	elements: &'a [T],
+	bounds: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Coloring")]
+struct ColoringData<L> {
+	elements: L,
+	bounds: Vec<usize>,
+}
+
+/// Serializes a coloring as its elements and the starts of its cells
+/// (excluding `0`).
+impl<S: Set + ?Sized> Serialize for Coloring<S>
+where
+	S::Item: Serialize,
+{
+	fn serialize<E: Serializer>(&self, serializer: E) -> Result<E::Ok, E::Error> {
+		let mut bounds = Vec::new();
+		let mut start = 0;
+		while let Some(end) = self.cell_end(start) {
+			if end < self.elements().len() {
+				bounds.push(end)
+			}
+
+			start = end
+		}
+
+		ColoringRef {
+			elements: self.elements(),
+			bounds,
+		}
+		.serialize(serializer)
+	}
+}
+
+/// Deserializes a coloring, checking that the bounds are strictly increasing,
+/// that no cell is empty and that each cell is sorted without duplicates.
+impl<'de, S: Set + ?Sized> Deserialize<'de> for Coloring<S>
+where
+	S::List<S::Item>: Deserialize<'de>,
+{
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let data: ColoringData<S::List<S::Item>> = ColoringData::deserialize(deserializer)?;
+
+		let mut start = 0;
+		for &bound in &data.bounds {
+			if bound <= start || bound >= data.elements.as_ref().len() {
+				return Err(de::Error::custom("invalid coloring bounds"));
+			}
+
+			start = bound
+		}
+
+		let bounds = data.bounds;
+		let result =
+			Self::from_sorted_cells(data.elements, |_, p| bounds.binary_search(&p).is_ok());
+
+		if result
+			.colors()
+			.any(|color| color.windows(2).any(|w| w[0] >= w[1]))
+		{
+			return Err(de::Error::custom("unsorted coloring cell"));
+		}
+
+		let mut elements: Vec<_> = result.elements().iter().collect();
+		elements.sort_unstable();
+		if elements.windows(2).any(|w| w[0] == w[1]) {
+			return Err(de::Error::custom("duplicate coloring element"));
+		}
+
+		Ok(result)
+	}
+}
+
+#[derive(Serialize)]
+#[serde(rename = "ReversibleColoring")]
+struct ReversibleColoringRef<'a, C, M> {
+	coloring: &'a C,
+	reverse: &'a M,
+	positions: &'a M,
+	splits: &'a [Split],
+	levels: &'a [usize],
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "ReversibleColoring")]
+struct ReversibleColoringData<C, M> {
+	coloring: C,
+	reverse: M,
+	positions: M,
+	splits: Vec<Split>,
+	levels: Vec<usize>,
+}
+
+impl<S: Set + ?Sized> Serialize for ReversibleColoring<S>
+where
+	S::Item: Serialize,
+	S::Map<usize>: Serialize,
+{
+	fn serialize<E: Serializer>(&self, serializer: E) -> Result<E::Ok, E::Error> {
+		ReversibleColoringRef {
+			coloring: &self.coloring,
+			reverse: &self.reverse,
+			positions: &self.positions,
+			splits: &self.splits,
+			levels: &self.levels,
+		}
+		.serialize(serializer)
+	}
+}
+
+/// Deserializes a reversible coloring, checking that the underlying coloring
+/// is valid, that the `positions` map is the permutation given by its
+/// elements, that the `reverse` map is consistent with its cells and that the
+/// recorded splits can be undone.
+impl<'de, S: Set + ?Sized> Deserialize<'de> for ReversibleColoring<S>
+where
+	S::List<S::Item>: Deserialize<'de>,
+	S::Map<usize>: Deserialize<'de>,
+{
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let data: ReversibleColoringData<Coloring<S>, S::Map<usize>> =
+			ReversibleColoringData::deserialize(deserializer)?;
+
+		// Since the coloring elements are distinct, checking the position of
+		// each of them ensures that `positions` is a permutation.
+		let coloring = &data.coloring;
+		let len = coloring.elements().len();
+		if data.reverse.len() != len || data.positions.len() != len {
+			return Err(de::Error::custom("inconsistent reverse coloring"));
+		}
+
+		let mut start = 0;
+		while let Some(end) = coloring.cell_end(start) {
+			for (i, x) in coloring.elements()[start..end].iter().enumerate() {
+				if data.positions.get(x) != Some(&(start + i)) {
+					return Err(de::Error::custom("invalid coloring positions"));
+				}
+
+				if data.reverse.get(x) != Some(&start) {
+					return Err(de::Error::custom("inconsistent reverse coloring"));
+				}
+			}
+
+			start = end
+		}
+
+		if data.levels.windows(2).any(|w| w[0] > w[1])
+			|| data.levels.last().is_some_and(|l| *l > data.splits.len())
+		{
+			return Err(de::Error::custom("invalid coloring levels"));
+		}
+
+		// Undo every split on a copy of the cell ends.
+		let mut ends = data.coloring.ends.clone();
+		let ends = ends.as_mut();
+		for split in data.splits.iter().rev() {
+			let valid = split.start < split.at
+				&& ends.get(split.start) == Some(&split.at)
+				&& ends.get(split.at).is_some_and(|end| *end != 0);
+
+			if !valid {
+				return Err(de::Error::custom("invalid coloring split"));
+			}
+
+			ends[split.start] = ends[split.at];
+			ends[split.at] = 0
+		}
+
+		let result = Self {
+			coloring: data.coloring,
+			reverse: data.reverse,
+			positions: data.positions,
+			splits: data.splits,
+			levels: data.levels,
+			buffers: Default::default(),
+		};
+
+		debug_assert!(result.check());
+		Ok(result)
+	}
+}
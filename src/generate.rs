@@ -0,0 +1,85 @@
+//! Isomorph-free generation.
+//!
+//! This module implements McKay's canonical construction path method
+//! ([Isomorph-free exhaustive generation, McKay 1998](https://doi.org/10.1006/jagm.1997.0898))
+//! to enumerate exactly one structure per isomorphism class, without
+//! storing the structures generated so far.
+//!
+//! Structures are generated from a root by repeatedly adding an element with
+//! [`Augment::augment`]. A child is accepted only if the element added last
+//! is, up to automorphism, its *canonical* last element: the removable
+//! element with the greatest canonical index. This ensures that the accepted
+//! children of non-isomorphic parents are non-isomorphic. Isomorphic
+//! children of the same parent are then filtered out by comparing their
+//! canonical forms.
+use crate::{search, Map, Normalize, Set};
+use std::collections::BTreeSet;
+
+/// Structure that can be extended by adding an element.
+pub trait Augment: Normalize {
+	/// Returns every augmentation of this structure, with the element it
+	/// adds.
+	///
+	/// For the generation to be exhaustive, removing the added element from
+	/// any structure of the class must give a structure isomorphic to one of
+	/// its parents, and conversely augmenting a parent must be able to
+	/// produce every such structure. Augmentations of the same parent that
+	/// are equivalent under its automorphisms are allowed.
+	fn augment(&self) -> Vec<(Self, <Self::Elements as Set>::Item)>;
+
+	/// Checks if the given element can be the element added last, meaning
+	/// that removing it gives a valid parent.
+	///
+	/// This must be invariant under isomorphism. By default, every element
+	/// is removable.
+	fn is_removable(&self, _item: &<Self::Elements as Set>::Item) -> bool {
+		true
+	}
+}
+
+/// Generates every structure reachable from `root` by augmentations, one per
+/// isomorphism class, in depth-first order.
+///
+/// The `visit` function is called on each generated structure, starting
+/// with `root`. It returns `true` if the structure must be augmented, and
+/// `false` to stop the generation in this branch (for instance once a given
+/// size is reached).
+///
+/// # Panics
+///
+/// Panics if an augmented structure has no removable element.
+pub fn generate<T, F>(root: T, mut visit: F)
+where
+	T: Augment,
+	<T::Elements as Set>::Map<usize>: Clone,
+	F: FnMut(&T) -> bool,
+{
+	let mut stack = vec![root];
+
+	while let Some(t) = stack.pop() {
+		if visit(&t) {
+			let mut accepted = Vec::new();
+
+			// Canonical forms of the accepted children.
+			let mut normal_forms = BTreeSet::new();
+
+			for (child, item) in t.augment() {
+				let result = search(&child, false);
+				let last = child
+					.elements()
+					.iter()
+					.filter(|x| child.is_removable(x))
+					.max_by_key(|x| *result.permutation.get(x).unwrap())
+					.expect("augmented structure has no removable element");
+
+				if result.orbits.get(&item) == result.orbits.get(&last)
+					&& normal_forms.insert(result.morphed)
+				{
+					accepted.push(child)
+				}
+			}
+
+			stack.extend(accepted.into_iter().rev())
+		}
+	}
+}
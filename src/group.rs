@@ -0,0 +1,538 @@
+//! Permutation groups.
+//!
+//! This module implements the Schreier–Sims algorithm, computing a base and
+//! strong generating set of the group generated by a set of permutations.
+//! Permutations are maps from the items of a [`Set`] to items of the same
+//! set, like the automorphisms returned by
+//! [`Normalize::automorphisms`](crate::Normalize::automorphisms).
+//!
+//! ```
+//! use normal_form::group::Group;
+//!
+//! // Rotation and reflection of a square.
+//! let group = Group::new(&4usize, [vec![1, 2, 3, 0], vec![3, 2, 1, 0]]);
+//! assert_eq!(group.order().to_u128(), Some(8));
+//! assert!(group.contains(&vec![2, 1, 0, 3]));
+//! assert!(!group.contains(&vec![1, 0, 2, 3]));
+//! ```
+use crate::{GroupOrder, Map, Set};
+
+/// Permutation of the indexes `0..n`, mapping `i` to `p[i]`.
+type Permutation = Vec<usize>;
+
+/// Level of the stabilizer chain.
+struct Level {
+	/// Base point.
+	base: usize,
+
+	/// Indexes of the strong generators fixing the previous base points.
+	generators: Vec<usize>,
+
+	/// Orbit of the base point.
+	orbit: Vec<usize>,
+
+	/// For each point, the index in `orbit` and `transversal` if it is in the
+	/// orbit.
+	positions: Vec<Option<usize>>,
+
+	/// For each point of the orbit, a permutation mapping the base point to
+	/// it.
+	transversal: Vec<Permutation>,
+}
+
+impl Level {
+	/// Creates a level with the given base point, among `n` points.
+	fn new(base: usize, n: usize, generators: Vec<usize>) -> Self {
+		let mut positions = vec![None; n];
+		positions[base] = Some(0);
+		Self {
+			base,
+			generators,
+			orbit: vec![base],
+			positions,
+			transversal: vec![(0..n).collect()],
+		}
+	}
+}
+
+/// Permutation group, given by a base and strong generating set.
+pub struct Group<S: Set + ?Sized> {
+	/// Items of the set, in iteration order.
+	items: Vec<S::Item>,
+
+	/// Index of each item.
+	indexes: S::Map<usize>,
+
+	/// Identity permutation.
+	identity: S::Map<S::Item>,
+
+	/// Strong generating set.
+	generators: Vec<Permutation>,
+
+	/// Stabilizer chain.
+	levels: Vec<Level>,
+}
+
+impl<S: Set + ?Sized> Group<S> {
+	/// Computes the group generated by the given permutations of `set`.
+	pub fn new(set: &S, generators: impl IntoIterator<Item = S::Map<S::Item>>) -> Self {
+		Self::with_base(set, &[], generators)
+	}
+
+	/// Computes the group generated by the given permutations of `set`, with
+	/// a base starting with the given distinct items.
+	///
+	/// The `i`-th level of the stabilizer chain is then the stabilizer of
+	/// the first `i` items of `base`, even if some of them are fixed by the
+	/// whole group.
+	pub fn with_base(
+		set: &S,
+		base: &[S::Item],
+		generators: impl IntoIterator<Item = S::Map<S::Item>>,
+	) -> Self {
+		let items: Vec<_> = set.iter().collect();
+		let mut indexes = set.map(|_| 0);
+		for (i, item) in items.iter().enumerate() {
+			indexes.set(item, i)
+		}
+
+		let n = items.len();
+		let levels = base
+			.iter()
+			.map(|x| Level::new(*indexes.get(x).unwrap(), n, Vec::new()))
+			.collect();
+
+		let mut result = Self {
+			items,
+			indexes,
+			identity: set.map(|x| x.clone()),
+			generators: Vec::new(),
+			levels,
+		};
+
+		for g in generators {
+			let g = result.to_permutation(&g);
+			if result.sift(0, g.clone()).is_some() {
+				result.add_strong_generator(g);
+			}
+		}
+
+		result.schreier_sims(result.levels.len());
+		result
+	}
+
+	/// Adds a generator to the group.
+	///
+	/// Returns `true` if the group has grown.
+	pub fn add_generator(&mut self, generator: &S::Map<S::Item>) -> bool {
+		let g = self.to_permutation(generator);
+		if self.sift(0, g.clone()).is_some() {
+			let level = self.add_strong_generator(g);
+			self.schreier_sims(level + 1);
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Returns the order of the group.
+	pub fn order(&self) -> GroupOrder {
+		GroupOrder::from_factors(self.levels.iter().map(|level| level.orbit.len()))
+	}
+
+	/// Checks if the group contains the given permutation.
+	pub fn contains(&self, permutation: &S::Map<S::Item>) -> bool {
+		self.sift(0, self.to_permutation(permutation)).is_none()
+	}
+
+	/// Returns the image of each item by each strong generator of the
+	/// stabilizer of the first `level` base points.
+	pub(crate) fn stabilizer_images(
+		&self,
+		level: usize,
+	) -> impl '_ + Iterator<Item = (&S::Item, &S::Item)> {
+		self.levels
+			.as_slice()
+			.get(level)
+			.into_iter()
+			.flat_map(|level| &level.generators)
+			.flat_map(|&g| {
+				let g = &self.generators[g];
+				self.items.iter().zip(g).map(|(x, &y)| (x, &self.items[y]))
+			})
+	}
+
+	fn to_permutation(&self, map: &S::Map<S::Item>) -> Permutation {
+		self.items
+			.iter()
+			.map(|x| *self.indexes.get(map.get(x).unwrap()).unwrap())
+			.collect()
+	}
+
+	/// Adds a strong generator, updating the stabilizer chain.
+	///
+	/// Returns the deepest level the generator has been added to, which is
+	/// the first level whose base point it moves.
+	fn add_strong_generator(&mut self, g: Permutation) -> usize {
+		let index = self.generators.len();
+		self.generators.push(g);
+
+		let g = &self.generators[index];
+		let mut depth = self.levels.len();
+		for (i, level) in self.levels.iter_mut().enumerate() {
+			level.generators.push(index);
+			if g[level.base] != level.base {
+				depth = i;
+				break;
+			}
+		}
+
+		// Make sure the generator moves a base point.
+		if depth == self.levels.len() {
+			let base = (0..g.len()).find(|&x| g[x] != x).unwrap();
+			let generators = (0..self.generators.len())
+				.filter(|&h| {
+					let h = &self.generators[h];
+					self.levels.iter().all(|level| h[level.base] == level.base)
+				})
+				.collect();
+			self.levels
+				.push(Level::new(base, self.items.len(), generators))
+		}
+
+		for i in 0..=depth {
+			self.update_orbit(i)
+		}
+
+		depth
+	}
+
+	/// Extends the orbit of a level with its generators.
+	fn update_orbit(&mut self, i: usize) {
+		let level = &mut self.levels[i];
+		let mut j = 0;
+		while j < level.orbit.len() {
+			let p = level.orbit[j];
+			for &g in &level.generators {
+				let g = &self.generators[g];
+				let q = g[p];
+				if level.positions[q].is_none() {
+					level.positions[q] = Some(level.orbit.len());
+					level.orbit.push(q);
+					let u = compose(&level.transversal[j], g);
+					level.transversal.push(u)
+				}
+			}
+
+			j += 1
+		}
+	}
+
+	/// Completes the strong generating set, by checking that every Schreier
+	/// generator of each level is in the next level's group.
+	///
+	/// The levels from `i` are assumed to be complete already.
+	fn schreier_sims(&mut self, mut i: usize) {
+		while i > 0 {
+			let level = &self.levels[i - 1];
+			let mut residue = None;
+			'search: for (j, u) in level.transversal.iter().enumerate() {
+				let p = level.orbit[j];
+				for &g in &level.generators {
+					let g = &self.generators[g];
+					let v = &level.transversal[level.positions[g[p]].unwrap()];
+					let schreier = compose(&compose(u, g), &inverse(v));
+					if let Some(r) = self.sift(i, schreier) {
+						residue = Some(r);
+						break 'search;
+					}
+				}
+			}
+
+			// Only the levels that received the new generator must be
+			// checked again.
+			match residue {
+				Some(r) => i = self.add_strong_generator(r) + 1,
+				None => i -= 1,
+			}
+		}
+	}
+
+	/// Sifts the given permutation, fixing the first `i` base points, through
+	/// the stabilizer chain from level `i`.
+	///
+	/// Returns the residue if the permutation is not in the group.
+	fn sift(&self, i: usize, mut g: Permutation) -> Option<Permutation> {
+		for level in &self.levels[i..] {
+			match level.positions[g[level.base]] {
+				Some(j) => g = compose(&g, &inverse(&level.transversal[j])),
+				None => return Some(g),
+			}
+		}
+
+		if is_identity(&g) {
+			None
+		} else {
+			Some(g)
+		}
+	}
+}
+
+impl<S: Set + ?Sized> Group<S>
+where
+	S::Map<S::Item>: Clone,
+{
+	/// Returns the base of the group.
+	pub fn base(&self) -> Vec<S::Item> {
+		self.levels
+			.iter()
+			.map(|level| self.items[level.base].clone())
+			.collect()
+	}
+
+	/// Returns the strong generating set of the group.
+	pub fn strong_generators(&self) -> Vec<S::Map<S::Item>> {
+		self.generators.iter().map(|g| self.to_map(g)).collect()
+	}
+
+	/// Returns the length of the stabilizer chain, which is the length of the
+	/// base.
+	pub fn chain_len(&self) -> usize {
+		self.levels.len()
+	}
+
+	/// Returns the orbit of the `level`-th base point under the stabilizer
+	/// of the previous base points.
+	pub fn basic_orbit(&self, level: usize) -> Vec<S::Item> {
+		self.levels[level]
+			.orbit
+			.iter()
+			.map(|&p| self.items[p].clone())
+			.collect()
+	}
+
+	/// Returns the strong generators of the stabilizer of the first `level`
+	/// base points.
+	pub fn stabilizer_generators(&self, level: usize) -> Vec<S::Map<S::Item>> {
+		match self.levels.as_slice().get(level) {
+			Some(level) => level
+				.generators
+				.iter()
+				.map(|&g| self.to_map(&self.generators[g]))
+				.collect(),
+			None => Vec::new(),
+		}
+	}
+
+	/// Computes the orbits of the group, mapping each item to the smallest
+	/// item of its orbit.
+	pub fn orbits(&self) -> S::Map<S::Item> {
+		let n = self.items.len();
+		let mut orbits: Vec<usize> = (0..n).collect();
+		for x in 0..n {
+			if orbits[x] == x {
+				let mut stack = vec![x];
+				while let Some(y) = stack.pop() {
+					for g in &self.generators {
+						let z = g[y];
+						if orbits[z] != x {
+							orbits[z] = x;
+							stack.push(z)
+						}
+					}
+				}
+			}
+		}
+
+		let mut result = self.identity.clone();
+		for (x, y) in self.items.iter().zip(orbits) {
+			result.set(x, self.items[y].clone())
+		}
+		result
+	}
+
+	/// Returns a uniformly distributed random element of the group.
+	///
+	/// The `random` function must return a uniformly distributed random
+	/// integer lower than its argument.
+	pub fn random_element<R>(&self, mut random: R) -> S::Map<S::Item>
+	where
+		R: FnMut(usize) -> usize,
+	{
+		let indexes: Vec<_> = self
+			.levels
+			.iter()
+			.map(|level| random(level.orbit.len()))
+			.collect();
+		self.to_map(&self.element(&indexes))
+	}
+
+	/// Returns an iterator over all the elements of the group.
+	pub fn elements(&self) -> Elements<'_, S> {
+		Elements {
+			group: self,
+			indexes: Some(vec![0; self.levels.len()]),
+		}
+	}
+
+	/// Returns the element `uₖ` then … then `u₀`, where `uᵢ` is the
+	/// `indexes[i]`-th transversal element of level `i`.
+	fn element(&self, indexes: &[usize]) -> Permutation {
+		let mut result: Permutation = (0..self.items.len()).collect();
+		for (level, &i) in self.levels.iter().zip(indexes).rev() {
+			result = compose(&result, &level.transversal[i])
+		}
+		result
+	}
+
+	fn to_map(&self, p: &Permutation) -> S::Map<S::Item> {
+		let mut result = self.identity.clone();
+		for (x, &y) in self.items.iter().zip(p) {
+			result.set(x, self.items[y].clone())
+		}
+		result
+	}
+}
+
+/// Iterator over the elements of a [`Group`].
+pub struct Elements<'a, S: Set + ?Sized> {
+	group: &'a Group<S>,
+
+	/// Transversal indexes of the next element.
+	indexes: Option<Vec<usize>>,
+}
+
+impl<'a, S: Set + ?Sized> Iterator for Elements<'a, S>
+where
+	S::Map<S::Item>: Clone,
+{
+	type Item = S::Map<S::Item>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let indexes = self.indexes.as_mut()?;
+		let result = self.group.to_map(&self.group.element(indexes));
+
+		// Increment the indexes.
+		let mut done = true;
+		for (i, level) in indexes.iter_mut().zip(&self.group.levels).rev() {
+			*i += 1;
+			if *i < level.orbit.len() {
+				done = false;
+				break;
+			}
+
+			*i = 0
+		}
+
+		if done {
+			self.indexes = None
+		}
+
+		Some(result)
+	}
+}
+
+/// Computes `a` then `b`.
+fn compose(a: &[usize], b: &[usize]) -> Permutation {
+	a.iter().map(|&x| b[x]).collect()
+}
+
+fn inverse(p: &[usize]) -> Permutation {
+	let mut result = vec![0; p.len()];
+	for (x, &y) in p.iter().enumerate() {
+		result[y] = x
+	}
+	result
+}
+
+fn is_identity(p: &[usize]) -> bool {
+	p.iter().enumerate().all(|(x, &y)| x == y)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Group;
+	use std::collections::BTreeSet;
+
+	fn cycle(n: usize) -> Vec<usize> {
+		(0..n).map(|i| (i + 1) % n).collect()
+	}
+
+	#[test]
+	fn symmetric() {
+		let group = Group::new(&5usize, [vec![1, 0, 2, 3, 4], cycle(5)]);
+		assert_eq!(group.order().to_u128(), Some(120));
+
+		let elements: BTreeSet<_> = group.elements().collect();
+		assert_eq!(elements.len(), 120);
+		assert!(elements.iter().all(|g| group.contains(g)));
+		assert_eq!(group.orbits(), vec![0; 5]);
+	}
+
+	#[test]
+	fn alternating() {
+		let group = Group::new(&5usize, [vec![1, 2, 0, 3, 4], vec![0, 1, 3, 4, 2]]);
+		assert_eq!(group.order().to_u128(), Some(60));
+		assert!(group.contains(&vec![1, 0, 3, 2, 4]));
+		assert!(!group.contains(&vec![1, 0, 2, 3, 4]));
+		assert_eq!(group.elements().count(), 60);
+	}
+
+	#[test]
+	fn stabilizer_chain() {
+		// Two independent cycles.
+		let group = Group::new(
+			&7usize,
+			[vec![1, 2, 0, 3, 4, 5, 6], vec![0, 1, 2, 4, 5, 6, 3]],
+		);
+		assert_eq!(group.order().to_u128(), Some(12));
+		assert_eq!(group.orbits(), vec![0, 0, 0, 3, 3, 3, 3]);
+		assert_eq!(group.chain_len(), 2);
+
+		let base = group.base();
+		for level in 0..group.chain_len() {
+			for g in group.stabilizer_generators(level) {
+				assert!(base[..level].iter().all(|&x| g[x] == x))
+			}
+		}
+
+		let mut seed = 1usize;
+		let random = |n: usize| {
+			seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+			(seed >> 33) % n
+		};
+		assert!(group.contains(&group.random_element(random)))
+	}
+
+	#[test]
+	fn incremental() {
+		// Symmetries of a cube, as permutations of its vertices.
+		let generators = [
+			vec![1, 3, 0, 2, 5, 7, 4, 6],
+			vec![4, 5, 0, 1, 6, 7, 2, 3],
+			vec![1, 0, 3, 2, 5, 4, 7, 6],
+		];
+
+		let mut group = Group::with_base(&8usize, &[0, 7], []);
+		assert_eq!(group.chain_len(), 2);
+		assert!(group.order().is_trivial());
+		for g in &generators {
+			assert!(group.add_generator(g))
+		}
+		assert!(!group.add_generator(&vec![7, 6, 5, 4, 3, 2, 1, 0]));
+		assert_eq!(group.order().to_u128(), Some(48));
+		assert_eq!(group.base()[..2], [0, 7]);
+
+		// The stabilizer of the opposite vertices 0 and 7 has order 6.
+		assert_eq!(group.basic_orbit(0).len(), 8);
+		assert_eq!(group.basic_orbit(1).len(), 1);
+		assert_eq!(Group::new(&8usize, generators).order().to_u128(), Some(48))
+	}
+
+	#[test]
+	fn trivial() {
+		let group = Group::new(&3usize, [vec![0, 1, 2]]);
+		assert!(group.order().is_trivial());
+		assert_eq!(group.elements().collect::<Vec<_>>(), [vec![0, 1, 2]]);
+	}
+}
@@ -0,0 +1,310 @@
+//! Edge-permutable graphs.
+//!
+//! In an [`IncidenceGraph`], edges carry an identity of their own (for
+//! instance RDF statement identifiers, or reified triples): the permutable
+//! elements are both the vertices and the edges, so that the canonical
+//! permutation relabels the edges too. The coloring is refined through the
+//! incidence between vertices and edges, which amounts to canonizing the
+//! vertices together with the line graph.
+//!
+//! ```
+//! use normal_form::{incidence::IncidenceGraph, Normalize};
+//!
+//! // Two statements between the same vertices, given in different orders.
+//! let a = IncidenceGraph::directed(vec![(); 2], vec![(0, 1, 'p'), (1, 0, 'q')]);
+//! let b = IncidenceGraph::directed(vec![(); 2], vec![(0, 1, 'q'), (1, 0, 'p')]);
+//! let (form_a, permutation_a) = a.normalize();
+//! let (form_b, permutation_b) = b.normalize();
+//! assert_eq!(form_a, form_b);
+//!
+//! // The `p` statement gets the same canonical index in both graphs.
+//! assert_eq!(permutation_a[2], permutation_b[3]);
+//! ```
+use crate::{Normalize, ReversibleColoring};
+
+/// Graph whose vertices and edges are both permutable elements.
+///
+/// Vertices are numbered from `0` to `vertex_count`, and the `i`-th edge is
+/// numbered `vertex_count + i`.
+#[derive(Clone, Debug)]
+pub struct IncidenceGraph<V, E = ()> {
+	directed: bool,
+
+	/// Number of vertices and edges.
+	len: usize,
+
+	/// Vertex colors.
+	vertices: Vec<V>,
+
+	/// Edges, in the given order.
+	edges: Vec<(usize, usize, E)>,
+
+	/// For each vertex, the edges it is the source of and the edges it is the
+	/// target of. In an undirected graph, only sources are used.
+	incidences: Vec<[Vec<usize>; 2]>,
+}
+
+impl<V, E> IncidenceGraph<V, E> {
+	/// Creates a new undirected graph from its vertex colors and edges `(a,
+	/// b, color)`, where parallel edges and self-loops are allowed.
+	///
+	/// # Panics
+	///
+	/// Panics if an edge endpoint is not a vertex.
+	pub fn new(vertices: Vec<V>, edges: Vec<(usize, usize, E)>) -> Self {
+		Self::with_direction(false, vertices, edges)
+	}
+
+	/// Creates a new directed graph from its vertex colors and edges
+	/// `(source, target, color)`, where parallel edges and self-loops are
+	/// allowed.
+	///
+	/// # Panics
+	///
+	/// Panics if an edge endpoint is not a vertex.
+	pub fn directed(vertices: Vec<V>, edges: Vec<(usize, usize, E)>) -> Self {
+		Self::with_direction(true, vertices, edges)
+	}
+
+	fn with_direction(directed: bool, vertices: Vec<V>, edges: Vec<(usize, usize, E)>) -> Self {
+		let vertex_count = vertices.len();
+		let mut incidences = vec![[Vec::new(), Vec::new()]; vertex_count];
+		for (i, &(a, b, _)) in edges.iter().enumerate() {
+			assert!(a < vertex_count && b < vertex_count, "invalid edge");
+			let e = vertex_count + i;
+			incidences[a][0].push(e);
+			incidences[b][directed as usize].push(e)
+		}
+
+		Self {
+			directed,
+			len: vertex_count + edges.len(),
+			vertices,
+			edges,
+			incidences,
+		}
+	}
+
+	pub fn is_directed(&self) -> bool {
+		self.directed
+	}
+
+	pub fn vertex_count(&self) -> usize {
+		self.vertices.len()
+	}
+
+	pub fn edge_count(&self) -> usize {
+		self.edges.len()
+	}
+
+	pub fn vertices(&self) -> &[V] {
+		&self.vertices
+	}
+
+	pub fn edges(&self) -> &[(usize, usize, E)] {
+		&self.edges
+	}
+
+	/// Returns the element number of the given edge.
+	pub fn edge_element(&self, i: usize) -> usize {
+		self.vertices.len() + i
+	}
+
+	/// Returns the endpoints of the given edge, sorted if the graph is
+	/// undirected.
+	fn endpoints<T: Ord>(&self, a: T, b: T) -> (T, T) {
+		if self.directed || a <= b {
+			(a, b)
+		} else {
+			(b, a)
+		}
+	}
+}
+
+/// Color of an [`IncidenceGraph`] element.
+///
+/// Vertices come first, so they are given the first canonical indexes.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum ElementColor<V, E> {
+	Vertex(V),
+
+	/// Edge color, and whether the edge is a self-loop.
+	Edge(E, bool),
+}
+
+/// Canonical form of an [`IncidenceGraph`].
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct MorphedIncidenceGraph<V, E> {
+	/// Vertex colors, in canonical order.
+	pub vertices: Vec<V>,
+
+	/// Edges, in canonical order. In an undirected graph, the smallest
+	/// endpoint comes first.
+	pub edges: Vec<(usize, usize, E)>,
+}
+
+impl<V: Ord + Clone, E: Ord + Clone> Normalize for IncidenceGraph<V, E> {
+	type Elements = usize;
+	type Color = ElementColor<V, E>;
+	type Cache = ();
+	type Morphed = MorphedIncidenceGraph<V, E>;
+
+	fn initialize_cache(&self) {}
+
+	fn elements(&self) -> &usize {
+		&self.len
+	}
+
+	fn initial_coloring(&self) -> Vec<ElementColor<V, E>> {
+		let vertices = self.vertices.iter().cloned().map(ElementColor::Vertex);
+		let edges = self
+			.edges
+			.iter()
+			.map(|(a, b, e)| ElementColor::Edge(e.clone(), a == b));
+		vertices.chain(edges).collect()
+	}
+
+	/// Refines the color of each vertex by the colors of the edges it is the
+	/// source or target of, and the color of each edge by the colors of its
+	/// endpoints, until it is stable.
+	fn refine_coloring(&self, _cache: &mut (), coloring: &mut ReversibleColoring<usize>) {
+		let vertex_count = self.vertices.len();
+		coloring.make_equitable_by(
+			&self.len,
+			|&x| {
+				let edge = x.checked_sub(vertex_count).map(|i| &self.edges[i]);
+				let incidences = self.incidences.as_slice().get(x);
+				incidences
+					.into_iter()
+					.flatten()
+					.flatten()
+					.chain(edge.into_iter().flat_map(|(a, b, _)| [a, b]))
+			},
+			|&x, colors| {
+				if x < vertex_count {
+					let histograms = self.incidences[x].clone().map(|mut edges| {
+						for e in &mut edges {
+							*e = colors[*e]
+						}
+						edges.sort_unstable();
+						edges
+					});
+					(histograms, (0, 0))
+				} else {
+					let (a, b, _) = self.edges[x - vertex_count];
+					(Default::default(), self.endpoints(colors[a], colors[b]))
+				}
+			},
+		)
+	}
+
+	fn apply_morphism<F>(&self, f: F) -> MorphedIncidenceGraph<V, E>
+	where
+		F: Fn(&usize) -> usize,
+	{
+		let vertex_count = self.vertices.len();
+		let mut vertices: Vec<_> = (0..vertex_count).map(|x| (f(&x), x)).collect();
+		vertices.sort_unstable();
+
+		// Canonical vertex indexes.
+		let mut indexes = vec![0; vertex_count];
+		for (k, &(_, x)) in vertices.iter().enumerate() {
+			indexes[x] = k
+		}
+
+		let mut edges: Vec<_> = (0..self.edges.len())
+			.map(|i| (f(&(vertex_count + i)), i))
+			.collect();
+		edges.sort_unstable();
+
+		MorphedIncidenceGraph {
+			vertices: vertices
+				.into_iter()
+				.map(|(_, x)| self.vertices[x].clone())
+				.collect(),
+			edges: edges
+				.into_iter()
+				.map(|(_, i)| {
+					let (a, b, e) = &self.edges[i];
+					let (a, b) = self.endpoints(indexes[*a], indexes[*b]);
+					(a, b, e.clone())
+				})
+				.collect(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::IncidenceGraph;
+	use crate::Normalize;
+	use rand::{seq::SliceRandom, Rng};
+
+	#[test]
+	fn group_order() {
+		// Parallel edges are permuted.
+		let dipole = IncidenceGraph::new(vec![(); 2], vec![(0, 1, ()); 3]);
+		assert_eq!(dipole.automorphism_group_order().to_u128(), Some(12));
+
+		let dipole = IncidenceGraph::directed(vec![(); 2], vec![(0, 1, ()); 3]);
+		assert_eq!(dipole.automorphism_group_order().to_u128(), Some(6));
+
+		let cycle = IncidenceGraph::directed(vec![(); 2], vec![(0, 1, ()), (1, 0, ())]);
+		assert_eq!(cycle.automorphism_group_order().to_u128(), Some(2));
+
+		let loops = IncidenceGraph::new(vec![(); 1], vec![(0, 0, ()); 2]);
+		assert_eq!(loops.automorphism_group_order().to_u128(), Some(2));
+	}
+
+	/// Reified statements, where vertices are RDF terms and edges are
+	/// statements, colored by their predicate.
+	#[test]
+	fn reified_statements() {
+		let mut rng = rand::thread_rng();
+		for _ in 0..100 {
+			let n = rng.gen_range(1..6);
+			let vertices: Vec<bool> = (0..n).map(|_| rng.gen()).collect();
+			let edges: Vec<_> = (0..rng.gen_range(0..10))
+				.map(|_| {
+					(
+						rng.gen_range(0..n),
+						rng.gen_range(0..n),
+						rng.gen_range(0..2u8),
+					)
+				})
+				.collect();
+			let a = IncidenceGraph::directed(vertices.clone(), edges.clone());
+
+			// Rename the vertices and the statements.
+			let mut vertex_morphism: Vec<_> = (0..n).collect();
+			vertex_morphism.shuffle(&mut rng);
+			let mut edge_morphism: Vec<_> = (0..edges.len()).collect();
+			edge_morphism.shuffle(&mut rng);
+
+			let mut b_vertices = vertices.clone();
+			for (x, &v) in vertices.iter().enumerate() {
+				b_vertices[vertex_morphism[x]] = v
+			}
+			let mut b_edges = edges.clone();
+			for (i, &(s, o, p)) in edges.iter().enumerate() {
+				b_edges[edge_morphism[i]] = (vertex_morphism[s], vertex_morphism[o], p)
+			}
+			let b = IncidenceGraph::directed(b_vertices, b_edges);
+
+			let (form_a, permutation_a) = a.normalize();
+			let (form_b, permutation_b) = b.normalize();
+			assert_eq!(form_a, form_b);
+			assert_eq!(b.apply_morphism(|x| permutation_b[*x]), form_b);
+
+			// Each statement is mapped to its canonical statement.
+			for (i, &(s, o, p)) in edges.iter().enumerate() {
+				let k = permutation_a[a.edge_element(i)] - n;
+				assert_eq!(form_a.edges[k], (permutation_a[s], permutation_a[o], p));
+
+				let (s, o) = (vertex_morphism[s], vertex_morphism[o]);
+				let l = permutation_b[b.edge_element(edge_morphism[i])] - n;
+				assert_eq!(form_b.edges[l], (permutation_b[s], permutation_b[o], p))
+			}
+		}
+	}
+}
@@ -0,0 +1,419 @@
+//! Vertex invariants.
+//!
+//! A vertex invariant associates to each element of a structure a value that
+//! is preserved by isomorphisms. The functions of this module compute common
+//! invariants of graphs given by their [`Adjacency`], the sorted neighbors
+//! of each element. It is computed by [`adjacency`] from a `neighbors`
+//! function, in the same way as [`ReversibleColoring::make_equitable`], and
+//! can be computed once (for instance in
+//! [`Normalize::initialize_cache`](crate::Normalize::initialize_cache)) to
+//! compute invariants at every node of the search. The relation is assumed
+//! to be symmetric.
+//!
+//! Invariants can be used as (part of) the initial coloring of a
+//! [`Normalize`](crate::Normalize) implementation, or to refine a coloring
+//! during the search with [`ReversibleColoring::refine`]:
+//!
+//! ```
+//! use normal_form::{invariant, Map, ReversibleColoring};
+//!
+//! // A triangle `0, 1, 2` with a tail `2, 3`.
+//! let neighbors = [vec![1, 2], vec![0, 2], vec![0, 1, 3], vec![2]];
+//! let adjacency = invariant::adjacency(&4usize, |i| &neighbors[*i]);
+//! let triangles = invariant::triangles(&4usize, &adjacency);
+//!
+//! let mut coloring = ReversibleColoring::new(&4usize);
+//! coloring.refine(|i| triangles.get(i).unwrap());
+//! assert_eq!(coloring.len(), 2);
+//! ```
+//!
+//! Some invariants, like [`cell_quotient`] or [`adjacency_triples`], depend
+//! on the current coloring and are only invariant for nodes of the search
+//! tree, where they can help to distinguish elements that equitable
+//! refinement cannot (for instance in strongly regular graphs).
+use crate::{set::Map, ReversibleColoring, Set};
+
+/// Sorted, deduplicated neighbors of each element of the set `S`.
+pub type Adjacency<S> = <S as Set>::Map<Vec<<S as Set>::Item>>;
+
+/// Computes the sorted, deduplicated neighbors of each element.
+pub fn adjacency<'i, S, F, I>(set: &S, neighbors: F) -> Adjacency<S>
+where
+	S: Set + ?Sized,
+	F: Fn(&S::Item) -> I,
+	I: IntoIterator<Item = &'i S::Item>,
+	S::Item: 'i,
+{
+	set.map(|x| {
+		let mut result: Vec<_> = neighbors(x).into_iter().cloned().collect();
+		result.sort_unstable();
+		result.dedup();
+		result
+	})
+}
+
+/// Computes the degree of each element, the number of its distinct
+/// neighbors.
+pub fn degree<S: Set + ?Sized>(set: &S, adjacency: &Adjacency<S>) -> S::Map<usize> {
+	set.map(|x| adjacency.get(x).unwrap().len())
+}
+
+/// Computes, for each element, the sorted degrees of its neighbors.
+pub fn neighbor_degrees<S: Set + ?Sized>(set: &S, adjacency: &Adjacency<S>) -> S::Map<Vec<usize>> {
+	set.map(|x| {
+		let mut degrees: Vec<_> = adjacency
+			.get(x)
+			.unwrap()
+			.iter()
+			.map(|y| adjacency.get(y).unwrap().len())
+			.collect();
+		degrees.sort_unstable();
+		degrees
+	})
+}
+
+/// Computes the number of triangles through each element.
+///
+/// Self loops are ignored.
+pub fn triangles<S: Set + ?Sized>(set: &S, adjacency: &Adjacency<S>) -> S::Map<usize> {
+	cliques(set, adjacency, 3)
+}
+
+/// Computes the number of cliques of size `k` containing each element.
+///
+/// Self loops are ignored. The number of cliques grows exponentially with
+/// `k` in dense graphs.
+pub fn cliques<S: Set + ?Sized>(set: &S, adjacency: &Adjacency<S>, k: usize) -> S::Map<usize> {
+	let mut counts = set.map(|_| 0);
+
+	/// Extends the given clique with elements of `candidates`, all adjacent
+	/// to every element of the clique and greater than them.
+	fn extend<S: Set + ?Sized>(
+		adjacency: &Adjacency<S>,
+		counts: &mut S::Map<usize>,
+		clique: &mut Vec<S::Item>,
+		candidates: &[S::Item],
+		k: usize,
+	) {
+		if clique.len() == k {
+			for x in clique.iter() {
+				let count = *counts.get(x).unwrap();
+				counts.set(x, count + 1)
+			}
+
+			return;
+		}
+
+		for (i, y) in candidates.iter().enumerate() {
+			let y_neighbors = adjacency.get(y).unwrap();
+			let next: Vec<_> = candidates[(i + 1)..]
+				.iter()
+				.filter(|z| y_neighbors.binary_search(z).is_ok())
+				.cloned()
+				.collect();
+
+			clique.push(y.clone());
+			extend::<S>(adjacency, counts, clique, &next, k);
+			clique.pop();
+		}
+	}
+
+	if k > 0 {
+		for x in set.iter() {
+			let candidates: Vec<_> = adjacency
+				.get(&x)
+				.unwrap()
+				.iter()
+				.filter(|y| **y > x)
+				.cloned()
+				.collect();
+
+			let mut clique = vec![x];
+			extend::<S>(adjacency, &mut counts, &mut clique, &candidates, k);
+		}
+	}
+
+	counts
+}
+
+/// Computes the distance profile of each element: the number of elements at
+/// distance `1`, `2`, etc. from it.
+///
+/// This performs a breadth-first search from every element.
+pub fn distances<S: Set + ?Sized>(set: &S, adjacency: &Adjacency<S>) -> S::Map<Vec<usize>> {
+	let mut visited = set.map(|_| false);
+	let mut result = set.map(|_| Vec::new());
+
+	for x in set.iter() {
+		let x = &x;
+		let mut profile = Vec::new();
+		let mut visited_items = vec![x.clone()];
+		visited.set(x, true);
+
+		let mut layer = vec![x.clone()];
+		while !layer.is_empty() {
+			let mut next = Vec::new();
+			for y in &layer {
+				for z in adjacency.get(y).unwrap() {
+					if !visited.get(z).unwrap() {
+						visited.set(z, true);
+						visited_items.push(z.clone());
+						next.push(z.clone())
+					}
+				}
+			}
+
+			if !next.is_empty() {
+				profile.push(next.len())
+			}
+
+			layer = next
+		}
+
+		for y in &visited_items {
+			visited.set(y, false)
+		}
+
+		result.set(x, profile)
+	}
+
+	result
+}
+
+/// Computes the cell quotient row of each element: the number of its
+/// neighbors in each cell of the given coloring, as a list of
+/// `(cell, count)` pairs ordered by cell, omitting empty counts.
+///
+/// A coloring is equitable if and only if the elements of each cell share
+/// the same row.
+pub fn cell_quotient<S: Set + ?Sized>(
+	set: &S,
+	coloring: &ReversibleColoring<S>,
+	adjacency: &Adjacency<S>,
+) -> S::Map<Vec<(usize, usize)>> {
+	set.map(|x| {
+		let mut cells: Vec<_> = adjacency
+			.get(x)
+			.unwrap()
+			.iter()
+			.map(|y| coloring.cell_start(y).unwrap())
+			.collect();
+		cells.sort_unstable();
+
+		let mut row: Vec<(usize, usize)> = Vec::new();
+		for cell in cells {
+			match row.last_mut() {
+				Some((last, count)) if *last == cell => *count += 1,
+				_ => row.push((cell, 1)),
+			}
+		}
+
+		row
+	})
+}
+
+/// Type of a triple of elements `{ x, y, z }` seen from `x`:
+/// `(cell(y), cell(z), e, n)` where `cell(y) <= cell(z)`, `e` is the number
+/// of edges between `x`, `y` and `z`, and `n` the number of their common
+/// neighbors.
+pub type AdjacencyTriple = (usize, usize, usize, usize);
+
+/// Computes the adjacency triples invariant of each element `x`: the sorted
+/// list of the [`AdjacencyTriple`] types of `{ x, y, z }` for every pair of
+/// distinct elements `y < z` different from `x`, each with its number of
+/// occurrences.
+///
+/// This is similar to the `triples` invariant of nauty, and costs `O(n³)`
+/// neighborhood intersections.
+pub fn adjacency_triples<S: Set + ?Sized>(
+	set: &S,
+	coloring: &ReversibleColoring<S>,
+	adjacency: &Adjacency<S>,
+) -> S::Map<Vec<(AdjacencyTriple, usize)>> {
+	let items: Vec<_> = set.iter().collect();
+
+	set.map(|x| {
+		let a = adjacency.get(x).unwrap();
+		let mut triples = Vec::new();
+
+		for (i, y) in items.iter().enumerate() {
+			if y == x {
+				continue;
+			}
+
+			let b = adjacency.get(y).unwrap();
+			let ab = intersection(a, b);
+			for z in &items[(i + 1)..] {
+				if z == x {
+					continue;
+				}
+
+				let c = adjacency.get(z).unwrap();
+				let e = [(a, y), (a, z), (b, z)]
+					.iter()
+					.filter(|(n, t)| n.binary_search(t).is_ok())
+					.count();
+				let n = intersection(&ab, c).len();
+
+				let (cy, cz) = (
+					coloring.cell_start(y).unwrap(),
+					coloring.cell_start(z).unwrap(),
+				);
+				triples.push((cy.min(cz), cy.max(cz), e, n))
+			}
+		}
+
+		triples.sort_unstable();
+		let mut result: Vec<(_, usize)> = Vec::new();
+		for t in triples {
+			match result.last_mut() {
+				Some((last, count)) if *last == t => *count += 1,
+				_ => result.push((t, 1)),
+			}
+		}
+
+		result
+	})
+}
+
+/// Intersection of two sorted lists.
+fn intersection<T: Clone + Ord>(a: &[T], b: &[T]) -> Vec<T> {
+	let mut result = Vec::new();
+	let (mut i, mut j) = (0, 0);
+	while i < a.len() && j < b.len() {
+		match a[i].cmp(&b[j]) {
+			std::cmp::Ordering::Less => i += 1,
+			std::cmp::Ordering::Greater => j += 1,
+			std::cmp::Ordering::Equal => {
+				result.push(a[i].clone());
+				i += 1;
+				j += 1
+			}
+		}
+	}
+
+	result
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{set::Map, ReversibleColoring};
+
+	/// Triangle `0, 1, 2` with a tail `2, 3, 4`.
+	fn tailed_triangle() -> Vec<Vec<usize>> {
+		vec![vec![1, 2], vec![0, 2], vec![0, 1, 3], vec![2, 4], vec![3]]
+	}
+
+	/// Rook's graph `K_4 × K_4` and Shrikhande graph, both strongly regular
+	/// with parameters `(16, 6, 2, 2)`.
+	fn strongly_regular() -> [Vec<Vec<usize>>; 2] {
+		let rook = (0..16)
+			.map(|x: usize| {
+				(0..16)
+					.filter(|&y| y != x && (y / 4 == x / 4 || y % 4 == x % 4))
+					.collect()
+			})
+			.collect();
+
+		let shrikhande = (0..16)
+			.map(|x: usize| {
+				let (a, b) = (x / 4, x % 4);
+				let mut result: Vec<_> = [(1, 0), (3, 0), (0, 1), (0, 3), (1, 1), (3, 3)]
+					.iter()
+					.map(|(da, db)| ((a + da) % 4) * 4 + (b + db) % 4)
+					.collect();
+				result.sort_unstable();
+				result
+			})
+			.collect();
+
+		[rook, shrikhande]
+	}
+
+	#[test]
+	fn degree() {
+		let graph = tailed_triangle();
+		let adjacency = super::adjacency(&5usize, |x| &graph[*x]);
+		assert_eq!(super::degree(&5usize, &adjacency), [2, 2, 3, 2, 1]);
+
+		let degrees = super::neighbor_degrees(&5usize, &adjacency);
+		assert_eq!(degrees[3], [1, 3])
+	}
+
+	#[test]
+	fn triangles() {
+		let graph = tailed_triangle();
+		let adjacency = super::adjacency(&5usize, |x| &graph[*x]);
+		assert_eq!(super::triangles(&5usize, &adjacency), [1, 1, 1, 0, 0])
+	}
+
+	#[test]
+	fn distances() {
+		let graph = tailed_triangle();
+		let adjacency = super::adjacency(&5usize, |x| &graph[*x]);
+		let distances = super::distances(&5usize, &adjacency);
+		assert_eq!(distances[0], [2, 1, 1]);
+		assert_eq!(distances[2], [3, 1]);
+		assert_eq!(distances[4], [1, 1, 2])
+	}
+
+	#[test]
+	fn cliques() {
+		let [rook, shrikhande] = strongly_regular();
+		let rook = super::adjacency(&16usize, |x| &rook[*x]);
+		let shrikhande = super::adjacency(&16usize, |x| &shrikhande[*x]);
+
+		// Equitable refinement and simple invariants cannot distinguish them.
+		for adjacency in [&rook, &shrikhande] {
+			assert_eq!(super::triangles(&16usize, adjacency), [6; 16]);
+			assert_eq!(super::distances(&16usize, adjacency), vec![vec![6, 9]; 16])
+		}
+
+		// Rows and columns of the rook's graph are 4-cliques.
+		assert_eq!(super::cliques(&16usize, &rook, 4), [2; 16]);
+		assert_eq!(super::cliques(&16usize, &shrikhande, 4), [0; 16])
+	}
+
+	#[test]
+	fn cell_quotient() {
+		let graph = tailed_triangle();
+		let adjacency = super::adjacency(&5usize, |x| &graph[*x]);
+		let mut coloring = ReversibleColoring::new(&5usize);
+		let degree = super::degree(&5usize, &adjacency);
+		coloring.refine(|x| degree.get(x).unwrap());
+
+		// Cells: `[4]` (start 0), `[0, 1, 3]` (start 1), `[2]` (start 4).
+		let quotient = super::cell_quotient(&5usize, &coloring, &adjacency);
+		assert_eq!(quotient[0], [(1, 1), (4, 1)]);
+		assert_eq!(quotient[3], [(0, 1), (4, 1)])
+	}
+
+	#[test]
+	fn adjacency_triples() {
+		// A 6-cycle and two triangles are both 2-regular, hence cannot be
+		// distinguished by equitable refinement.
+		let cycle: Vec<Vec<usize>> = (0..6).map(|x| vec![(x + 1) % 6, (x + 5) % 6]).collect();
+		let triangles: Vec<Vec<usize>> = (0..6)
+			.map(|x| vec![x / 3 * 3 + (x + 1) % 3, x / 3 * 3 + (x + 2) % 3])
+			.collect();
+
+		let mut invariants = Vec::new();
+		for graph in [&cycle, &triangles] {
+			let mut coloring = ReversibleColoring::new(&6usize);
+			coloring.make_equitable(&6usize, |x| &graph[*x]);
+			assert_eq!(coloring.len(), 1);
+
+			let triples = super::adjacency_triples(
+				&6usize,
+				&coloring,
+				&super::adjacency(&6usize, |x| &graph[*x]),
+			);
+
+			// The graphs are vertex-transitive.
+			assert!(triples.windows(2).all(|w| w[0] == w[1]));
+			invariants.push(triples[0].clone())
+		}
+
+		assert_ne!(invariants[0], invariants[1])
+	}
+}
@@ -4,13 +4,30 @@
 //! [[McKay 2013]](https://arxiv.org/pdf/1301.1493.pdf) and heavily inspired by
 //! the [canonical-form](https://crates.io/crates/canonical-form) crate with the
 //! addition of caching and associated abstraction types.
-use std::collections::BTreeMap;
+use std::cmp::Ordering;
 
+pub mod bipartite;
+#[cfg(feature = "chem")]
+pub mod chem;
+pub mod cnf;
+pub mod collection;
 mod coloring;
+pub mod generate;
+pub mod group;
+pub mod incidence;
+pub mod invariant;
+pub mod matrix;
+pub mod multigraph;
+mod order;
+pub mod relational;
 pub mod set;
+mod sides;
 mod tree;
+pub mod trees;
+pub mod wl;
 
 pub use coloring::{Coloring, ReversibleColoring};
+pub use order::GroupOrder;
 pub use set::Map;
 pub use set::Set;
 
@@ -55,6 +72,29 @@ pub trait Normalize: Sized {
 	where
 		F: Fn(&<Self::Elements as Set>::Item) -> usize;
 
+	/// Compares the result of the given morphism with `other`, without
+	/// building the morphed structure.
+	///
+	/// If it returns `Some`, the result must be the same as
+	/// `self.apply_morphism(morphism).cmp(other)`. Overriding it to compare
+	/// the morphed structure lazily (for instance by producing it as an
+	/// ordered stream) saves the search from building the complete morphed
+	/// structure of leaves that are greater than the best one found so far.
+	///
+	/// Returns `None` by default, in which case the search builds the morphed
+	/// structure of each leaf once with [`Self::apply_morphism`].
+	fn cmp_morphism<F>(
+		&self,
+		_cache: &mut Self::Cache,
+		_morphism: F,
+		_other: &Self::Morphed,
+	) -> Option<Ordering>
+	where
+		F: Fn(&<Self::Elements as Set>::Item) -> usize,
+	{
+		None
+	}
+
 	/// Computes the canonical form of this object.
 	fn normal_form(&self) -> Self::Morphed
 	where
@@ -76,65 +116,445 @@ pub trait Normalize: Sized {
 	where
 		<Self::Elements as Set>::Map<usize>: Clone,
 	{
-		use std::collections::btree_map::Entry;
-		let mut cache = self.initialize_cache();
-		let elements = self.elements();
-		let initial_coloring = self.initial_coloring();
-		let mut node = Some(
-			tree::Node::root(ReversibleColoring::from_coloring(
-				elements,
-				Coloring::from_map(elements, &initial_coloring),
-			))
-			.into_first_child_leaf(|coloring| self.refine_coloring(&mut cache, coloring)),
-		);
+		let result = search(self, false);
+		(result.morphed, result.permutation)
+	}
+
+	/// Computes the order of the automorphism group of this object.
+	fn automorphism_group_order(&self) -> GroupOrder
+	where
+		<Self::Elements as Set>::Map<usize>: Clone,
+	{
+		search(self, false).order
+	}
 
-		pub struct Automorphism<T: Normalize> {
-			path: Vec<<T::Elements as Set>::Item>,
-			permutation: <T::Elements as Set>::Map<usize>,
+	/// Computes generators of the automorphism group of this object.
+	///
+	/// Those are the automorphisms found while searching for the canonical
+	/// form. Use [`group::Group`] to work with the group they generate.
+	fn automorphisms(&self) -> Vec<<Self::Elements as Set>::Map<<Self::Elements as Set>::Item>>
+	where
+		<Self::Elements as Set>::Map<usize>: Clone,
+	{
+		search(self, true).automorphisms
+	}
+
+	/// Computes the automorphism group of this object.
+	fn automorphism_group(&self) -> group::Group<Self::Elements>
+	where
+		<Self::Elements as Set>::Map<usize>: Clone,
+		<Self::Elements as Set>::Map<<Self::Elements as Set>::Item>: Clone,
+	{
+		group::Group::new(self.elements(), self.automorphisms())
+	}
+
+	/// Computes the orbits of the automorphism group of this object.
+	///
+	/// Each element is mapped to the smallest element of its orbit.
+	fn automorphism_orbits(&self) -> <Self::Elements as Set>::Map<<Self::Elements as Set>::Item>
+	where
+		<Self::Elements as Set>::Map<usize>: Clone,
+	{
+		search(self, false).orbits
+	}
+}
+
+/// Result of the search tree exploration.
+struct Search<T: Normalize> {
+	/// Canonical form.
+	morphed: T::Morphed,
+
+	/// Canonical permutation.
+	permutation: <T::Elements as Set>::Map<usize>,
+
+	/// Order of the automorphism group.
+	order: GroupOrder,
+
+	/// Orbits of the automorphism group, each element being mapped to the
+	/// smallest element of its orbit.
+	orbits: <T::Elements as Set>::Map<<T::Elements as Set>::Item>,
+
+	/// Automorphisms found during the search, if requested.
+	automorphisms: Vec<<T::Elements as Set>::Map<<T::Elements as Set>::Item>>,
+}
+
+/// Explores the search tree of the given object.
+///
+/// Returns the canonical form of the object, with the associated permutation
+/// and its automorphism group order and orbits. The automorphisms found are
+/// kept only if `keep_automorphisms` is `true`.
+fn search<T: Normalize>(t: &T, keep_automorphisms: bool) -> Search<T>
+where
+	<T::Elements as Set>::Map<usize>: Clone,
+{
+	let mut cache = t.initialize_cache();
+	let elements = t.elements();
+	let initial_coloring = t.initial_coloring();
+	let mut root_coloring = ReversibleColoring::from_coloring(
+		elements,
+		Coloring::from_map(elements, &initial_coloring),
+	);
+
+	// Refining the root coloring first avoids individualizing the elements of
+	// the (potentially large) initial cells.
+	t.refine_coloring(&mut cache, &mut root_coloring);
+
+	let mut node = Some(
+		tree::Node::root(root_coloring)
+			.into_first_child_leaf(|coloring| t.refine_coloring(&mut cache, coloring)),
+	);
+
+	pub struct Leaf<T: Normalize> {
+		path: Vec<<T::Elements as Set>::Item>,
+		permutation: <T::Elements as Set>::Map<usize>,
+		trace: Trace,
+		morphed: T::Morphed,
+	}
+
+	impl<T: Normalize> Leaf<T>
+	where
+		<T::Elements as Set>::Map<usize>: Clone,
+	{
+		/// Creates the leaf of the given node, building its morphed structure
+		/// if not given.
+		fn new(
+			t: &T,
+			node: &tree::Node<T::Elements>,
+			trace: Trace,
+			morphed: Option<T::Morphed>,
+		) -> Self {
+			let permutation = node.coloring().as_permutation().unwrap().clone();
+			Self {
+				path: node.path().clone(),
+				morphed: morphed
+					.unwrap_or_else(|| t.apply_morphism(|i| *permutation.get(i).unwrap())),
+				permutation,
+				trace,
+			}
 		}
+	}
+
+	// First leaf, and smallest leaf found if different.
+	// Leaves are ordered by refinement trace first, and canonical form second.
+	let first = Leaf::new(
+		t,
+		node.as_ref().unwrap(),
+		trace(node.as_ref().unwrap()),
+		None,
+	);
+	let mut best: Option<Leaf<T>> = None;
+
+	// For each node of the first path, the children known to be in the same
+	// orbit as the first path child under the stabilizer of the node.
+	let mut orbits: Vec<Orbit<T::Elements>> = first
+		.path
+		.iter()
+		.map(|item| Orbit::new(elements, item))
+		.collect();
+
+	// Automorphisms found so far, as maps from elements to elements, used to
+	// skip children in the orbit of already explored siblings.
+	// Only the most recent ones are used, and the others are only kept if
+	// `keep_automorphisms` is `true`.
+	let mut generators: Vec<<T::Elements as Set>::Map<<T::Elements as Set>::Item>> = Vec::new();
+	let mut found = 0;
+
+	// Group generated by all the automorphisms found, whose stabilizer chain
+	// gives the stabilizers of the nodes of the first path.
+	let mut group = group::Group::with_base(elements, &first.path, std::iter::empty());
 
-		let mut automorphisms: BTreeMap<Self::Morphed, Automorphism<Self>> = BTreeMap::new();
-
-		while let Some(mut n) = node {
-			debug_assert!(n.coloring().is_discrete());
-			let permutation = n.coloring().as_permutation().unwrap();
-			let morphed = self.apply_morphism(|i| *permutation.get(i).unwrap());
-			match automorphisms.entry(morphed) {
-				Entry::Occupied(entry) => {
-					// We found an automorphism with a previous branch, we can prune the search tree!
-					// We can prune up to the parent node sharing the longest prefix path.
-					// Why: because the first different choice lead to an automorphism.
-					// Any other leaf node morphism in this branch will be an automorphism with
-					// one of the leaves in the previous branch.
-					let len = n.path().len();
-
-					// Step 1: We find the longest common prefix path length.
-					let prefix_len = longest_common_prefix_len(n.path(), &entry.get().path);
-
-					// Step 2: We skip the other nodes in this branch and directly
-					// go back to the parent node of depth `prefix_len`.
-					// More precisely, we go back to the parent node of depth
-					// `prefix_len + 1` (just after the divergence), and let the
-					// call to `into_next_leaf` below move up to the parent and to the
-					// next leaf node.
-					n.restore(len - prefix_len - 1); // prune the search tree.
+	// Orbits of the stabilizer of the path of a node, computed once for all
+	// the children of the node.
+	let mut stabilizer: Option<Stabilizer<T::Elements>> = None;
+
+	// Orbits of the group generated by all the automorphisms found, as a
+	// union-find forest where each root is the smallest element of its tree.
+	let mut group_orbits = elements.map(|x| x.clone());
+
+	node = node.unwrap().into_next_leaf(
+		|coloring| t.refine_coloring(&mut cache, coloring),
+		|_, _| false,
+		|_| false,
+	);
+
+	while let Some(mut n) = node {
+		debug_assert!(n.coloring().is_discrete());
+		let permutation = n.coloring().as_permutation().unwrap();
+		let trace = trace(&n);
+
+		// Compares the current leaf with the given one, without computing the
+		// current leaf morphed value if possible, or computing it once.
+		let mut morphed: Option<T::Morphed> = None;
+		let mut compare = |leaf: &Leaf<T>| {
+			trace.cmp(&leaf.trace).then_with(|| {
+				let f = |i: &_| *permutation.get(i).unwrap();
+				if morphed.is_none() {
+					if let Some(ordering) = t.cmp_morphism(&mut cache, f, &leaf.morphed) {
+						return ordering;
+					}
+
+					morphed = Some(t.apply_morphism(f))
 				}
-				Entry::Vacant(entry) => {
-					entry.insert(Automorphism {
-						path: n.path().clone(),
-						permutation: permutation.clone(),
-					});
+
+				morphed.as_ref().unwrap().cmp(&leaf.morphed)
+			})
+		};
+
+		let mut automorphism = None;
+		let first_ordering = compare(&first);
+		if first_ordering.is_eq() {
+			automorphism = Some(&first)
+		} else {
+			let best_ordering = match &best {
+				Some(best) => compare(best),
+				None => first_ordering,
+			};
+
+			match best_ordering {
+				Ordering::Less => best = Some(Leaf::new(t, &n, trace, morphed)),
+				Ordering::Equal => automorphism = best.as_ref(),
+				Ordering::Greater => (),
+			}
+		}
+
+		if let Some(other) = automorphism {
+			// Maps the elements of the other leaf to the elements of the
+			// current leaf with the same canonical index.
+			let leaf_elements = n.coloring().elements();
+			let generator = t
+				.elements()
+				.map(|x| leaf_elements[*other.permutation.get(x).unwrap()].clone());
+			for x in t.elements().iter() {
+				union::<T::Elements>(&mut group_orbits, &x, generator.get(&x).unwrap())
+			}
+			group.add_generator(&generator);
+			if !keep_automorphisms && generators.len() == MAX_GENERATORS {
+				generators.remove(0);
+			}
+			generators.push(generator);
+			found += 1;
+
+			let other_path = &other.path;
+
+			// We found an automorphism with a previous branch, we can prune the search tree!
+			// We can prune up to the parent node sharing the longest prefix path.
+			// Why: because the first different choice lead to an automorphism.
+			// Any other leaf node morphism in this branch will be an automorphism with
+			// one of the leaves in the previous branch.
+			let len = n.path().len();
+
+			// Step 1: We find the longest common prefix path length.
+			let prefix_len = longest_common_prefix_len(n.path(), other_path);
+
+			// The automorphism fixes the first `prefix_len` choices and
+			// maps the previous branch child to the current one.
+			// If the previous child is in the orbit of the first path child,
+			// so is the current one.
+			let level = longest_common_prefix_len(n.path(), &first.path);
+			if prefix_len == level && orbits[level].contains(&other_path[level]) {
+				orbits[level].insert(&n.path()[level])
+			}
+
+			// Step 2: We skip the other nodes in this branch and directly
+			// go back to the parent node of depth `prefix_len`.
+			// More precisely, we go back to the parent node of depth
+			// `prefix_len + 1` (just after the divergence), and let the
+			// call to `into_next_leaf` below move up to the parent and to the
+			// next leaf node.
+			n.restore(len - prefix_len - 1); // prune the search tree.
+		}
+
+		// A node can be pruned if its trace differs from the first leaf's
+		// (hence it is not equivalent to the first path node), and is greater
+		// than the smallest leaf's (hence no leaf below can be smaller).
+		let best_trace = &best.as_ref().unwrap_or(&first).trace;
+		node = n.into_next_leaf(
+			|coloring| t.refine_coloring(&mut cache, coloring),
+			|n, child| {
+				// A child can be skipped if it is in the orbit of an explored
+				// sibling (a smaller element of its cell), under the known
+				// automorphisms fixing the path. On the first path, those are
+				// given by the group, and elsewhere by the recent automorphisms
+				// fixing the path.
+				let path = n.path();
+				let level = path.len();
+				let on_first_path = first.path[..level] == path[..];
+				let stabilizer = match &mut stabilizer {
+					Some(stabilizer) if stabilizer.found == found && stabilizer.path == *path => {
+						stabilizer
+					}
+					stabilizer => stabilizer.insert(if on_first_path {
+						Stabilizer::from_group(elements, path, &group, found)
+					} else {
+						let recent = &generators[generators.len().saturating_sub(MAX_GENERATORS)..];
+						Stabilizer::new(elements, path, recent, found)
+					}),
+				};
+
+				let root = find::<T::Elements>(&mut stabilizer.orbits, child);
+				if root < *child {
+					if on_first_path
+						&& !orbits[level].contains(child)
+						&& orbits[level]
+							.members
+							.iter()
+							.any(|x| find::<T::Elements>(&mut stabilizer.orbits, x) == root)
+					{
+						orbits[level].insert(child)
+					}
+
+					true
+				} else {
+					false
 				}
+			},
+			|n| {
+				let depth = n.path().len();
+				let first = first.trace.iter().take(depth).map(Vec::as_slice);
+				let best = best_trace.iter().take(depth).map(Vec::as_slice);
+				n.coloring().traces().ne(first) && n.coloring().traces().gt(best)
+			},
+		);
+	}
+
+	let order = GroupOrder::from_factors(orbits.iter().map(|orbit| orbit.members.len()));
+	for x in elements.iter() {
+		let root = find::<T::Elements>(&mut group_orbits, &x);
+		group_orbits.set(&x, root)
+	}
+
+	let best = best.unwrap_or(first);
+	Search {
+		morphed: best.morphed,
+		permutation: best.permutation,
+		order,
+		orbits: group_orbits,
+		automorphisms: if keep_automorphisms {
+			generators
+		} else {
+			Vec::new()
+		},
+	}
+}
+
+/// Maximum number of recent automorphisms used to skip children during the
+/// search.
+const MAX_GENERATORS: usize = 64;
+
+/// Elements known to be in the same orbit.
+struct Orbit<S: Set + ?Sized> {
+	/// Elements of the orbit.
+	members: Vec<S::Item>,
+
+	/// Binds each element to `true` if it is in the orbit.
+	contains: S::Map<bool>,
+}
+
+impl<S: Set + ?Sized> Orbit<S> {
+	fn new(elements: &S, item: &S::Item) -> Self {
+		let mut contains = elements.map(|_| false);
+		contains.set(item, true);
+		Self {
+			members: vec![item.clone()],
+			contains,
+		}
+	}
+
+	fn contains(&self, item: &S::Item) -> bool {
+		*self.contains.get(item).unwrap()
+	}
+
+	fn insert(&mut self, item: &S::Item) {
+		if !self.contains(item) {
+			self.contains.set(item, true);
+			self.members.push(item.clone())
+		}
+	}
+}
+
+/// Orbits of the group generated by the automorphisms fixing a path.
+struct Stabilizer<S: Set + ?Sized> {
+	/// Fixed path.
+	path: Vec<S::Item>,
+
+	/// Number of automorphisms found when the orbits were computed.
+	found: usize,
+
+	/// Orbits, as a union-find forest where each root is the smallest element
+	/// of its tree.
+	orbits: S::Map<S::Item>,
+}
+
+impl<S: Set + ?Sized> Stabilizer<S> {
+	/// Computes the orbits of the given automorphisms fixing `path`.
+	fn new(elements: &S, path: &[S::Item], generators: &[S::Map<S::Item>], found: usize) -> Self {
+		let fixing = generators
+			.iter()
+			.filter(|g| path.iter().all(|x| g.get(x) == Some(x)));
+		let mut orbits = elements.map(|x| x.clone());
+		for g in fixing {
+			for x in elements.iter() {
+				union::<S>(&mut orbits, &x, g.get(&x).unwrap())
 			}
+		}
 
-			node = n.into_next_leaf(|coloring| self.refine_coloring(&mut cache, coloring));
+		Self {
+			path: path.to_vec(),
+			found,
+			orbits,
 		}
+	}
 
-		let (normal_form, data) = automorphisms.into_iter().next().unwrap();
-		(normal_form, data.permutation)
+	/// Computes the orbits of the stabilizer of the first path, with the
+	/// images of each element by its generators in `group`.
+	fn from_group(elements: &S, path: &[S::Item], group: &group::Group<S>, found: usize) -> Self {
+		let mut orbits = elements.map(|x| x.clone());
+		for (x, y) in group.stabilizer_images(path.len()) {
+			union::<S>(&mut orbits, x, y)
+		}
+
+		Self {
+			path: path.to_vec(),
+			found,
+			orbits,
+		}
+	}
+}
+
+/// Finds the root of `x` in the given union-find forest, halving the path
+/// to the root on the way.
+fn find<S: Set + ?Sized>(forest: &mut S::Map<S::Item>, x: &S::Item) -> S::Item {
+	let mut x = x.clone();
+	loop {
+		let parent = forest.get(&x).unwrap().clone();
+		if parent == x {
+			break x;
+		}
+
+		let grandparent = forest.get(&parent).unwrap().clone();
+		forest.set(&x, grandparent.clone());
+		x = grandparent
+	}
+}
+
+/// Merges the trees of `x` and `y` in the given union-find forest, keeping
+/// the smallest root.
+fn union<S: Set + ?Sized>(forest: &mut S::Map<S::Item>, x: &S::Item, y: &S::Item) {
+	let (x, y) = (find::<S>(forest, x), find::<S>(forest, y));
+	match x.cmp(&y) {
+		Ordering::Less => forest.set(&y, x),
+		Ordering::Greater => forest.set(&x, y),
+		Ordering::Equal => (),
 	}
 }
 
+/// Refinement trace of a search tree node, for each level.
+type Trace = Vec<Vec<coloring::Split>>;
+
+fn trace<S: Set + ?Sized>(node: &tree::Node<S>) -> Trace {
+	node.coloring().traces().map(<[_]>::to_vec).collect()
+}
+
 fn longest_common_prefix_len<T: PartialEq>(a: &[T], b: &[T]) -> usize {
 	let mut n = 0;
 
@@ -0,0 +1,350 @@
+//! Matrices up to row and column permutations.
+//!
+//! A [`Matrix`] is canonized under independent permutations of its rows and
+//! columns, and optionally under transposition (see
+//! [`Matrix::with_transposition`]). This is the usual way to compare
+//! relational tables or experimental results up to reordering.
+//! Automorphisms are always row and column permutations, and
+//! [`Matrix::is_equivalent_to_transpose`] tells if a matrix is equivalent to
+//! its transpose.
+//!
+//! Rows and columns are initially colored by the histogram of their values,
+//! and then refined by the histogram of their `(value, opposite color)`
+//! pairs until the coloring is stable.
+//!
+//! ```
+//! use normal_form::{matrix::Matrix, Normalize};
+//!
+//! let a = Matrix::new(2, 3, vec![1, 0, 2, 0, 0, 1]);
+//! let b = Matrix::new(2, 3, vec![1, 0, 0, 2, 0, 1]); // rows swapped, columns reversed
+//! assert_eq!(a.normal_form(), b.normal_form());
+//! ```
+use crate::sides::{self, Sides};
+use crate::{Normalize, ReversibleColoring};
+
+/// Matrix.
+///
+/// As permutable elements, rows are numbered from `0` to `rows` and columns
+/// from `rows` to `rows + columns`.
+#[derive(Clone, Debug)]
+pub struct Matrix<T> {
+	rows: usize,
+	columns: usize,
+
+	/// Number of rows and columns.
+	len: usize,
+
+	/// Entries, row by row.
+	entries: Vec<T>,
+
+	/// Isomorphisms may transpose the matrix.
+	transposable: bool,
+}
+
+impl<T> Matrix<T> {
+	/// Creates a new matrix from its entries, given row by row.
+	///
+	/// # Panics
+	///
+	/// Panics if there are not `rows * columns` entries.
+	pub fn new(rows: usize, columns: usize, entries: Vec<T>) -> Self {
+		assert_eq!(entries.len(), rows * columns, "invalid entry count");
+		Self {
+			rows,
+			columns,
+			len: rows + columns,
+			entries,
+			transposable: false,
+		}
+	}
+
+	/// Allows isomorphisms to transpose the matrix.
+	pub fn with_transposition(mut self) -> Self {
+		self.transposable = true;
+		self
+	}
+
+	pub fn rows(&self) -> usize {
+		self.rows
+	}
+
+	pub fn columns(&self) -> usize {
+		self.columns
+	}
+
+	pub fn entries(&self) -> &[T] {
+		&self.entries
+	}
+
+	pub fn is_transposable(&self) -> bool {
+		self.transposable
+	}
+
+	pub fn get(&self, i: usize, j: usize) -> Option<&T> {
+		if i < self.rows && j < self.columns {
+			self.entries.get(i * self.columns + j)
+		} else {
+			None
+		}
+	}
+
+	/// Returns the entries of the given row or column element, with the
+	/// opposite element of each entry.
+	fn line(&self, x: usize) -> impl '_ + Iterator<Item = (&T, usize)> {
+		let (len, row) = if x < self.rows {
+			(self.columns, Some(x))
+		} else {
+			(self.rows, None)
+		};
+
+		(0..len).map(move |k| match row {
+			Some(i) => (&self.entries[i * self.columns + k], self.rows + k),
+			None => (&self.entries[k * self.columns + x - self.rows], k),
+		})
+	}
+}
+
+impl<T: Clone> Matrix<T> {
+	/// Returns the transpose of this matrix.
+	pub fn transpose(&self) -> Self {
+		let entries = (0..self.columns)
+			.flat_map(|j| (0..self.rows).map(move |i| (i, j)))
+			.map(|(i, j)| self.entries[i * self.columns + j].clone())
+			.collect();
+
+		Self {
+			rows: self.columns,
+			columns: self.rows,
+			len: self.len,
+			entries,
+			transposable: self.transposable,
+		}
+	}
+}
+
+impl<T: Ord + Clone> Matrix<T> {
+	/// Checks if this matrix is equivalent to its transpose, up to row and
+	/// column permutations.
+	///
+	/// If so, and the matrix is transposable, the transpositions double the
+	/// automorphism group, which only contains row and column permutations.
+	pub fn is_equivalent_to_transpose(&self) -> bool {
+		sides::is_self_dual(self)
+	}
+}
+
+/// Canonical form of a [`Matrix`].
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct MorphedMatrix<T> {
+	pub rows: usize,
+	pub columns: usize,
+
+	/// Entries, row by row.
+	pub entries: Vec<T>,
+}
+
+impl<T: Ord + Clone> Normalize for Matrix<T> {
+	type Elements = usize;
+
+	/// Whether the element is a column, and the sorted values of its
+	/// entries.
+	type Color = (bool, Vec<T>);
+
+	type Cache = ();
+
+	type Morphed = MorphedMatrix<T>;
+
+	fn initialize_cache(&self) {}
+
+	fn elements(&self) -> &usize {
+		&self.len
+	}
+
+	fn initial_coloring(&self) -> Vec<(bool, Vec<T>)> {
+		(0..self.len)
+			.map(|x| {
+				let mut values: Vec<_> = self.line(x).map(|(v, _)| v.clone()).collect();
+				values.sort_unstable();
+				(x >= self.rows, values)
+			})
+			.collect()
+	}
+
+	/// Refines the color of each row (resp. column) by the histogram of its
+	/// values paired with the color of their column (resp. row), until it is
+	/// stable.
+	fn refine_coloring(&self, _cache: &mut (), coloring: &mut ReversibleColoring<usize>) {
+		// Every row is related to every column, only the entries tell them
+		// apart.
+		coloring.make_equitable_by(
+			&self.len,
+			|_| [],
+			|&x, colors| {
+				let mut histogram: Vec<_> = self.line(x).map(|(v, y)| (v, colors[y])).collect();
+				histogram.sort_unstable();
+				histogram
+			},
+		)
+	}
+
+	fn apply_morphism<F>(&self, f: F) -> MorphedMatrix<T>
+	where
+		F: Fn(&usize) -> usize,
+	{
+		let mut indexes = vec![0; self.entries.len()];
+		if self.swaps_sides(&f) {
+			// Columns are mapped to rows, and rows to columns.
+			for i in 0..self.rows {
+				for j in 0..self.columns {
+					let (a, b) = (f(&(self.rows + j)), f(&i) - self.columns);
+					indexes[a * self.rows + b] = i * self.columns + j
+				}
+			}
+
+			return MorphedMatrix {
+				rows: self.columns,
+				columns: self.rows,
+				entries: indexes
+					.into_iter()
+					.map(|k| self.entries[k].clone())
+					.collect(),
+			};
+		}
+
+		for i in 0..self.rows {
+			for j in 0..self.columns {
+				let (a, b) = (f(&i), f(&(self.rows + j)) - self.rows);
+				indexes[a * self.columns + b] = i * self.columns + j
+			}
+		}
+
+		MorphedMatrix {
+			rows: self.rows,
+			columns: self.columns,
+			entries: indexes
+				.into_iter()
+				.map(|k| self.entries[k].clone())
+				.collect(),
+		}
+	}
+
+	/// If the matrix is transposable, this is the smallest canonical form of
+	/// the matrix and of its transpose. In the latter case, the canonical
+	/// permutation maps rows after columns, and [`Normalize::apply_morphism`]
+	/// transposes the matrix.
+	fn normalize(&self) -> (MorphedMatrix<T>, Vec<usize>) {
+		sides::normalize(self)
+	}
+
+	fn normal_form(&self) -> MorphedMatrix<T> {
+		self.normalize().0
+	}
+
+	fn canonical_permutation(&self) -> Vec<usize> {
+		self.normalize().1
+	}
+}
+
+/// Rows are the first side, and columns the second one.
+impl<T: Ord + Clone> Sides for Matrix<T> {
+	fn first_side_len(&self) -> usize {
+		self.rows
+	}
+
+	fn swappable_sides(&self) -> bool {
+		self.transposable
+	}
+
+	fn swap_sides(&self) -> Self {
+		self.transpose()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Matrix;
+	use crate::Normalize;
+	use rand::{seq::SliceRandom, Rng};
+
+	fn random_matrix(rows: usize, columns: usize) -> Matrix<u8> {
+		let mut rng = rand::thread_rng();
+		let entries = (0..rows * columns).map(|_| rng.gen_range(0..3)).collect();
+		Matrix::new(rows, columns, entries)
+	}
+
+	/// Shuffles the rows and columns of the given matrix.
+	fn shuffle(matrix: &Matrix<u8>) -> Matrix<u8> {
+		let mut rng = rand::thread_rng();
+		let mut rows: Vec<_> = (0..matrix.rows()).collect();
+		let mut columns: Vec<_> = (0..matrix.columns()).collect();
+		rows.shuffle(&mut rng);
+		columns.shuffle(&mut rng);
+
+		let entries = rows
+			.iter()
+			.flat_map(|&i| columns.iter().map(move |&j| (i, j)))
+			.map(|(i, j)| *matrix.get(i, j).unwrap())
+			.collect();
+		Matrix::new(matrix.rows(), matrix.columns(), entries)
+	}
+
+	#[test]
+	fn random() {
+		for _ in 0..100 {
+			let mut rng = rand::thread_rng();
+			let a = random_matrix(rng.gen_range(0..5), rng.gen_range(0..5));
+			let b = shuffle(&a);
+			assert_eq!(a.normal_form(), b.normal_form());
+
+			let (form, permutation) = b.normalize();
+			assert_eq!(b.apply_morphism(|x| permutation[*x]), form);
+
+			let t = b.transpose().with_transposition();
+			assert_eq!(a.with_transposition().normal_form(), t.normal_form());
+
+			let (form, permutation) = t.normalize();
+			assert_eq!(t.apply_morphism(|x| permutation[*x]), form)
+		}
+	}
+
+	#[test]
+	fn transposition() {
+		let column = Matrix::new(2, 1, vec![0u8, 1]).with_transposition();
+		let empty = Matrix::new(2, 0, Vec::<u8>::new()).with_transposition();
+		for m in [column, empty] {
+			let (form, permutation) = m.normalize();
+			assert_eq!((form.rows, form.columns), (m.columns(), m.rows()));
+			assert_eq!(m.apply_morphism(|x| permutation[*x]), form);
+			assert_eq!(m.transpose().normal_form(), form);
+			assert_eq!(
+				m.automorphism_group_order().to_u128(),
+				m.transpose().automorphism_group_order().to_u128()
+			)
+		}
+	}
+
+	#[test]
+	fn group_order() {
+		let identity = Matrix::new(3, 3, vec![1, 0, 0, 0, 1, 0, 0, 0, 1]);
+		assert_eq!(identity.automorphism_group_order().to_u128(), Some(6));
+
+		// Automorphisms are row and column permutations, even if the matrix
+		// is transposable.
+		let identity = identity.with_transposition();
+		assert_eq!(identity.automorphism_group_order().to_u128(), Some(6));
+		assert_eq!(identity.automorphism_group().order().to_u128(), Some(6));
+		assert!(identity.is_equivalent_to_transpose());
+
+		let ones = Matrix::new(2, 3, vec![1; 6]);
+		assert_eq!(ones.automorphism_group_order().to_u128(), Some(12));
+		assert!(!ones.is_equivalent_to_transpose());
+
+		let triangular = Matrix::new(3, 3, vec![1, 1, 1, 0, 1, 1, 0, 0, 1]).with_transposition();
+		assert_eq!(triangular.automorphism_group_order().to_u128(), Some(1));
+		assert!(triangular.is_equivalent_to_transpose());
+
+		let rows = Matrix::new(2, 2, vec![1, 1, 0, 0]);
+		assert_ne!(rows.normal_form(), rows.transpose().normal_form());
+		assert!(!rows.is_equivalent_to_transpose());
+	}
+}
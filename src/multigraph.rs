@@ -0,0 +1,228 @@
+//! Colored multigraphs.
+//!
+//! A [`Multigraph`] is an undirected graph with colored vertices and colored
+//! edges, where parallel edges and self-loops are allowed. Its canonical form
+//! lists the vertex colors in canonical order, and the sorted multiset of
+//! edges.
+//!
+//! ```
+//! use normal_form::{multigraph::Multigraph, Normalize};
+//!
+//! // A double edge and a loop, labeled differently.
+//! let a = Multigraph::new(vec!['a', 'b'], [(0, 1, 0), (1, 0, 0), (1, 1, 1)]);
+//! let b = Multigraph::new(vec!['b', 'a'], [(0, 0, 1), (0, 1, 0), (0, 1, 0)]);
+//! assert_eq!(a.normal_form(), b.normal_form());
+//! ```
+use crate::{Normalize, ReversibleColoring};
+
+/// Undirected multigraph with vertex colors `V` and edge colors `E`.
+#[derive(Clone, Debug)]
+pub struct Multigraph<V, E = ()> {
+	/// Number of vertices.
+	len: usize,
+
+	/// Vertex colors.
+	vertices: Vec<V>,
+
+	/// Sorted edges, with the smallest endpoint first.
+	edges: Vec<(usize, usize, E)>,
+
+	/// For each vertex, the opposite endpoint and index of each incident
+	/// edge. A self-loop is listed once.
+	incidences: Vec<Vec<(usize, usize)>>,
+}
+
+impl<V, E: Ord> Multigraph<V, E> {
+	/// Creates a new multigraph from its vertex colors and edges `(a, b,
+	/// color)`.
+	///
+	/// # Panics
+	///
+	/// Panics if an edge endpoint is not a vertex.
+	pub fn new(vertices: Vec<V>, edges: impl IntoIterator<Item = (usize, usize, E)>) -> Self {
+		let len = vertices.len();
+		let mut edges: Vec<_> = edges
+			.into_iter()
+			.map(|(a, b, e)| {
+				assert!(a < len && b < len, "invalid edge");
+				(a.min(b), a.max(b), e)
+			})
+			.collect();
+		edges.sort_unstable();
+
+		let mut incidences = vec![Vec::new(); len];
+		for (i, &(a, b, _)) in edges.iter().enumerate() {
+			incidences[a].push((b, i));
+			if a != b {
+				incidences[b].push((a, i))
+			}
+		}
+
+		Self {
+			len,
+			vertices,
+			edges,
+			incidences,
+		}
+	}
+}
+
+impl<V, E> Multigraph<V, E> {
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	pub fn vertices(&self) -> &[V] {
+		&self.vertices
+	}
+
+	/// Returns the sorted edges, with the smallest endpoint first.
+	pub fn edges(&self) -> &[(usize, usize, E)] {
+		&self.edges
+	}
+
+	/// Returns the number of edge endpoints on the given vertex, a self-loop
+	/// counting twice.
+	pub fn degree(&self, vertex: usize) -> usize {
+		self.incidences[vertex]
+			.iter()
+			.map(|&(other, _)| if other == vertex { 2 } else { 1 })
+			.sum()
+	}
+
+	/// Returns the colors of the self-loops on the given vertex.
+	fn loops(&self, vertex: usize) -> impl '_ + Iterator<Item = &E> {
+		self.incidences[vertex]
+			.iter()
+			.filter(move |&&(other, _)| other == vertex)
+			.map(|&(_, i)| &self.edges[i].2)
+	}
+}
+
+/// Canonical form of a [`Multigraph`].
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct MorphedMultigraph<V, E> {
+	/// Vertex colors, in canonical order.
+	pub vertices: Vec<V>,
+
+	/// Sorted edges, with the smallest endpoint first.
+	pub edges: Vec<(usize, usize, E)>,
+}
+
+impl<V: Ord + Clone, E: Ord + Clone> Normalize for Multigraph<V, E> {
+	type Elements = usize;
+
+	/// Vertex color, with the sorted colors of its self-loops.
+	type Color = (V, Vec<E>);
+
+	type Cache = ();
+
+	type Morphed = MorphedMultigraph<V, E>;
+
+	fn initialize_cache(&self) {}
+
+	fn elements(&self) -> &usize {
+		&self.len
+	}
+
+	fn initial_coloring(&self) -> Vec<(V, Vec<E>)> {
+		self.vertices
+			.iter()
+			.enumerate()
+			.map(|(x, v)| {
+				let mut loops: Vec<_> = self.loops(x).cloned().collect();
+				loops.sort_unstable();
+				(v.clone(), loops)
+			})
+			.collect()
+	}
+
+	/// Refines the color of each vertex by the multiset of its incident edge
+	/// colors paired with the color of the opposite endpoint, until it is
+	/// stable. Parallel edges are counted with their multiplicity.
+	fn refine_coloring(&self, _cache: &mut (), coloring: &mut ReversibleColoring<usize>) {
+		coloring.make_equitable_by(
+			&self.len,
+			|x| self.incidences[*x].iter().map(|(y, _)| y),
+			|&x, colors| {
+				let mut histogram: Vec<_> = self.incidences[x]
+					.iter()
+					.map(|&(y, i)| (&self.edges[i].2, colors[y]))
+					.collect();
+				histogram.sort_unstable();
+				histogram
+			},
+		)
+	}
+
+	fn apply_morphism<F>(&self, f: F) -> MorphedMultigraph<V, E>
+	where
+		F: Fn(&usize) -> usize,
+	{
+		let mut vertices: Vec<_> = (0..self.len).map(|x| (f(&x), x)).collect();
+		vertices.sort_unstable();
+
+		let mut edges: Vec<_> = self
+			.edges
+			.iter()
+			.map(|(a, b, e)| {
+				let (a, b) = (f(a), f(b));
+				(a.min(b), a.max(b), e.clone())
+			})
+			.collect();
+		edges.sort_unstable();
+
+		MorphedMultigraph {
+			vertices: vertices
+				.into_iter()
+				.map(|(_, x)| self.vertices[x].clone())
+				.collect(),
+			edges,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Multigraph;
+	use crate::Normalize;
+
+	#[test]
+	fn group_order() {
+		// Parallel edges are not permuted.
+		let dipole = Multigraph::new(vec![(); 2], [(0, 1, ()); 3]);
+		assert_eq!(dipole.automorphism_group_order().to_u128(), Some(2));
+
+		// A loop breaks the symmetry.
+		let dipole = Multigraph::new(vec![(); 2], [(0, 1, ()), (0, 1, ()), (1, 1, ())]);
+		assert_eq!(dipole.automorphism_group_order().to_u128(), Some(1));
+
+		// Triangle with one double edge.
+		let triangle = Multigraph::new(
+			vec![(); 3],
+			[(0, 1, ()), (1, 2, ()), (2, 0, ()), (2, 0, ())],
+		);
+		assert_eq!(triangle.automorphism_group_order().to_u128(), Some(2));
+		assert_eq!(triangle.degree(2), 3);
+
+		// Square with alternating edge colors.
+		let square = Multigraph::new(vec![(); 4], [(0, 1, 0), (1, 2, 1), (2, 3, 0), (3, 0, 1)]);
+		assert_eq!(square.automorphism_group_order().to_u128(), Some(4));
+	}
+
+	#[test]
+	fn multiplicity() {
+		// Both graphs are 4-regular, but not isomorphic.
+		let a = Multigraph::new(
+			vec![(); 2],
+			[(0, 0, ()), (1, 1, ()), (0, 1, ()), (0, 1, ())],
+		);
+		let b = Multigraph::new(vec![(); 2], [(0, 1, ()); 4]);
+		assert_eq!(a.degree(0), b.degree(0));
+		assert_ne!(a.normal_form(), b.normal_form())
+	}
+}
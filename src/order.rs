@@ -0,0 +1,106 @@
+use std::fmt;
+
+/// Order of an automorphism group.
+///
+/// The order is stored as the list of the orbit sizes of the stabilizer
+/// chain found along the first path of the search tree. The group order is
+/// the product of those factors, which may not fit in any primitive integer
+/// type.
+///
+/// Since the factors depend on the path taken by the search, two group
+/// orders are compared by value, not by factors.
+#[derive(Clone, Debug, Default)]
+pub struct GroupOrder {
+	factors: Vec<usize>,
+}
+
+impl GroupOrder {
+	/// Creates the order of the trivial group.
+	pub fn trivial() -> Self {
+		Self::default()
+	}
+
+	/// Creates a group order from its factors.
+	///
+	/// Factors equal to `1` are ignored.
+	pub fn from_factors(factors: impl IntoIterator<Item = usize>) -> Self {
+		let mut result = Self::trivial();
+		for f in factors {
+			result.push(f)
+		}
+		result
+	}
+
+	pub(crate) fn push(&mut self, factor: usize) {
+		debug_assert!(factor > 0);
+		if factor > 1 {
+			self.factors.push(factor)
+		}
+	}
+
+	/// Returns the factors of the group order (all greater than `1`).
+	pub fn factors(&self) -> &[usize] {
+		&self.factors
+	}
+
+	/// Checks if this is the order of the trivial group.
+	pub fn is_trivial(&self) -> bool {
+		self.factors.is_empty()
+	}
+
+	/// Returns the group order as an `u128`, if it fits.
+	pub fn to_u128(&self) -> Option<u128> {
+		self.factors
+			.iter()
+			.try_fold(1u128, |acc, &f| acc.checked_mul(f as u128))
+	}
+
+	/// Returns an approximation of the group order.
+	pub fn to_f64(&self) -> f64 {
+		self.factors.iter().map(|&f| f as f64).product()
+	}
+
+	/// Returns the decimal digits of the group order, in base `10^9`, least
+	/// significant first.
+	fn digits(&self) -> Vec<u64> {
+		const BASE: u64 = 1_000_000_000;
+		let mut digits = vec![1u64];
+
+		for &f in &self.factors {
+			let mut carry = 0u128;
+			for d in &mut digits {
+				let v = *d as u128 * f as u128 + carry;
+				*d = (v % BASE as u128) as u64;
+				carry = v / BASE as u128;
+			}
+
+			while carry > 0 {
+				digits.push((carry % BASE as u128) as u64);
+				carry /= BASE as u128;
+			}
+		}
+
+		digits
+	}
+}
+
+impl PartialEq for GroupOrder {
+	fn eq(&self, other: &Self) -> bool {
+		self.digits() == other.digits()
+	}
+}
+
+impl Eq for GroupOrder {}
+
+impl fmt::Display for GroupOrder {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let digits = self.digits();
+		let (last, rest) = digits.split_last().unwrap();
+		write!(f, "{}", last)?;
+		for d in rest.iter().rev() {
+			write!(f, "{:09}", d)?;
+		}
+
+		Ok(())
+	}
+}
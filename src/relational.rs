@@ -0,0 +1,350 @@
+//! Finite relational structures.
+//!
+//! A [`RelationalStructure`] is a domain of `n` elements with named relations
+//! of arbitrary arities, such as a database instance or a finite model of a
+//! first-order theory. Its canonical form is itself a relational structure,
+//! with the same schema.
+//!
+//! ```
+//! use normal_form::{relational::RelationalStructure, Normalize};
+//!
+//! // Two directed paths with a labeled start.
+//! let mut a = RelationalStructure::new(3);
+//! a.insert("edge", vec![0, 1]);
+//! a.insert("edge", vec![1, 2]);
+//! a.insert("start", vec![0]);
+//!
+//! let mut b = RelationalStructure::new(3);
+//! b.insert("start", vec![2]);
+//! b.insert("edge", vec![2, 0]);
+//! b.insert("edge", vec![0, 1]);
+//!
+//! assert_eq!(a.normal_form(), b.normal_form());
+//! ```
+use crate::{Normalize, ReversibleColoring};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Relation of a [`RelationalStructure`].
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Relation {
+	arity: usize,
+	tuples: BTreeSet<Vec<usize>>,
+}
+
+impl Relation {
+	pub fn arity(&self) -> usize {
+		self.arity
+	}
+
+	pub fn tuples(&self) -> &BTreeSet<Vec<usize>> {
+		&self.tuples
+	}
+
+	pub fn len(&self) -> usize {
+		self.tuples.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.tuples.is_empty()
+	}
+
+	pub fn contains(&self, tuple: &[usize]) -> bool {
+		self.tuples.contains(tuple)
+	}
+}
+
+/// Finite relational structure, with relations named by `R`.
+///
+/// Elements of the domain are numbered from `0` to `len`. Declared relations
+/// are part of the structure even if they are empty.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct RelationalStructure<R = String> {
+	len: usize,
+	relations: BTreeMap<R, Relation>,
+}
+
+impl<R: Ord> RelationalStructure<R> {
+	/// Creates a new structure with the given domain size, and no relation.
+	pub fn new(len: usize) -> Self {
+		Self {
+			len,
+			relations: BTreeMap::new(),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Declares a relation with the given arity.
+	///
+	/// # Panics
+	///
+	/// Panics if the relation is already declared with another arity.
+	pub fn declare(&mut self, name: R, arity: usize) -> &mut Relation {
+		let relation = self.relations.entry(name).or_insert_with(|| Relation {
+			arity,
+			tuples: BTreeSet::new(),
+		});
+		assert_eq!(relation.arity, arity, "invalid arity");
+		relation
+	}
+
+	/// Inserts a tuple in the given relation, declaring it if necessary.
+	///
+	/// Returns `true` if the tuple was not already in the relation.
+	///
+	/// # Panics
+	///
+	/// Panics if the tuple length differs from the relation arity, or if an
+	/// element of the tuple is not in the domain.
+	pub fn insert(&mut self, name: R, tuple: Vec<usize>) -> bool {
+		assert!(tuple.iter().all(|&x| x < self.len), "invalid element");
+		self.declare(name, tuple.len()).tuples.insert(tuple)
+	}
+
+	pub fn relation(&self, name: &R) -> Option<&Relation> {
+		self.relations.get(name)
+	}
+
+	/// Returns the relations, by name.
+	pub fn relations(&self) -> impl '_ + Iterator<Item = (&R, &Relation)> {
+		self.relations.iter()
+	}
+
+	pub fn contains(&self, name: &R, tuple: &[usize]) -> bool {
+		self.relations.get(name).is_some_and(|r| r.contains(tuple))
+	}
+
+	/// Returns the positions of the given element in the given tuple.
+	fn positions(tuple: &[usize], x: usize) -> Vec<usize> {
+		(0..tuple.len()).filter(|&i| tuple[i] == x).collect()
+	}
+}
+
+pub struct Cache {
+	/// Every tuple, with the index of its relation.
+	tuples: Vec<(usize, Vec<usize>)>,
+
+	/// Tuples in which each element occurs.
+	occurrences: Vec<Vec<usize>>,
+}
+
+impl<R: Ord + Clone> Normalize for RelationalStructure<R> {
+	type Elements = usize;
+
+	/// Sorted occurrences of the element, given by the index of the relation
+	/// and the positions of the element in the tuple.
+	type Color = Vec<(usize, Vec<usize>)>;
+
+	type Cache = Cache;
+	type Morphed = Self;
+
+	fn initialize_cache(&self) -> Cache {
+		let mut tuples = Vec::new();
+		let mut occurrences = vec![Vec::new(); self.len];
+		for (r, relation) in self.relations.values().enumerate() {
+			for tuple in &relation.tuples {
+				let mut elements = tuple.clone();
+				elements.sort_unstable();
+				elements.dedup();
+				for x in elements {
+					occurrences[x].push(tuples.len())
+				}
+
+				tuples.push((r, tuple.clone()))
+			}
+		}
+
+		Cache {
+			tuples,
+			occurrences,
+		}
+	}
+
+	fn elements(&self) -> &usize {
+		&self.len
+	}
+
+	fn initial_coloring(&self) -> Vec<Vec<(usize, Vec<usize>)>> {
+		let mut colors = vec![Vec::new(); self.len];
+		for (r, relation) in self.relations.values().enumerate() {
+			for tuple in &relation.tuples {
+				let mut elements = tuple.clone();
+				elements.sort_unstable();
+				elements.dedup();
+				for x in elements {
+					colors[x].push((r, Self::positions(tuple, x)))
+				}
+			}
+		}
+
+		for c in &mut colors {
+			c.sort_unstable()
+		}
+
+		colors
+	}
+
+	/// Refines the color of each element by the multiset of the tuples it
+	/// occurs in, each given by its relation, the positions of the element,
+	/// and the colors of the elements at every position, until it is stable.
+	fn refine_coloring(&self, cache: &mut Cache, coloring: &mut ReversibleColoring<usize>) {
+		let cache = &*cache;
+		coloring.make_equitable_by(
+			&self.len,
+			|x| {
+				cache.occurrences[*x]
+					.iter()
+					.flat_map(|&t| &cache.tuples[t].1)
+			},
+			|&x, colors| {
+				let mut histogram: Vec<_> = cache.occurrences[x]
+					.iter()
+					.map(|&t| {
+						let (r, tuple) = &cache.tuples[t];
+						let tuple_colors: Vec<_> = tuple.iter().map(|&y| colors[y]).collect();
+						(*r, Self::positions(tuple, x), tuple_colors)
+					})
+					.collect();
+				histogram.sort_unstable();
+				histogram
+			},
+		)
+	}
+
+	fn apply_morphism<F>(&self, f: F) -> Self
+	where
+		F: Fn(&usize) -> usize,
+	{
+		let relations = self
+			.relations
+			.iter()
+			.map(|(name, relation)| {
+				let tuples = relation
+					.tuples
+					.iter()
+					.map(|tuple| tuple.iter().map(&f).collect())
+					.collect();
+
+				(
+					name.clone(),
+					Relation {
+						arity: relation.arity,
+						tuples,
+					},
+				)
+			})
+			.collect();
+
+		Self {
+			len: self.len,
+			relations,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::RelationalStructure;
+	use crate::Normalize;
+	use rand::{seq::SliceRandom, Rng};
+
+	fn structure(
+		len: usize,
+		tuples: &[(&'static str, &[usize])],
+	) -> RelationalStructure<&'static str> {
+		let mut s = RelationalStructure::new(len);
+		for (name, tuple) in tuples {
+			s.insert(*name, tuple.to_vec());
+		}
+		s
+	}
+
+	#[test]
+	fn group_order() {
+		let cycle = structure(
+			4,
+			&[
+				("e", &[0, 1]),
+				("e", &[1, 0]),
+				("e", &[1, 2]),
+				("e", &[2, 1]),
+				("e", &[2, 3]),
+				("e", &[3, 2]),
+				("e", &[3, 0]),
+				("e", &[0, 3]),
+			],
+		);
+		assert_eq!(cycle.automorphism_group_order().to_u128(), Some(8));
+
+		let directed = structure(3, &[("e", &[0, 1]), ("e", &[1, 2]), ("e", &[2, 0])]);
+		assert_eq!(directed.automorphism_group_order().to_u128(), Some(3));
+
+		// Positions matter.
+		let triple = structure(3, &[("r", &[0, 1, 2])]);
+		assert_eq!(triple.automorphism_group_order().to_u128(), Some(1));
+		let rotations = structure(
+			3,
+			&[("r", &[0, 1, 2]), ("r", &[1, 2, 0]), ("r", &[2, 0, 1])],
+		);
+		assert_eq!(rotations.automorphism_group_order().to_u128(), Some(3));
+
+		// Unrelated elements are interchangeable.
+		let unary = structure(5, &[("p", &[0]), ("p", &[1])]);
+		assert_eq!(unary.automorphism_group_order().to_u128(), Some(12));
+	}
+
+	#[test]
+	fn schema() {
+		// Repeated elements.
+		let a = structure(2, &[("r", &[0, 0, 1])]);
+		let b = structure(2, &[("r", &[0, 1, 1])]);
+		assert_ne!(a.normal_form(), b.normal_form());
+
+		// Relation names.
+		let a = structure(2, &[("p", &[0]), ("q", &[1])]);
+		let b = structure(2, &[("p", &[1]), ("q", &[0])]);
+		let c = structure(2, &[("p", &[0]), ("r", &[1])]);
+		assert_eq!(a.normal_form(), b.normal_form());
+		assert_ne!(a.normal_form(), c.normal_form());
+
+		// Declared empty relations.
+		let mut d = a.clone();
+		d.declare("r", 2);
+		assert_ne!(a.normal_form(), d.normal_form());
+
+		// Nullary relations.
+		let mut e = a.clone();
+		e.insert("true", Vec::new());
+		assert_ne!(a.normal_form(), e.normal_form());
+		assert!(e.normal_form().contains(&"true", &[]));
+	}
+
+	#[test]
+	fn random() {
+		let mut rng = rand::thread_rng();
+		let schema = [("p", 1), ("e", 2), ("r", 3)];
+
+		for _ in 0..100 {
+			let len = rng.gen_range(1..8);
+			let mut a = RelationalStructure::new(len);
+			for _ in 0..rng.gen_range(0..12) {
+				let (name, arity) = schema[rng.gen_range(0..schema.len())];
+				a.insert(name, (0..arity).map(|_| rng.gen_range(0..len)).collect());
+			}
+
+			let mut morphism: Vec<_> = (0..len).collect();
+			morphism.shuffle(&mut rng);
+			let b = a.apply_morphism(|x| morphism[*x]);
+
+			let (form, permutation) = b.normalize();
+			assert_eq!(a.normal_form(), form);
+			assert_eq!(b.apply_morphism(|x| permutation[*x]), form)
+		}
+	}
+}
@@ -1,18 +1,48 @@
+//! Sets of permutable elements, and maps over them.
+//!
+//! Colorings of small sets are built without heap allocation: [`BitSet64`],
+//! [`BitSet256`] and `u8` store their maps and lists inline. Since the
+//! natural number `n` represents `{ 0, ..., n - 1 }`, a `u8` set holds at
+//! most 255 elements; [`BitSet256`] holds up to 256. Other natural numbers,
+//! including `u16`, store their maps and lists in vectors, and allocate.
+mod array;
+mod btree;
+mod hash;
+mod interned;
+mod slice;
 mod r#usize;
 
+pub use array::{ArrayList, ArrayMap, BitSet256, BitSet64, BitSetIter};
+pub use interned::Interned;
+pub use slice::VecSet;
+
 #[allow(clippy::len_without_is_empty)]
 /// Ordered set.
+///
+/// This trait is implemented for the natural numbers `u8`, `u16`, `u32`,
+/// `u64` and `usize`, where `n` represents the set `{ 0, ..., n - 1 }`, and
+/// for the standard collections `BTreeSet`, `HashSet` and slices (which must
+/// not contain duplicates). Vectors of distinct items can be wrapped in a
+/// [`VecSet`].
+///
+/// Small sets use inline maps and lists (see the [module](crate::set)
+/// documentation). Sparse sets of identifiers can be wrapped in an
+/// [`Interned`] set.
 pub trait Set {
 	/// Type of the items of the set.
 	type Item: Clone + Ord;
 
-	/// Map type, binding each item of the graph to a value `V`.
-	///
-	/// ## Example
-	///
-	/// `Vec<V>`.
+	/// Map type, binding each item of the set to a value `V`, such as
+	/// `Vec<V>` for natural numbers.
 	type Map<V>: Map<Self::Item, V>;
 
+	/// Fixed-length list of values, such as `Vec<U>`.
+	///
+	/// Colorings use it to store the items of the set in a given order, and
+	/// a value for each position. Its length never exceeds the number of
+	/// items of the set.
+	type List<U: Clone + Eq>: Clone + Eq + AsRef<[U]> + AsMut<[U]> + FromIterator<U>;
+
 	/// Items iterator.
 	type Iter<'a>: 'a + Iterator<Item = Self::Item>
 	where
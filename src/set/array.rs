@@ -0,0 +1,485 @@
+use super::{Map, Set};
+
+use std::{fmt, mem::MaybeUninit};
+
+/// Fixed-capacity map from small natural numbers to values of type `V`,
+/// stored inline.
+///
+/// Keys must be lower than `N`, which must not exceed `256`. The map takes
+/// `N * size_of::<V>()` bytes, plus a bit field of the bound keys.
+pub struct ArrayMap<V, const N: usize> {
+	/// Bound keys.
+	keys: BitSet256,
+
+	/// Values, initialized for the bound keys only.
+	values: [MaybeUninit<V>; N],
+}
+
+impl<V, const N: usize> ArrayMap<V, N> {
+	/// Creates an empty map.
+	pub fn new() -> Self {
+		Self {
+			keys: BitSet256::new(),
+			values: std::array::from_fn(|_| MaybeUninit::uninit()),
+		}
+	}
+
+	/// Inserts a new binding, returning the previous value bound to `key`.
+	///
+	/// ## Panics
+	///
+	/// Panics if `key` is greater or equal to `N`.
+	pub fn insert(&mut self, key: u8, value: V) -> Option<V> {
+		let slot = &mut self.values[key as usize];
+		if self.keys.insert(key) {
+			slot.write(value);
+			None
+		} else {
+			// SAFETY: the key is bound, so its value is initialized.
+			Some(std::mem::replace(unsafe { slot.assume_init_mut() }, value))
+		}
+	}
+
+	/// Returns an iterator over the bindings of the map, ordered by key.
+	pub fn iter(&self) -> impl Iterator<Item = (u8, &V)> {
+		// SAFETY: the keys are bound, so their values are initialized.
+		self.keys
+			.iter()
+			.map(|k| (k, unsafe { self.values[k as usize].assume_init_ref() }))
+	}
+}
+
+impl<V, const N: usize> Drop for ArrayMap<V, N> {
+	fn drop(&mut self) {
+		for k in self.keys.iter() {
+			// SAFETY: the key is bound, so its value is initialized.
+			unsafe { self.values[k as usize].assume_init_drop() }
+		}
+	}
+}
+
+impl<V: Clone, const N: usize> Clone for ArrayMap<V, N> {
+	fn clone(&self) -> Self {
+		self.iter().map(|(k, v)| (k, v.clone())).collect()
+	}
+}
+
+impl<V: PartialEq, const N: usize> PartialEq for ArrayMap<V, N> {
+	fn eq(&self, other: &Self) -> bool {
+		self.iter().eq(other.iter())
+	}
+}
+
+impl<V: Eq, const N: usize> Eq for ArrayMap<V, N> {}
+
+impl<V: fmt::Debug, const N: usize> fmt::Debug for ArrayMap<V, N> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_map().entries(self.iter()).finish()
+	}
+}
+
+impl<V, const N: usize> Default for ArrayMap<V, N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<V, const N: usize> FromIterator<(u8, V)> for ArrayMap<V, N> {
+	fn from_iter<I: IntoIterator<Item = (u8, V)>>(iter: I) -> Self {
+		let mut result = Self::new();
+		for (k, v) in iter {
+			result.insert(k, v);
+		}
+		result
+	}
+}
+
+impl<V, const N: usize> Map<u8, V> for ArrayMap<V, N> {
+	fn len(&self) -> usize {
+		self.keys.len()
+	}
+
+	fn get(&self, key: &u8) -> Option<&V> {
+		if self.keys.contains(*key) {
+			// SAFETY: the key is bound, so its value is initialized.
+			Some(unsafe { self.values[*key as usize].assume_init_ref() })
+		} else {
+			None
+		}
+	}
+
+	fn set(&mut self, key: &u8, value: V) {
+		assert!(self.keys.contains(*key), "unbound key");
+		// SAFETY: the key is bound, so its value is initialized.
+		*unsafe { self.values[*key as usize].assume_init_mut() } = value
+	}
+
+	fn map<F>(&mut self, f: F)
+	where
+		F: Fn(&u8, V) -> V,
+	{
+		let keys = self.keys;
+		for k in keys.iter() {
+			// The key is unbound while its value is moved out, so that the
+			// value is not dropped twice if `f` panics.
+			self.keys.remove(k);
+			let slot = &mut self.values[k as usize];
+			// SAFETY: the key was bound, so its value is initialized.
+			let v = unsafe { slot.assume_init_read() };
+			slot.write(f(&k, v));
+			self.keys.insert(k);
+		}
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<V: serde::Serialize, const N: usize> serde::Serialize for ArrayMap<V, N> {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_map(self.iter())
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de, V: serde::Deserialize<'de>, const N: usize> serde::Deserialize<'de> for ArrayMap<V, N> {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		use serde::de::Error;
+		let entries = std::collections::BTreeMap::<u8, V>::deserialize(deserializer)?;
+		if entries.keys().any(|k| *k as usize >= N) {
+			return Err(D::Error::custom("array map key out of bounds"));
+		}
+
+		Ok(entries.into_iter().collect())
+	}
+}
+
+/// Fixed-capacity list of values of type `T`, stored inline.
+///
+/// The list holds at most `N` values, and takes `N * size_of::<T>()` bytes.
+pub struct ArrayList<T, const N: usize> {
+	len: usize,
+
+	/// Values, initialized up to `len`.
+	items: [MaybeUninit<T>; N],
+}
+
+impl<T, const N: usize> ArrayList<T, N> {
+	/// Creates an empty list.
+	pub fn new() -> Self {
+		Self {
+			len: 0,
+			items: std::array::from_fn(|_| MaybeUninit::uninit()),
+		}
+	}
+
+	/// Appends a value to the list.
+	///
+	/// ## Panics
+	///
+	/// Panics if the list already holds `N` values.
+	pub fn push(&mut self, value: T) {
+		assert!(self.len < N, "array list capacity exceeded");
+		self.items[self.len].write(value);
+		self.len += 1
+	}
+}
+
+impl<T, const N: usize> AsRef<[T]> for ArrayList<T, N> {
+	fn as_ref(&self) -> &[T] {
+		// SAFETY: the first `len` values are initialized.
+		unsafe { std::slice::from_raw_parts(self.items.as_ptr().cast(), self.len) }
+	}
+}
+
+impl<T, const N: usize> AsMut<[T]> for ArrayList<T, N> {
+	fn as_mut(&mut self) -> &mut [T] {
+		// SAFETY: the first `len` values are initialized.
+		unsafe { std::slice::from_raw_parts_mut(self.items.as_mut_ptr().cast(), self.len) }
+	}
+}
+
+impl<T, const N: usize> Drop for ArrayList<T, N> {
+	fn drop(&mut self) {
+		// SAFETY: the first `len` values are initialized, and not used after.
+		unsafe { std::ptr::drop_in_place(self.as_mut()) }
+	}
+}
+
+impl<T: Clone, const N: usize> Clone for ArrayList<T, N> {
+	fn clone(&self) -> Self {
+		self.as_ref().iter().cloned().collect()
+	}
+}
+
+impl<T: PartialEq, const N: usize> PartialEq for ArrayList<T, N> {
+	fn eq(&self, other: &Self) -> bool {
+		self.as_ref() == other.as_ref()
+	}
+}
+
+impl<T: Eq, const N: usize> Eq for ArrayList<T, N> {}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for ArrayList<T, N> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.as_ref()).finish()
+	}
+}
+
+impl<T, const N: usize> Default for ArrayList<T, N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// ## Panics
+///
+/// Panics if the iterator yields more than `N` values.
+impl<T, const N: usize> FromIterator<T> for ArrayList<T, N> {
+	fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+		let mut result = Self::new();
+		for value in iter {
+			result.push(value)
+		}
+		result
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize, const N: usize> serde::Serialize for ArrayList<T, N> {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_seq(self.as_ref())
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>, const N: usize> serde::Deserialize<'de> for ArrayList<T, N> {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		use serde::de::Error;
+		let values = Vec::<T>::deserialize(deserializer)?;
+		if values.len() > N {
+			return Err(D::Error::custom("array list capacity exceeded"));
+		}
+
+		Ok(values.into_iter().collect())
+	}
+}
+
+/// The natural number `n` represents the set `{ 0, ..., n - 1 }`.
+///
+/// Maps and lists are stored inline.
+impl Set for u8 {
+	type Item = u8;
+
+	type Map<V> = ArrayMap<V, 256>;
+
+	type List<U: Clone + Eq> = ArrayList<U, 256>;
+
+	type Iter<'a> = std::ops::Range<u8>;
+
+	fn len(&self) -> usize {
+		*self as usize
+	}
+
+	fn iter(&self) -> Self::Iter<'_> {
+		0..*self
+	}
+
+	fn map<V: Clone, F>(&self, f: F) -> Self::Map<V>
+	where
+		F: Fn(&Self::Item) -> V,
+	{
+		(0..*self).map(|i| (i, f(&i))).collect()
+	}
+}
+
+macro_rules! bit_set {
+	($($(#[$meta:meta])* $id:ident : $words:literal),*) => {
+		$(
+			$(#[$meta])*
+			#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+			pub struct $id([u64; $words]);
+
+			impl $id {
+				/// Maximum number of items in the set.
+				pub const CAPACITY: usize = 64 * $words;
+
+				/// Creates a new empty set.
+				pub fn new() -> Self {
+					Self::default()
+				}
+
+				/// Checks if the given item is in the set.
+				pub fn contains(&self, item: u8) -> bool {
+					let item = item as usize;
+					item < Self::CAPACITY && self.0[item / 64] & (1 << (item % 64)) != 0
+				}
+
+				/// Inserts an item in the set.
+				///
+				/// Returns `true` if the item was not already in the set.
+				///
+				/// ## Panics
+				///
+				/// Panics if `item` is greater or equal to `Self::CAPACITY`.
+				pub fn insert(&mut self, item: u8) -> bool {
+					let item = item as usize;
+					let bit = 1 << (item % 64);
+					let word = &mut self.0[item / 64];
+					let result = *word & bit == 0;
+					*word |= bit;
+					result
+				}
+
+				/// Removes an item from the set.
+				///
+				/// Returns `true` if the item was in the set.
+				pub fn remove(&mut self, item: u8) -> bool {
+					let result = self.contains(item);
+					if result {
+						let item = item as usize;
+						self.0[item / 64] &= !(1 << (item % 64))
+					}
+					result
+				}
+			}
+
+			impl FromIterator<u8> for $id {
+				fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Self {
+					let mut result = Self::new();
+					for i in iter {
+						result.insert(i);
+					}
+					result
+				}
+			}
+
+			impl Set for $id {
+				type Item = u8;
+
+				type Map<V> = ArrayMap<V, { 64 * $words }>;
+
+				type List<U: Clone + Eq> = ArrayList<U, { 64 * $words }>;
+
+				type Iter<'a> = BitSetIter<'a>;
+
+				fn len(&self) -> usize {
+					self.0.iter().map(|w| w.count_ones() as usize).sum()
+				}
+
+				fn iter(&self) -> Self::Iter<'_> {
+					BitSetIter::new(&self.0)
+				}
+
+				fn map<V: Clone, F>(&self, f: F) -> Self::Map<V>
+				where
+					F: Fn(&Self::Item) -> V,
+				{
+					self.iter().map(|i| (i, f(&i))).collect()
+				}
+			}
+		)*
+	};
+}
+
+bit_set! {
+	/// Set of natural numbers lower than `64`, represented as a bit field.
+	///
+	/// Maps and lists are stored inline.
+	BitSet64: 1,
+
+	/// Set of natural numbers lower than `256`, represented as a bit field.
+	///
+	/// Maps and lists are stored inline.
+	BitSet256: 4
+}
+
+/// Iterator over the items of a bit set, in increasing order.
+pub struct BitSetIter<'a> {
+	words: &'a [u64],
+	offset: usize,
+	current: u64,
+}
+
+impl<'a> BitSetIter<'a> {
+	fn new(words: &'a [u64]) -> Self {
+		Self {
+			words,
+			offset: 0,
+			current: words.first().copied().unwrap_or(0),
+		}
+	}
+}
+
+impl<'a> Iterator for BitSetIter<'a> {
+	type Item = u8;
+
+	fn next(&mut self) -> Option<u8> {
+		while self.current == 0 {
+			self.offset += 1;
+			self.current = *self.words.get(self.offset)?;
+		}
+
+		let bit = self.current.trailing_zeros() as usize;
+		self.current &= self.current - 1;
+		Some((self.offset * 64 + bit) as u8)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ArrayList, ArrayMap};
+	use crate::Map;
+	use std::panic::{catch_unwind, AssertUnwindSafe};
+	use std::rc::Rc;
+
+	#[test]
+	fn drop_values() {
+		let value = Rc::new(());
+		let mut map: ArrayMap<Rc<()>, 64> = [(3, value.clone()), (40, value.clone())]
+			.into_iter()
+			.collect();
+		assert_eq!(map.insert(3, value.clone()).map(|_| ()), Some(()));
+		assert_eq!(Rc::strong_count(&value), 3);
+
+		let copy = map.clone();
+		assert_eq!(copy, map);
+		assert_eq!(Rc::strong_count(&value), 5);
+
+		drop(copy);
+		drop(map);
+		assert_eq!(Rc::strong_count(&value), 1)
+	}
+
+	#[test]
+	fn panicking_map() {
+		let value = Rc::new(());
+		let mut map: ArrayMap<Rc<()>, 256> = (0..10).map(|k| (k, value.clone())).collect();
+		let result = catch_unwind(AssertUnwindSafe(|| {
+			map.map(|k, v| if *k == 5 { panic!() } else { v })
+		}));
+		assert!(result.is_err());
+
+		// The value being mapped is dropped exactly once.
+		assert_eq!(map.len(), 9);
+		assert_eq!(Rc::strong_count(&value), 10);
+		drop(map);
+		assert_eq!(Rc::strong_count(&value), 1)
+	}
+
+	#[test]
+	fn list() {
+		let value = Rc::new(());
+		let list: ArrayList<Rc<()>, 4> = (0..3).map(|_| value.clone()).collect();
+		assert_eq!(list.as_ref().len(), 3);
+
+		let copy = list.clone();
+		assert_eq!(copy, list);
+		assert_eq!(Rc::strong_count(&value), 7);
+
+		drop(copy);
+		drop(list);
+		assert_eq!(Rc::strong_count(&value), 1);
+
+		// The capacity is checked.
+		assert!(catch_unwind(|| (0..5).collect::<ArrayList<u8, 4>>()).is_err())
+	}
+}
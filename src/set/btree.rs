@@ -0,0 +1,57 @@
+use super::{Map, Set};
+use std::collections::{BTreeMap, BTreeSet};
+
+impl<T: Clone + Ord> Set for BTreeSet<T> {
+	type Item = T;
+
+	type Map<V> = BTreeMap<T, V>;
+
+	type List<U: Clone + Eq> = Vec<U>;
+
+	type Iter<'a>
+		= std::iter::Cloned<std::collections::btree_set::Iter<'a, T>>
+	where
+		T: 'a;
+
+	fn len(&self) -> usize {
+		self.len()
+	}
+
+	fn iter(&self) -> Self::Iter<'_> {
+		self.iter().cloned()
+	}
+
+	fn map<V: Clone, F>(&self, f: F) -> Self::Map<V>
+	where
+		F: Fn(&Self::Item) -> V,
+	{
+		self.iter().map(|t| (t.clone(), f(t))).collect()
+	}
+}
+
+impl<K: Ord, T> Map<K, T> for BTreeMap<K, T> {
+	fn len(&self) -> usize {
+		self.len()
+	}
+
+	fn get(&self, key: &K) -> Option<&T> {
+		self.get(key)
+	}
+
+	fn set(&mut self, key: &K, value: T) {
+		*self.get_mut(key).unwrap() = value
+	}
+
+	fn map<F>(&mut self, f: F)
+	where
+		F: Fn(&K, T) -> T,
+	{
+		*self = std::mem::take(self)
+			.into_iter()
+			.map(|(k, v)| {
+				let v = f(&k, v);
+				(k, v)
+			})
+			.collect()
+	}
+}
@@ -0,0 +1,61 @@
+use super::{Map, Set};
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasher, Hash};
+
+/// Hash set, iterated in sorted order.
+impl<T: Clone + Ord + Hash, H: BuildHasher + Default> Set for HashSet<T, H> {
+	type Item = T;
+
+	type Map<V> = HashMap<T, V, H>;
+
+	type List<U: Clone + Eq> = Vec<U>;
+
+	type Iter<'a>
+		= std::vec::IntoIter<T>
+	where
+		Self: 'a;
+
+	fn len(&self) -> usize {
+		self.len()
+	}
+
+	fn iter(&self) -> Self::Iter<'_> {
+		let mut items: Vec<_> = HashSet::iter(self).cloned().collect();
+		items.sort_unstable();
+		items.into_iter()
+	}
+
+	fn map<V: Clone, F>(&self, f: F) -> Self::Map<V>
+	where
+		F: Fn(&Self::Item) -> V,
+	{
+		HashSet::iter(self).map(|t| (t.clone(), f(t))).collect()
+	}
+}
+
+impl<K: Eq + Hash, T, H: BuildHasher + Default> Map<K, T> for HashMap<K, T, H> {
+	fn len(&self) -> usize {
+		self.len()
+	}
+
+	fn get(&self, key: &K) -> Option<&T> {
+		self.get(key)
+	}
+
+	fn set(&mut self, key: &K, value: T) {
+		*self.get_mut(key).unwrap() = value
+	}
+
+	fn map<F>(&mut self, f: F)
+	where
+		F: Fn(&K, T) -> T,
+	{
+		*self = std::mem::take(self)
+			.into_iter()
+			.map(|(k, v)| {
+				let v = f(&k, v);
+				(k, v)
+			})
+			.collect()
+	}
+}
@@ -0,0 +1,84 @@
+use super::Set;
+use std::sync::Arc;
+
+/// Sparse set of identifiers, interned with dense indexes.
+///
+/// The identifiers are sorted once, and each identifier is assigned its rank
+/// as index. The permuted elements are these indexes, so that the search
+/// works on vectors. This is useful when only some of the identifiers of a
+/// structure are permutable (for instance the blank nodes of an RDF graph).
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Interned<T> {
+	items: Arc<[T]>,
+}
+
+impl<T: Ord> Interned<T> {
+	/// Creates a new interned set from the given identifiers.
+	///
+	/// Duplicate identifiers are removed.
+	pub fn new(items: impl IntoIterator<Item = T>) -> Self {
+		let mut items: Vec<_> = items.into_iter().collect();
+		items.sort_unstable();
+		items.dedup();
+		Self {
+			items: items.into(),
+		}
+	}
+
+	/// Returns the index of the given identifier, if it belongs to the set.
+	pub fn index_of(&self, item: &T) -> Option<usize> {
+		self.items.binary_search(item).ok()
+	}
+
+	/// Returns the identifier of the given index.
+	pub fn get(&self, index: usize) -> Option<&T> {
+		self.items.get(index)
+	}
+
+	/// Returns the sorted identifiers.
+	pub fn as_slice(&self) -> &[T] {
+		&self.items
+	}
+
+	/// Checks if the given identifier belongs to the set.
+	pub fn contains(&self, item: &T) -> bool {
+		self.index_of(item).is_some()
+	}
+}
+
+impl<T: Ord> FromIterator<T> for Interned<T> {
+	fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+		Self::new(iter)
+	}
+}
+
+/// The items of the set are the indexes of the identifiers, so that maps are
+/// vectors indexed directly. Identifiers are resolved to indexes once, with
+/// [`Interned::index_of`], before the search.
+impl<T: Ord> Set for Interned<T> {
+	type Item = usize;
+
+	type Map<V> = Vec<V>;
+
+	type List<U: Clone + Eq> = Vec<U>;
+
+	type Iter<'a>
+		= std::ops::Range<usize>
+	where
+		T: 'a;
+
+	fn len(&self) -> usize {
+		self.items.len()
+	}
+
+	fn iter(&self) -> Self::Iter<'_> {
+		0..self.items.len()
+	}
+
+	fn map<V: Clone, F>(&self, f: F) -> Self::Map<V>
+	where
+		F: Fn(&Self::Item) -> V,
+	{
+		(0..self.items.len()).map(|i| f(&i)).collect()
+	}
+}
@@ -0,0 +1,117 @@
+//! Slices and vectors of distinct items.
+//!
+//! Items are identified by value, so duplicates would share the same color
+//! and map entry, and silently produce wrong canonical forms. Distinctness
+//! is only checked in debug builds.
+use super::Set;
+use std::collections::BTreeMap;
+
+/// Checks that the given items are distinct.
+fn all_distinct<T: Ord>(items: &[T]) -> bool {
+	let mut sorted: Vec<_> = items.iter().collect();
+	sorted.sort_unstable();
+	sorted.windows(2).all(|w| w[0] != w[1])
+}
+
+/// Slice of distinct items.
+impl<T: Clone + Ord> Set for [T] {
+	type Item = T;
+
+	type Map<V> = BTreeMap<T, V>;
+
+	type List<U: Clone + Eq> = Vec<U>;
+
+	type Iter<'a>
+		= std::iter::Cloned<std::slice::Iter<'a, T>>
+	where
+		T: 'a;
+
+	fn len(&self) -> usize {
+		self.len()
+	}
+
+	fn iter(&self) -> Self::Iter<'_> {
+		<[T]>::iter(self).cloned()
+	}
+
+	fn map<V: Clone, F>(&self, f: F) -> Self::Map<V>
+	where
+		F: Fn(&Self::Item) -> V,
+	{
+		let map: BTreeMap<_, _> = <[T]>::iter(self).map(|t| (t.clone(), f(t))).collect();
+		debug_assert_eq!(map.len(), self.len(), "duplicate items in slice set");
+		map
+	}
+}
+
+/// Vector of distinct items.
+///
+/// Vectors do not implement [`Set`] directly, otherwise calling `iter` on any
+/// vector while the trait is in scope would resolve to [`Set::iter`] instead
+/// of the inherent slice iterator, and break unrelated code. Wrapping the
+/// vector keeps the two apart, and gives a place to check that the items are
+/// distinct.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct VecSet<T>(Vec<T>);
+
+impl<T: Ord> VecSet<T> {
+	/// Creates a new set from the given items, which must be distinct.
+	///
+	/// # Panics
+	///
+	/// In debug builds, panics if two items are equal.
+	pub fn new(items: Vec<T>) -> Self {
+		debug_assert!(all_distinct(&items), "duplicate items in `VecSet`");
+		Self(items)
+	}
+}
+
+impl<T> VecSet<T> {
+	pub fn as_slice(&self) -> &[T] {
+		&self.0
+	}
+
+	pub fn into_vec(self) -> Vec<T> {
+		self.0
+	}
+}
+
+impl<T: Ord> From<Vec<T>> for VecSet<T> {
+	fn from(items: Vec<T>) -> Self {
+		Self::new(items)
+	}
+}
+
+impl<T: Ord> FromIterator<T> for VecSet<T> {
+	fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+		Self::new(iter.into_iter().collect())
+	}
+}
+
+impl<T: Clone + Ord> Set for VecSet<T> {
+	type Item = T;
+
+	type Map<V> = BTreeMap<T, V>;
+
+	type List<U: Clone + Eq> = Vec<U>;
+
+	type Iter<'a>
+		= std::iter::Cloned<std::slice::Iter<'a, T>>
+	where
+		T: 'a;
+
+	fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	fn iter(&self) -> Self::Iter<'_> {
+		self.0.iter().cloned()
+	}
+
+	fn map<V: Clone, F>(&self, f: F) -> Self::Map<V>
+	where
+		F: Fn(&Self::Item) -> V,
+	{
+		Set::map(self.as_slice(), f)
+	}
+}
@@ -6,13 +6,12 @@ macro_rules! natural_set {
 			impl Set for $ty {
 				type Item = $ty;
 
-				/// Map type, binding each item of the graph to a value `V`.
-				///
-				/// ## Example
-				///
-				/// `Vec<V>`.
+				/// Map type, binding each item of the set to a value `V`, stored in a
+				/// vector indexed by item.
 				type Map<V> = Vec<V>;
 
+				type List<U: Clone + Eq> = Vec<U>;
+
 				/// Items iterator.
 				type Iter<'a> = std::ops::Range<$ty>;
 
@@ -55,16 +54,15 @@ macro_rules! natural_set {
 				where
 					F: Fn(&$ty, T) -> T,
 				{
-					for (i, v) in self.iter_mut().enumerate() {
-						unsafe {
-							let t = std::ptr::read(v);
-							std::ptr::write(v, f(&(i as $ty), t));
-						}
-					}
+					*self = std::mem::take(self)
+						.into_iter()
+						.enumerate()
+						.map(|(i, v)| f(&(i as $ty), v))
+						.collect()
 				}
 			}
 		)*
 	};
 }
 
-natural_set!(u32, u64, usize);
+natural_set!(u16, u32, u64, usize);
@@ -0,0 +1,70 @@
+//! Structures with two sides of elements that isomorphisms may swap, such
+//! as the rows and columns of a transposable matrix.
+use crate::{search, Normalize};
+
+/// Structure whose elements are split in two sides, the first
+/// `first_side_len` elements and the others, that isomorphisms may swap.
+pub(crate) trait Sides: Normalize<Elements = usize> + Sized {
+	/// Number of elements of the first side.
+	fn first_side_len(&self) -> usize;
+
+	/// Whether isomorphisms may swap the sides.
+	fn swappable_sides(&self) -> bool;
+
+	/// Returns the same structure with the sides swapped, each side keeping
+	/// the order of its elements.
+	fn swap_sides(&self) -> Self;
+
+	/// Checks if the given morphism swaps the sides, in which case the
+	/// morphed structure is the one of the swapped structure.
+	///
+	/// This is the case if sides are swappable and the morphism maps the
+	/// first side after the second one, as the canonical permutation does
+	/// when the canonical form is the one of the swapped structure. If the
+	/// second side is empty, nothing tells the sides apart, so morphisms
+	/// always swap them and the empty side comes first.
+	fn swaps_sides<F: Fn(&usize) -> usize>(&self, f: F) -> bool {
+		let first_len = self.first_side_len();
+		self.swappable_sides()
+			&& (first_len == *self.elements() || (0..first_len).any(|x| f(&x) >= first_len))
+	}
+
+	/// Maps the elements of this structure to the elements of the structure
+	/// with the sides swapped.
+	fn to_swapped(&self, x: usize) -> usize {
+		let first_len = self.first_side_len();
+		if x < first_len {
+			self.elements() - first_len + x
+		} else {
+			x - first_len
+		}
+	}
+}
+
+/// Canonizes the structure. If sides are swappable, this is the smallest
+/// canonical form of the structure and of its swapped structure, and the
+/// canonical permutation then swaps the sides (see [`Sides::swaps_sides`]).
+pub(crate) fn normalize<T: Sides>(t: &T) -> (T::Morphed, Vec<usize>) {
+	let result = search(t, false);
+	if t.swappable_sides() {
+		let swapped = search(&t.swap_sides(), false);
+		if swapped.morphed < result.morphed {
+			let permutation = (0..*t.elements())
+				.map(|x| swapped.permutation[t.to_swapped(x)])
+				.collect();
+			return (swapped.morphed, permutation);
+		}
+	}
+
+	(result.morphed, result.permutation)
+}
+
+/// Checks if the structure is isomorphic to its swapped structure, by a
+/// morphism that swaps the sides.
+///
+/// Such a morphism is not an automorphism of the structure, but combined
+/// with them, it gives a group twice as large.
+pub(crate) fn is_self_dual<T: Sides>(t: &T) -> bool {
+	t.first_side_len() * 2 == *t.elements()
+		&& search(t, false).morphed == search(&t.swap_sides(), false).morphed
+}
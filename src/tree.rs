@@ -80,35 +80,53 @@ impl<S: Set + ?Sized> Node<S> {
 		self
 	}
 
-	pub fn into_next_leaf<F>(mut self, mut refine: F) -> Option<Self>
+	/// Moves to the next leaf, in depth-first order.
+	///
+	/// Before moving to a sibling `child` of a node, `skip(node, child)` is
+	/// called. If it returns `true`, the sibling is skipped without being
+	/// individualized. After each individualization, `prune` is called on the
+	/// new node. If it returns `true`, the subtree of the node is skipped.
+	pub fn into_next_leaf<F, K, P>(
+		mut self,
+		mut refine: F,
+		mut skip: K,
+		mut prune: P,
+	) -> Option<Self>
 	where
 		F: FnMut(&mut ReversibleColoring<S>),
+		K: FnMut(&Self, &S::Item) -> bool,
+		P: FnMut(&Self) -> bool,
 	{
 		debug_assert_eq!(self.path.len(), self.coloring.depth());
-		let last = self.path.pop()?;
-		self.coloring.restore(1); // undo individualization & refinement.
-
-		let color_index = self.coloring.color_index_of(&last).unwrap();
-		let color = self.coloring.get(color_index).unwrap();
-		let next_sibling_index = color.binary_search(&last).unwrap() + 1;
-		match color.get(next_sibling_index) {
-			Some(next_sibling) => {
-				// move to next sibling...
-				let next_sibling = next_sibling.clone();
-				self.individualize(next_sibling, &mut refine);
-
-				while let Some(color) = self.children_color() {
-					// ...then move to leaf
-					let child = color[0].clone();
-					self.individualize(child, &mut refine)
+		let mut descend = false;
+
+		loop {
+			if descend {
+				match self.children_color() {
+					Some(color) => {
+						// move to first child...
+						let child = color[0].clone();
+						self.individualize(child, &mut refine);
+						descend = !prune(&self)
+					}
+					None => break Some(self),
+				}
+			} else {
+				let last = self.path.pop()?;
+				self.coloring.restore(1); // undo individualization & refinement.
+
+				let color = self.coloring.cell_of(&last).unwrap();
+				let next_sibling_index = color.binary_search(&last).unwrap() + 1;
+				let next_sibling = color[next_sibling_index..]
+					.iter()
+					.find(|sibling| !skip(&self, sibling))
+					.cloned();
+
+				if let Some(next_sibling) = next_sibling {
+					// move to next sibling, otherwise to the parent node.
+					self.individualize(next_sibling, &mut refine);
+					descend = !prune(&self)
 				}
-
-				Some(self)
-			}
-			None => {
-				// move to parent node...
-				debug_assert_eq!(self.path.len(), self.coloring.depth());
-				self.into_next_leaf(refine) // ...then move to sibling leaf
 			}
 		}
 	}
@@ -0,0 +1,665 @@
+//! Labeled trees.
+//!
+//! Trees can be canonized without searching, using the
+//! Aho–Hopcroft–Ullman (AHU) encoding: each subtree is given a code from its
+//! label and the sorted codes of its children, and the canonical
+//! permutation is the preorder traversal of the tree where children are
+//! visited by increasing code. Unrooted trees are rooted at their center, or
+//! at the smallest of their two centers. Once labels are sorted, this takes
+//! linear time, where the generic search may take exponential time on
+//! symmetric trees. Automorphism generators and orbits are also derived
+//! from the encoding.
+//!
+//! ```
+//! use normal_form::{trees::Tree, Normalize};
+//!
+//! // A star with three leaves, labeled from different centers.
+//! let a = Tree::new(vec!['a'; 4], [(0, 1), (0, 2), (0, 3)]);
+//! let b = Tree::new(vec!['a'; 4], [(3, 0), (3, 1), (3, 2)]);
+//! assert_eq!(a.normal_form(), b.normal_form());
+//! assert_eq!(a.automorphism_group_order().to_u128(), Some(6));
+//! ```
+use crate::{GroupOrder, Normalize, ReversibleColoring};
+use std::collections::{HashMap, VecDeque};
+
+/// Labeled tree, possibly rooted.
+#[derive(Clone, Debug)]
+pub struct Tree<L> {
+	/// Number of nodes.
+	len: usize,
+
+	labels: Vec<L>,
+	neighbors: Vec<Vec<usize>>,
+	root: Option<usize>,
+}
+
+impl<L> Tree<L> {
+	/// Creates an unrooted tree from its node labels and edges.
+	///
+	/// # Panics
+	///
+	/// Panics if the edges do not form a tree over the nodes
+	/// `0..labels.len()`.
+	pub fn new(labels: Vec<L>, edges: impl IntoIterator<Item = (usize, usize)>) -> Self {
+		let n = labels.len();
+		let mut neighbors = vec![Vec::new(); n];
+		let mut edge_count = 0;
+		for (a, b) in edges {
+			neighbors[a].push(b);
+			neighbors[b].push(a);
+			edge_count += 1
+		}
+
+		let result = Self {
+			len: n,
+			labels,
+			neighbors,
+			root: None,
+		};
+
+		assert!(
+			n == 0 || (edge_count == n - 1 && result.bfs(&[0]).0.len() == n),
+			"not a tree"
+		);
+		result
+	}
+
+	/// Creates a tree rooted at `root`.
+	///
+	/// # Panics
+	///
+	/// Panics if the edges do not form a tree over the nodes
+	/// `0..labels.len()`, or if `root` is not a node.
+	pub fn rooted(
+		labels: Vec<L>,
+		edges: impl IntoIterator<Item = (usize, usize)>,
+		root: usize,
+	) -> Self {
+		assert!(root < labels.len());
+		let mut result = Self::new(labels, edges);
+		result.root = Some(root);
+		result
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	pub fn root(&self) -> Option<usize> {
+		self.root
+	}
+
+	pub fn labels(&self) -> &[L] {
+		&self.labels
+	}
+
+	pub fn neighbors(&self, node: usize) -> &[usize] {
+		&self.neighbors[node]
+	}
+
+	/// Breadth-first traversal from the given roots, ignoring the edges
+	/// between roots.
+	///
+	/// Returns the nodes in traversal order, and the parent of each node.
+	fn bfs(&self, roots: &[usize]) -> (Vec<usize>, Vec<Option<usize>>) {
+		let n = self.labels.len();
+		let mut visited = vec![false; n];
+		let mut parents = vec![None; n];
+		let mut order = Vec::with_capacity(n);
+		let mut queue: VecDeque<_> = roots.iter().copied().collect();
+		for &r in roots {
+			visited[r] = true
+		}
+
+		while let Some(x) = queue.pop_front() {
+			order.push(x);
+			for &y in &self.neighbors[x] {
+				if !visited[y] {
+					visited[y] = true;
+					parents[y] = Some(x);
+					queue.push_back(y)
+				}
+			}
+		}
+
+		(order, parents)
+	}
+
+	/// Returns the one or two centers of the tree.
+	fn centers(&self) -> Vec<usize> {
+		let n = self.labels.len();
+		let mut degrees: Vec<_> = self.neighbors.iter().map(Vec::len).collect();
+		let mut leaves: Vec<_> = (0..n).filter(|&x| degrees[x] <= 1).collect();
+		let mut remaining = n;
+		while remaining > 2 {
+			remaining -= leaves.len();
+			let mut next = Vec::new();
+			for &x in &leaves {
+				for &y in &self.neighbors[x] {
+					degrees[y] -= 1;
+					if degrees[y] == 1 {
+						next.push(y)
+					}
+				}
+			}
+			leaves = next
+		}
+
+		leaves
+	}
+}
+
+impl<L: Clone + Ord> Tree<L> {
+	/// Computes the AHU encoding of the tree.
+	///
+	/// Labels are ranked once, then the nodes of each depth, from the
+	/// deepest, are ranked by label and children codes in linear time.
+	fn encode(&self) -> Encoding {
+		let n = self.labels.len();
+		let roots = match self.root {
+			Some(root) => vec![root],
+			None if n == 0 => Vec::new(),
+			None => self.centers(),
+		};
+
+		let (order, parents) = self.bfs(&roots);
+		let mut depths = vec![0; n];
+		let mut levels: Vec<Vec<usize>> = Vec::new();
+		for &x in &order {
+			if let Some(p) = parents[x] {
+				depths[x] = depths[p] + 1
+			}
+
+			if levels.len() <= depths[x] {
+				levels.push(Vec::new())
+			}
+		}
+
+		// Rank of each label, and nodes of each depth by label rank.
+		let mut by_label: Vec<_> = (0..n).collect();
+		by_label.sort_by(|&a, &b| self.labels[a].cmp(&self.labels[b]));
+		let mut label_ranks = vec![0; n];
+		let mut label_count = 0;
+		for (i, &x) in by_label.iter().enumerate() {
+			if i > 0 && self.labels[x] != self.labels[by_label[i - 1]] {
+				label_count += 1
+			}
+			label_ranks[x] = label_count;
+			levels[depths[x]].push(x)
+		}
+		let mut label_buckets = vec![Vec::new(); label_count + 1];
+
+		// The code of a node is the rank of its label and children codes
+		// among the nodes of the same depth.
+		let mut codes = vec![0; n];
+		let mut children = vec![Vec::new(); n];
+		let mut child_codes: Vec<Vec<usize>> = vec![Vec::new(); n];
+		let mut ranked: Vec<usize> = Vec::new();
+		for level in levels.iter().rev() {
+			// Children are visited by increasing code.
+			for &x in &ranked {
+				let p = parents[x].unwrap();
+				children[p].push(x);
+				child_codes[p].push(codes[x])
+			}
+
+			let alphabet = ranked.len();
+			ranked.clear();
+			for x in lex_sort(level, &child_codes, alphabet) {
+				label_buckets[label_ranks[x]].push(x)
+			}
+
+			for (i, &x) in level.iter().enumerate() {
+				if i == 0 || label_ranks[x] != label_ranks[level[i - 1]] {
+					ranked.append(&mut label_buckets[label_ranks[x]])
+				}
+			}
+
+			let mut rank = 0;
+			for i in 1..ranked.len() {
+				let (x, y) = (ranked[i - 1], ranked[i]);
+				if label_ranks[x] != label_ranks[y] || child_codes[x] != child_codes[y] {
+					rank += 1
+				}
+				codes[y] = rank
+			}
+		}
+
+		// With two centers, the tree is rooted at the smallest, and the other
+		// one becomes its first child.
+		let root = roots.iter().copied().min_by_key(|&r| codes[r]);
+		let symmetric_centers = matches!(roots[..], [a, b] if codes[a] == codes[b]);
+		let mut other_center = None;
+		if let [a, b] = roots[..] {
+			let (root, other) = if codes[a] <= codes[b] { (a, b) } else { (b, a) };
+			children[root].insert(0, other);
+			other_center = Some(other)
+		}
+
+		Encoding {
+			root,
+			children,
+			codes,
+			other_center,
+			symmetric_centers,
+		}
+	}
+
+	/// Returns the canonical permutation of the tree, the preorder traversal
+	/// with children sorted by code.
+	fn ahu_permutation(&self, encoding: &Encoding) -> Vec<usize> {
+		let mut permutation = vec![0; self.labels.len()];
+		for (i, x) in encoding.preorder().into_iter().enumerate() {
+			permutation[x] = i
+		}
+
+		permutation
+	}
+}
+
+/// Sorts the given nodes lexicographically by their string, made of
+/// symbols lower than `alphabet`, in `O(alphabet + total length)` time.
+///
+/// This is the algorithm of Aho, Hopcroft and Ullman: strings are
+/// distributed in buckets by symbol from their last position to the first,
+/// and only the non-empty buckets of each position are visited.
+fn lex_sort(nodes: &[usize], strings: &[Vec<usize>], alphabet: usize) -> Vec<usize> {
+	let len = nodes.iter().map(|&x| strings[x].len()).max().unwrap_or(0);
+	let mut by_len = vec![Vec::new(); len + 1];
+	for &x in nodes {
+		by_len[strings[x].len()].push(x)
+	}
+
+	// Sorted symbols appearing at each position.
+	let mut buckets = vec![Vec::new(); alphabet];
+	for &x in nodes {
+		for (i, &s) in strings[x].iter().enumerate() {
+			buckets[s].push(i)
+		}
+	}
+	let mut symbols = vec![Vec::new(); len];
+	for (s, positions) in buckets.iter_mut().enumerate() {
+		for i in positions.drain(..) {
+			if symbols[i].last() != Some(&s) {
+				symbols[i].push(s)
+			}
+		}
+	}
+
+	// Shorter strings come before the longer strings they prefix.
+	let mut queue = Vec::new();
+	for i in (0..len).rev() {
+		let mut next = std::mem::take(&mut by_len[i + 1]);
+		next.append(&mut queue);
+		for x in next {
+			buckets[strings[x][i]].push(x)
+		}
+
+		for &s in &symbols[i] {
+			queue.append(&mut buckets[s])
+		}
+	}
+
+	let mut result = std::mem::take(&mut by_len[0]);
+	result.append(&mut queue);
+	result
+}
+
+/// AHU encoding of a tree.
+struct Encoding {
+	root: Option<usize>,
+
+	/// Children of each node, sorted by code.
+	children: Vec<Vec<usize>>,
+
+	/// Code of each node, among the nodes of the same depth.
+	codes: Vec<usize>,
+
+	/// Second center of the tree, first child of the root.
+	other_center: Option<usize>,
+
+	/// The tree has two centers with the same code, which can be swapped.
+	symmetric_centers: bool,
+}
+
+impl Encoding {
+	/// Returns the nodes in preorder, visiting children by increasing code.
+	fn preorder(&self) -> Vec<usize> {
+		let mut result = Vec::with_capacity(self.children.len());
+		let mut stack: Vec<_> = self.root.into_iter().collect();
+		while let Some(x) = stack.pop() {
+			result.push(x);
+			stack.extend(self.children[x].iter().rev())
+		}
+
+		result
+	}
+
+	/// Returns the number of nodes of each subtree.
+	fn sizes(&self, preorder: &[usize]) -> Vec<usize> {
+		let mut sizes = vec![1; self.children.len()];
+		for &x in preorder.iter().rev() {
+			for &c in &self.children[x] {
+				sizes[x] += sizes[c]
+			}
+		}
+
+		sizes
+	}
+
+	/// Calls `f` with each run of children of `x` with the same code, which
+	/// can be permuted.
+	///
+	/// The other center is not a sibling of the children of the root.
+	fn for_each_run<F>(&self, x: usize, mut f: F)
+	where
+		F: FnMut(&[usize]),
+	{
+		let children = match self.children[x].split_first() {
+			Some((&first, rest)) if Some(first) == self.other_center => rest,
+			_ => &self.children[x],
+		};
+
+		let mut i = 0;
+		while i < children.len() {
+			let code = self.codes[children[i]];
+			let count = children[i..]
+				.iter()
+				.take_while(|&&c| self.codes[c] == code)
+				.count();
+			f(&children[i..(i + count)]);
+			i += count
+		}
+	}
+}
+
+/// Canonical form of a [`Tree`].
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct MorphedTree<L> {
+	pub root: Option<usize>,
+
+	/// Label of each node.
+	pub labels: Vec<L>,
+
+	/// Sorted edges, each edge `(a, b)` being such that `a < b`.
+	pub edges: Vec<(usize, usize)>,
+}
+
+impl<L: Clone + Ord> Normalize for Tree<L> {
+	type Elements = usize;
+
+	/// Node label, and whether it is the root.
+	type Color = (bool, L);
+
+	type Cache = ();
+
+	type Morphed = MorphedTree<L>;
+
+	fn initialize_cache(&self) {}
+
+	fn elements(&self) -> &usize {
+		&self.len
+	}
+
+	fn initial_coloring(&self) -> Vec<(bool, L)> {
+		self.labels
+			.iter()
+			.enumerate()
+			.map(|(x, l)| (self.root != Some(x), l.clone()))
+			.collect()
+	}
+
+	fn refine_coloring(&self, _cache: &mut (), coloring: &mut ReversibleColoring<usize>) {
+		coloring.make_equitable(&self.len, |x| &self.neighbors[*x])
+	}
+
+	fn apply_morphism<F>(&self, f: F) -> MorphedTree<L>
+	where
+		F: Fn(&usize) -> usize,
+	{
+		let n = self.labels.len();
+		let mut labels = vec![None; n];
+		for (x, l) in self.labels.iter().enumerate() {
+			labels[f(&x)] = Some(l.clone())
+		}
+
+		let mut edges: Vec<_> = self
+			.neighbors
+			.iter()
+			.enumerate()
+			.flat_map(|(x, neighbors)| {
+				neighbors
+					.iter()
+					.filter(move |&&y| x < y)
+					.map(move |&y| (x, y))
+			})
+			.map(|(x, y)| {
+				let (a, b) = (f(&x), f(&y));
+				(a.min(b), a.max(b))
+			})
+			.collect();
+		edges.sort_unstable();
+
+		MorphedTree {
+			root: self.root.map(|r| f(&r)),
+			labels: labels.into_iter().map(Option::unwrap).collect(),
+			edges,
+		}
+	}
+
+	/// Computes the canonical form with the AHU encoding.
+	fn normalize(&self) -> (MorphedTree<L>, Vec<usize>) {
+		let permutation = self.ahu_permutation(&self.encode());
+		(self.apply_morphism(|x| permutation[*x]), permutation)
+	}
+
+	fn normal_form(&self) -> MorphedTree<L> {
+		self.normalize().0
+	}
+
+	fn canonical_permutation(&self) -> Vec<usize> {
+		self.ahu_permutation(&self.encode())
+	}
+
+	/// Computes the automorphism group order from the AHU encoding: children
+	/// with the same code can be permuted.
+	fn automorphism_group_order(&self) -> GroupOrder {
+		let encoding = self.encode();
+		let mut order = GroupOrder::trivial();
+		for x in 0..self.len {
+			encoding.for_each_run(x, |run| {
+				for f in 2..=run.len() {
+					order.push(f)
+				}
+			})
+		}
+
+		if encoding.symmetric_centers {
+			order.push(2)
+		}
+
+		order
+	}
+
+	/// Computes generators of the automorphism group from the AHU encoding:
+	/// each one swaps two consecutive sibling subtrees with the same code, or
+	/// the two sides of symmetric centers.
+	fn automorphisms(&self) -> Vec<Vec<usize>> {
+		let encoding = self.encode();
+		let preorder = encoding.preorder();
+		let sizes = encoding.sizes(&preorder);
+		let mut positions = vec![0; self.len];
+		for (i, &x) in preorder.iter().enumerate() {
+			positions[x] = i
+		}
+
+		// Swaps the preorder ranges starting at `a` and `b`, of length `len`.
+		let swap = |a: usize, b: usize, len: usize| {
+			let mut g: Vec<_> = (0..self.len).collect();
+			for k in 0..len {
+				let (x, y) = (preorder[a + k], preorder[b + k]);
+				g[x] = y;
+				g[y] = x
+			}
+			g
+		};
+
+		let mut result = Vec::new();
+		for x in 0..self.len {
+			encoding.for_each_run(x, |run| {
+				for pair in run.windows(2) {
+					let (a, b) = (positions[pair[0]], positions[pair[1]]);
+					result.push(swap(a, b, sizes[pair[0]]))
+				}
+			})
+		}
+
+		if encoding.symmetric_centers {
+			// The root is followed by the other center and its subtree, then
+			// by the rest of its own side.
+			let half = self.len / 2;
+			let mut g: Vec<_> = (0..self.len).collect();
+			let side = std::iter::once(0).chain((half + 1)..self.len);
+			for (a, b) in side.zip(1..=half) {
+				let (x, y) = (preorder[a], preorder[b]);
+				g[x] = y;
+				g[y] = x
+			}
+			result.push(g)
+		}
+
+		result
+	}
+
+	/// Computes the orbits from the AHU encoding: the orbit of a node is
+	/// given by the orbit of its parent and its code.
+	fn automorphism_orbits(&self) -> Vec<usize> {
+		let encoding = self.encode();
+		let mut classes = vec![0; self.len];
+		let mut class_indexes = HashMap::new();
+		for x in encoding.preorder() {
+			for &c in &encoding.children[x] {
+				// Symmetric centers share the orbit of the root, and the other
+				// center is not a sibling of the children of the root.
+				let is_center = Some(c) == encoding.other_center;
+				classes[c] = if is_center && encoding.symmetric_centers {
+					classes[x]
+				} else {
+					let next = class_indexes.len() + 1;
+					*class_indexes
+						.entry((classes[x], is_center, encoding.codes[c]))
+						.or_insert(next)
+				}
+			}
+		}
+
+		let mut smallest = vec![usize::MAX; class_indexes.len() + 1];
+		for x in 0..self.len {
+			smallest[classes[x]] = smallest[classes[x]].min(x)
+		}
+
+		classes.into_iter().map(|c| smallest[c]).collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Tree;
+	use crate::{group::Group, search, Normalize};
+	use rand::{seq::SliceRandom, Rng};
+
+	/// Random tree with labels in `0..label_count`, rooted or not.
+	fn random_tree(n: usize, label_count: u8, rooted: bool) -> Tree<u8> {
+		let mut rng = rand::thread_rng();
+		let labels = (0..n).map(|_| rng.gen_range(0..label_count)).collect();
+		let edges: Vec<_> = (1..n).map(|x| (rng.gen_range(0..x), x)).collect();
+		if rooted {
+			Tree::rooted(labels, edges, rng.gen_range(0..n))
+		} else {
+			Tree::new(labels, edges)
+		}
+	}
+
+	fn random_relabeling(tree: &Tree<u8>) -> Tree<u8> {
+		let n = tree.len();
+		let mut permutation: Vec<_> = (0..n).collect();
+		permutation.shuffle(&mut rand::thread_rng());
+
+		let mut labels = vec![0; n];
+		for (x, l) in tree.labels().iter().enumerate() {
+			labels[permutation[x]] = *l
+		}
+
+		let mut edges = Vec::new();
+		for x in 0..n {
+			for &y in tree.neighbors(x).iter().filter(|&&y| x < y) {
+				edges.push((permutation[x], permutation[y]))
+			}
+		}
+
+		match tree.root() {
+			Some(root) => Tree::rooted(labels, edges, permutation[root]),
+			None => Tree::new(labels, edges),
+		}
+	}
+
+	#[test]
+	fn random() {
+		for rooted in [false, true] {
+			for _ in 0..200 {
+				let n = rand::thread_rng().gen_range(1..12);
+				let a = random_tree(n, 2, rooted);
+				let (form, permutation) = a.normalize();
+				assert_eq!(a.apply_morphism(|x| permutation[*x]), form);
+
+				let b = random_relabeling(&a);
+				assert_eq!(b.normal_form(), form);
+
+				// Consistent with the generic search.
+				let result = search(&a, false);
+				assert_eq!(
+					a.automorphism_group_order().to_u128(),
+					result.order.to_u128()
+				);
+
+				// Generators and orbits describe the same group.
+				let automorphisms = a.automorphisms();
+				for g in &automorphisms {
+					assert_eq!(a.apply_morphism(|x| g[*x]), a.apply_morphism(|x| *x))
+				}
+				let group = Group::new(&n, automorphisms);
+				assert_eq!(group.order().to_u128(), result.order.to_u128());
+				assert_eq!(a.automorphism_orbits(), result.orbits);
+
+				let c = random_tree(n, 2, rooted);
+				assert_eq!(
+					c.normal_form() == form,
+					search(&c, false).morphed == result.morphed
+				)
+			}
+		}
+	}
+
+	#[test]
+	fn symmetric() {
+		// Complete binary tree of depth 12.
+		let n = (1 << 13) - 1;
+		let edges = (1..n).map(|x| ((x - 1) / 2, x));
+		let tree = Tree::rooted(vec![(); n], edges.clone(), 0);
+		assert_eq!(tree.automorphism_group_order().factors(), vec![2; n / 2]);
+
+		// Unrooted, the tree is still only symmetric around its center.
+		let unrooted = Tree::new(vec![(); n], edges);
+		assert_eq!(unrooted.automorphism_group_order().factors().len(), n / 2);
+
+		// Two stars joined by their centers.
+		let tree = Tree::new(vec![(); 6], [(0, 1), (0, 2), (0, 3), (3, 4), (3, 5)]);
+		assert_eq!(tree.automorphism_group_order().to_u128(), Some(8));
+		assert_eq!(tree.automorphism_orbits(), [0, 1, 1, 0, 1, 1]);
+		assert_eq!(tree.automorphisms().len(), 3);
+	}
+}
@@ -0,0 +1,413 @@
+//! Weisfeiler–Leman refinement.
+//!
+//! The `k`-dimensional Weisfeiler–Leman algorithm (`k`-WL) colors the
+//! `k`-tuples of elements of a graph, starting from the isomorphism type of
+//! each tuple, and repeatedly refines the color of each tuple `t` with the
+//! multiset, over all elements `w`, of the colors of the tuples obtained by
+//! replacing each entry of `t` by `w`. This is the so-called *folklore*
+//! variant, where `k`-WL is as strong as the classical `(k + 1)`-WL. With
+//! `k = 1`, it is the usual color refinement, where each element is refined
+//! by the multiset of its neighbor colors.
+//!
+//! Color refinement has the same power as
+//! [`ReversibleColoring::make_equitable`] and fails to distinguish the
+//! elements of regular graphs. It only uses the neighbor lists, and costs
+//! `O(n + m)` time per round for `n` elements and `m` edges. 2-WL
+//! distinguishes, for instance, a 6-cycle from two triangles, at the cost of
+//! `O(n³)` time per round and `O(n²)` memory. In general, `k`-WL costs
+//! `O(k·nᵏ⁺¹)` time per round and `O(nᵏ)` memory, so it is best used at the
+//! root of the search tree only:
+//!
+//! ```
+//! use normal_form::{wl, ReversibleColoring};
+//!
+//! // A 6-cycle and two triangles.
+//! let neighbors = [
+//!     vec![1, 5], vec![0, 2], vec![1, 3], vec![2, 4], vec![3, 5], vec![4, 0],
+//!     vec![7, 8], vec![6, 8], vec![6, 7], vec![10, 11], vec![9, 11], vec![9, 10],
+//! ];
+//!
+//! let mut coloring = ReversibleColoring::new(&12usize);
+//! coloring.make_equitable(&12usize, |i| &neighbors[*i]);
+//! assert_eq!(coloring.len(), 1);
+//!
+//! if coloring.depth() == 0 {
+//!     wl::refine(&mut coloring, &12usize, 2, |i| &neighbors[*i]);
+//! }
+//! assert_eq!(coloring.len(), 2);
+//! ```
+//!
+//! Colors are 64-bit [FNV-1a](http://www.isthe.com/chongo/tech/comp/fnv/)
+//! hashes of a fixed little-endian encoding of the refined data. They do not
+//! depend on the platform, the Rust version or the build, and can be compared
+//! between graphs, for instance as features for machine learning with
+//! [`colors`] and [`hash`]. Changing them is a breaking change of this
+//! library.
+use crate::{set::Map, ReversibleColoring, Set};
+
+/// Computes the stable `k`-WL color of each element.
+///
+/// For `k > 1`, the color of an element `x` is the color of the tuple
+/// `(x, …, x)`.
+///
+/// # Panics
+///
+/// Panics if `k` is `0` or if there are more than `usize::MAX` `k`-tuples.
+pub fn colors<'i, S, F, I>(set: &S, k: usize, neighbors: F) -> S::Map<u64>
+where
+	S: Set + ?Sized,
+	F: Fn(&S::Item) -> I,
+	I: IntoIterator<Item = &'i S::Item>,
+	S::Item: 'i,
+{
+	let wl = Wl::new(set, k, neighbors, |_| 0);
+	let colors = wl.vertex_colors();
+	set.map(|x| colors[wl.index(x)])
+}
+
+/// Computes a `k`-WL hash of the whole graph.
+///
+/// It is the hash of the stable color multiset of the `k`-tuples. Isomorphic
+/// graphs have the same hash, but non-isomorphic graphs that `k`-WL cannot
+/// distinguish do too.
+///
+/// # Panics
+///
+/// Panics if `k` is `0` or if there are more than `usize::MAX` `k`-tuples.
+pub fn hash<'i, S, F, I>(set: &S, k: usize, neighbors: F) -> u64
+where
+	S: Set + ?Sized,
+	F: Fn(&S::Item) -> I,
+	I: IntoIterator<Item = &'i S::Item>,
+	S::Item: 'i,
+{
+	let mut colors = Wl::new(set, k, neighbors, |_| 0).colors;
+	colors.sort_unstable();
+	hash_of(|h| {
+		h.write(k as u64);
+		h.write_slice(&colors)
+	})
+}
+
+/// Refines the given coloring with the stable `k`-WL colors of its elements.
+///
+/// The initial color of each tuple includes the current cell of its entries,
+/// so the result is finer or equal to the stable coloring obtained with
+/// [`ReversibleColoring::make_equitable`]. Returns `true` if the coloring
+/// has been refined.
+///
+/// # Panics
+///
+/// Panics if `k` is `0` or if there are more than `usize::MAX` `k`-tuples.
+pub fn refine<'i, S, F, I>(
+	coloring: &mut ReversibleColoring<S>,
+	set: &S,
+	k: usize,
+	neighbors: F,
+) -> bool
+where
+	S: Set + ?Sized,
+	F: Fn(&S::Item) -> I,
+	I: IntoIterator<Item = &'i S::Item>,
+	S::Item: 'i,
+{
+	let wl = Wl::new(set, k, neighbors, |x| {
+		coloring.cell_start(x).unwrap() as u64
+	});
+	let colors = wl.vertex_colors();
+	coloring.refine(|x| colors[wl.index(x)])
+}
+
+/// Stable `k`-WL coloring.
+struct Wl<'s, S: Set + ?Sized> {
+	set: &'s S,
+	k: usize,
+
+	/// Number of elements.
+	n: usize,
+
+	/// Index of each element.
+	indexes: S::Map<usize>,
+
+	/// Stable color of each `k`-tuple, indexed in base `n` with the first
+	/// entry as the most significant digit.
+	colors: Vec<u64>,
+}
+
+impl<'s, S: Set + ?Sized> Wl<'s, S> {
+	fn new<'i, F, I, L>(set: &'s S, k: usize, neighbors: F, label: L) -> Self
+	where
+		F: Fn(&S::Item) -> I,
+		I: IntoIterator<Item = &'i S::Item>,
+		S::Item: 'i,
+		L: Fn(&S::Item) -> u64,
+	{
+		assert!(k > 0, "k-WL dimension must be positive");
+		let mut items: Vec<_> = set.iter().collect();
+		items.sort_unstable();
+		let n = items.len();
+		let indexes = set.map(|x| items.binary_search(x).unwrap());
+
+		let labels: Vec<_> = items.iter().map(label).collect();
+		let mut wl = Self {
+			set,
+			k,
+			n,
+			indexes,
+			colors: Vec::new(),
+		};
+
+		if k == 1 {
+			// Symmetric and deduplicated neighbors of each element, without
+			// loops, so that each round is linear in the size of the graph.
+			let mut loops = vec![false; n];
+			let mut adjacency = vec![Vec::new(); n];
+			for (a, x) in items.iter().enumerate() {
+				for y in neighbors(x) {
+					let b = *wl.indexes.get(y).unwrap();
+					if a == b {
+						loops[a] = true
+					} else {
+						adjacency[a].push(b);
+						adjacency[b].push(a)
+					}
+				}
+			}
+
+			for list in &mut adjacency {
+				list.sort_unstable();
+				list.dedup()
+			}
+
+			wl.colors = (0..n)
+				.map(|a| {
+					hash_of(|h| {
+						h.write(labels[a]);
+						h.write(loops[a] as u64)
+					})
+				})
+				.collect();
+			wl.stabilize(|colors, a| {
+				let mut neighbor_colors: Vec<_> = adjacency[a].iter().map(|&b| colors[b]).collect();
+				neighbor_colors.sort_unstable();
+				hash_of(|h| {
+					h.write(colors[a]);
+					h.write_slice(&neighbor_colors)
+				})
+			})
+		} else {
+			let len = n.checked_pow(k as u32).expect("too many tuples for k-WL");
+
+			let mut adjacent = vec![false; n * n];
+			for (a, x) in items.iter().enumerate() {
+				for y in neighbors(x) {
+					let b = *wl.indexes.get(y).unwrap();
+					adjacent[a * n + b] = true;
+					adjacent[b * n + a] = true;
+				}
+			}
+
+			let strides: Vec<_> = (0..k).map(|i| n.pow((k - 1 - i) as u32)).collect();
+			let entries = |t: usize| -> Vec<usize> { strides.iter().map(|s| t / s % n).collect() };
+
+			// Isomorphism type of each tuple.
+			wl.colors = (0..len)
+				.map(|t| {
+					let entries = entries(t);
+					hash_of(|h| {
+						for &a in &entries {
+							h.write(labels[a])
+						}
+
+						for &a in &entries {
+							for &b in &entries {
+								h.write((a == b) as u64);
+								h.write(adjacent[a * n + b] as u64)
+							}
+						}
+					})
+				})
+				.collect();
+
+			wl.stabilize(|colors, t| {
+				let entries = entries(t);
+				let mut substitutions: Vec<_> = (0..n)
+					.map(|w| {
+						entries
+							.iter()
+							.zip(&strides)
+							.map(|(&a, s)| colors[t - a * s + w * s])
+							.collect::<Vec<_>>()
+					})
+					.collect();
+				substitutions.sort_unstable();
+				hash_of(|h| {
+					h.write(colors[t]);
+					h.write(substitutions.len() as u64);
+					for substitution in &substitutions {
+						h.write_slice(substitution)
+					}
+				})
+			})
+		}
+
+		wl
+	}
+
+	/// Refines the colors with `f` until the number of colors is stable.
+	///
+	/// Since the new color of each tuple includes its previous color, the
+	/// number of colors can only grow.
+	fn stabilize<F>(&mut self, f: F)
+	where
+		F: Fn(&[u64], usize) -> u64,
+	{
+		let mut count = color_count(&self.colors);
+		loop {
+			let colors: Vec<_> = (0..self.colors.len()).map(|t| f(&self.colors, t)).collect();
+			let new_count = color_count(&colors);
+			self.colors = colors;
+
+			if new_count == count {
+				break;
+			}
+
+			count = new_count
+		}
+	}
+
+	fn index(&self, x: &S::Item) -> usize {
+		*self.indexes.get(x).unwrap()
+	}
+
+	/// Color of each element, by index.
+	fn vertex_colors(&self) -> Vec<u64> {
+		// Index of the `(x, …, x)` tuple is `x · (1 + n + … + nᵏ⁻¹)`.
+		let diagonal: usize = (0..self.k).map(|i| self.n.pow(i as u32)).sum();
+		debug_assert_eq!(self.set.len(), self.n);
+		(0..self.n).map(|a| self.colors[a * diagonal]).collect()
+	}
+}
+
+fn color_count(colors: &[u64]) -> usize {
+	let mut colors = colors.to_vec();
+	colors.sort_unstable();
+	colors.dedup();
+	colors.len()
+}
+
+/// 64-bit FNV-1a hasher.
+struct Fnv(u64);
+
+impl Fnv {
+	const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+	const PRIME: u64 = 0x100000001b3;
+
+	/// Hashes the little-endian bytes of `value`.
+	fn write(&mut self, value: u64) {
+		for byte in value.to_le_bytes() {
+			self.0 = (self.0 ^ byte as u64).wrapping_mul(Self::PRIME)
+		}
+	}
+
+	/// Hashes the length of `values`, then each value.
+	fn write_slice(&mut self, values: &[u64]) {
+		self.write(values.len() as u64);
+		for &value in values {
+			self.write(value)
+		}
+	}
+}
+
+/// Computes the hash of the values written by `f`.
+fn hash_of<F>(f: F) -> u64
+where
+	F: FnOnce(&mut Fnv),
+{
+	let mut hasher = Fnv(Fnv::OFFSET_BASIS);
+	f(&mut hasher);
+	hasher.0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{colors, hash, refine};
+	use crate::ReversibleColoring;
+
+	fn cycle(n: usize) -> Vec<Vec<usize>> {
+		(0..n).map(|i| vec![(i + 1) % n, (i + n - 1) % n]).collect()
+	}
+
+	/// Disjoint union of two triangles.
+	fn triangles() -> Vec<Vec<usize>> {
+		(0..6)
+			.map(|i| {
+				let base = i / 3 * 3;
+				vec![base + (i + 1) % 3, base + (i + 2) % 3]
+			})
+			.collect()
+	}
+
+	#[test]
+	fn color_refinement() {
+		// A path is distinguished from its ends to its middle.
+		let path = [vec![1], vec![0, 2], vec![1, 3], vec![2]];
+		let colors = colors(&4usize, 1, |i| &path[*i]);
+		assert_eq!(colors[0], colors[3]);
+		assert_eq!(colors[1], colors[2]);
+		assert_ne!(colors[0], colors[1]);
+
+		// 1-WL does not distinguish a 6-cycle from two triangles.
+		let (c6, t) = (cycle(6), triangles());
+		assert_eq!(hash(&6usize, 1, |i| &c6[*i]), hash(&6usize, 1, |i| &t[*i]));
+	}
+
+	#[test]
+	fn two_wl() {
+		let (c6, t) = (cycle(6), triangles());
+		assert_ne!(hash(&6usize, 2, |i| &c6[*i]), hash(&6usize, 2, |i| &t[*i]));
+
+		// Union of both graphs.
+		let union: Vec<Vec<usize>> = c6
+			.iter()
+			.cloned()
+			.chain(t.iter().map(|n| n.iter().map(|i| i + 6).collect()))
+			.collect();
+		let mut coloring = ReversibleColoring::new(&12usize);
+		coloring.make_equitable(&12usize, |i| &union[*i]);
+		assert_eq!(coloring.len(), 1);
+		assert!(refine(&mut coloring, &12usize, 2, |i| &union[*i]));
+		assert_eq!(coloring.len(), 2);
+		assert!(!refine(&mut coloring, &12usize, 2, |i| &union[*i]));
+	}
+
+	#[test]
+	fn stability() {
+		// Colors must not change between platforms and versions.
+		let c6 = cycle(6);
+		assert_eq!(hash(&6usize, 1, |i| &c6[*i]), 0x5665337666ac8dfa);
+	}
+
+	#[test]
+	fn invariance() {
+		let c = cycle(7);
+		let permutation = [3, 6, 0, 5, 1, 2, 4];
+		let mut permuted = vec![Vec::new(); 7];
+		for (i, neighbors) in c.iter().enumerate() {
+			permuted[permutation[i]] = neighbors.iter().map(|j| permutation[*j]).collect()
+		}
+
+		for k in 1..=3 {
+			let a = colors(&7usize, k, |i| &c[*i]);
+			let b = colors(&7usize, k, |i| &permuted[*i]);
+			for i in 0..7 {
+				assert_eq!(a[i], b[permutation[i]])
+			}
+
+			assert_eq!(
+				hash(&7usize, k, |i| &c[*i]),
+				hash(&7usize, k, |i| &permuted[*i])
+			)
+		}
+	}
+}
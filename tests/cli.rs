@@ -0,0 +1,134 @@
+#![cfg(feature = "cli")]
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Runs the command-line tool with the given arguments and standard input,
+/// and returns its standard output.
+fn run(args: &[&str], input: &str) -> String {
+	let mut child = Command::new(env!("CARGO_BIN_EXE_normal-form"))
+		.args(args)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.unwrap();
+
+	child
+		.stdin
+		.take()
+		.unwrap()
+		.write_all(input.as_bytes())
+		.unwrap();
+
+	let output = child.wait_with_output().unwrap();
+	assert!(
+		output.status.success(),
+		"{}",
+		String::from_utf8_lossy(&output.stderr)
+	);
+	String::from_utf8(output.stdout).unwrap()
+}
+
+/// Writes the given content in a temporary file, and returns its path.
+fn file(name: &str, content: &str) -> PathBuf {
+	let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+	std::fs::write(&path, content).unwrap();
+	path
+}
+
+/// Checks that the two given inputs have the same canonical form and hash,
+/// and returns the canonical form.
+fn assert_isomorphic(args: &[&str], a: &str, b: &str) -> String {
+	let canon = |input| run(&[&["canon"], args].concat(), input);
+	let hash = |input| run(&[&["hash"], args].concat(), input);
+
+	let form = canon(a);
+	assert_eq!(form, canon(b));
+	assert_eq!(hash(a), hash(b));
+	assert_eq!(hash(a).trim().len(), 64);
+	form
+}
+
+/// Path `0 - 1 - 2 - 3` and path `1 - 0 - 3 - 2`, and a triangle with an
+/// isolated vertex, in graph6.
+const GRAPH6: &str = "Ch\nCd\nCw\n";
+
+#[test]
+fn graph6() {
+	let form = assert_isomorphic(&[], "Ch\n", "Cd\n");
+	assert_ne!(form, run(&["canon"], "Cw\n"));
+
+	// The canonical form is stable.
+	assert_eq!(run(&["canon"], &form), form);
+	assert_eq!(run(&["aut"], GRAPH6), "2\n2\n6\n");
+}
+
+#[test]
+fn classes() {
+	assert_eq!(run(&["classes"], GRAPH6), "0 1\n2\n");
+	assert_eq!(run(&["classes"], "Cw\nCh\nCw\nCd\n"), "0 2\n1 3\n");
+}
+
+#[test]
+fn unique() {
+	let unique = run(&["unique"], GRAPH6);
+	assert_eq!(unique.lines().count(), 2);
+	assert_eq!(unique, run(&["canon"], "Ch\nCw\n"));
+}
+
+#[test]
+fn edges() {
+	let a = "a b\nb c\nc d\n";
+	let b = "z y\nx w\nw z\n";
+	assert_isomorphic(&["-f", "edges"], a, b);
+	assert_eq!(
+		run(&["classes", "-f", "edges"], &format!("{a}\n{b}\n")),
+		"0 1\n"
+	);
+
+	// Directed paths are not isomorphic to a path with a reversed edge.
+	let c = "a b\nc b\nc d\n";
+	let directed = run(
+		&["classes", "-f", "edges", "-d"],
+		&format!("{a}\n{b}\n{c}\n"),
+	);
+	assert_eq!(directed, "0 1\n2\n");
+}
+
+#[test]
+fn ntriples() {
+	let a = "_:x <http://example.org/knows> _:y .\n\
+		_:y <http://example.org/name> \"Bob\" .\n";
+	let b = "_:b <http://example.org/name> \"Bob\" .\n\
+		_:a <http://example.org/knows> _:b .\n";
+	let c = "_:x <http://example.org/knows> _:y .\n\
+		_:x <http://example.org/name> \"Bob\" .\n";
+	assert_isomorphic(&["-f", "ntriples"], a, b);
+
+	let classes = run(&["classes", "-f", "ntriples"], &format!("{a}\n{b}\n{c}\n"));
+	assert_eq!(classes, "0 1\n2\n");
+}
+
+#[test]
+fn dimacs() {
+	let a = file("a.cnf", "p cnf 3 2\n1 -2 0\n2 3 0\n");
+	let b = file("b.cnf", "c renamed\np cnf 3 2\n1 3 0\n2 -3 0\n");
+	let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+
+	// The format is guessed from the extension.
+	let form = run(&["canon", a], "");
+	assert_eq!(form, run(&["canon", b], ""));
+	assert_eq!(run(&["hash", a], ""), run(&["hash", b], ""));
+	assert_eq!(run(&["classes", a, b], ""), "0 1\n");
+
+	// Other extensions require an explicit format.
+	let txt = file("a.txt", "p cnf 3 2\n1 -2 0\n2 3 0\n");
+	let txt = txt.to_str().unwrap();
+	let output = Command::new(env!("CARGO_BIN_EXE_normal-form"))
+		.args(["canon", txt])
+		.output()
+		.unwrap();
+	assert!(!output.status.success());
+	assert_eq!(run(&["canon", "-f", "dimacs", txt], ""), form);
+}
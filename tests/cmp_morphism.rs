@@ -0,0 +1,185 @@
+use normal_form::{Normalize, ReversibleColoring};
+use rand::{seq::SliceRandom, Rng};
+use std::cell::Cell;
+use std::cmp::Ordering;
+
+/// Undirected graph, counting the morphed graphs it builds.
+struct Graph {
+	vertex_count: usize,
+	edges: Vec<(usize, usize)>,
+	lazy: bool,
+	built: Cell<usize>,
+}
+
+impl Graph {
+	fn new(vertex_count: usize, edges: Vec<(usize, usize)>, lazy: bool) -> Self {
+		Self {
+			vertex_count,
+			edges,
+			lazy,
+			built: Cell::new(0),
+		}
+	}
+}
+
+struct Cache {
+	neighbors: Vec<Vec<usize>>,
+	inverse: Vec<usize>,
+	targets: Vec<usize>,
+}
+
+impl Normalize for Graph {
+	type Elements = usize;
+	type Color = ();
+	type Cache = Cache;
+	type Morphed = Vec<(usize, usize)>;
+
+	fn elements(&self) -> &usize {
+		&self.vertex_count
+	}
+
+	fn initialize_cache(&self) -> Cache {
+		let mut neighbors = vec![Vec::new(); self.vertex_count];
+		for &(a, b) in &self.edges {
+			neighbors[a].push(b);
+			if a != b {
+				neighbors[b].push(a)
+			}
+		}
+
+		Cache {
+			neighbors,
+			inverse: vec![0; self.vertex_count],
+			targets: Vec::new(),
+		}
+	}
+
+	fn initial_coloring(&self) -> Vec<()> {
+		vec![(); self.vertex_count]
+	}
+
+	fn refine_coloring(&self, cache: &mut Cache, coloring: &mut ReversibleColoring<usize>) {
+		coloring.make_equitable(&self.vertex_count, |i| &cache.neighbors[*i])
+	}
+
+	fn apply_morphism<F>(&self, f: F) -> Self::Morphed
+	where
+		F: Fn(&usize) -> usize,
+	{
+		self.built.set(self.built.get() + 1);
+		let mut edges: Vec<_> = self
+			.edges
+			.iter()
+			.map(|(a, b)| {
+				let (a, b) = (f(a), f(b));
+				(a.min(b), a.max(b))
+			})
+			.collect();
+		edges.sort_unstable();
+		edges
+	}
+
+	/// Morphed edges are produced by source vertex, without building the
+	/// morphed graph.
+	fn cmp_morphism<F>(&self, cache: &mut Cache, f: F, other: &Self::Morphed) -> Option<Ordering>
+	where
+		F: Fn(&usize) -> usize,
+	{
+		if !self.lazy {
+			return None;
+		}
+
+		for a in 0..self.vertex_count {
+			cache.inverse[f(&a)] = a
+		}
+
+		let mut other_edges = other.iter();
+		for a in 0..self.vertex_count {
+			cache.targets.clear();
+			cache.targets.extend(
+				cache.neighbors[cache.inverse[a]]
+					.iter()
+					.map(&f)
+					.filter(|&b| a <= b),
+			);
+			cache.targets.sort_unstable();
+
+			for &b in &cache.targets {
+				match other_edges.next() {
+					Some(other_edge) => {
+						let ordering = (a, b).cmp(other_edge);
+						if ordering.is_ne() {
+							return Some(ordering);
+						}
+					}
+					None => return Some(Ordering::Greater),
+				}
+			}
+		}
+
+		if other_edges.next().is_some() {
+			Some(Ordering::Less)
+		} else {
+			Some(Ordering::Equal)
+		}
+	}
+}
+
+fn random_edges(rng: &mut impl Rng, n: usize, m: usize) -> Vec<(usize, usize)> {
+	let mut edges: Vec<_> = (0..m)
+		.map(|_| {
+			let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+			(a.min(b), a.max(b))
+		})
+		.collect();
+	edges.sort_unstable();
+	edges.dedup();
+	edges
+}
+
+#[test]
+fn lazy_cmp_morphism() {
+	let mut rng = rand::thread_rng();
+	for _ in 0..100 {
+		let n = 8;
+		let edges = random_edges(&mut rng, n, 12);
+		let mut permutation: Vec<usize> = (0..n).collect();
+		permutation.shuffle(&mut rng);
+		let permuted: Vec<_> = edges
+			.iter()
+			.map(|&(a, b)| (permutation[a], permutation[b]))
+			.collect();
+
+		let eager = Graph::new(n, edges.clone(), false);
+		let lazy = Graph::new(n, edges, true);
+		let permuted = Graph::new(n, permuted, true);
+
+		let form = eager.normal_form();
+		assert_eq!(lazy.normal_form(), form);
+		assert_eq!(permuted.normal_form(), form);
+		assert_eq!(
+			lazy.automorphism_group_order().to_u128(),
+			eager.automorphism_group_order().to_u128()
+		);
+
+		// Lazy comparisons build fewer morphed graphs.
+		assert!(lazy.built.get() <= eager.built.get())
+	}
+}
+
+#[test]
+fn lazy_build() {
+	// The cycle has many leaves equivalent to the first one, that an eager
+	// comparison builds.
+	let n = 12;
+	let edges = (0..n)
+		.map(|i| (i.min((i + 1) % n), i.max((i + 1) % n)))
+		.collect();
+	let eager = Graph::new(n, edges, false);
+	let lazy = Graph::new(n, eager.edges.clone(), true);
+	eager.normal_form();
+	lazy.normal_form();
+
+	// Only the first and best leaves are built by a lazy comparison.
+	assert!(lazy.built.get() < eager.built.get())
+}
@@ -0,0 +1,223 @@
+use normal_form::collection::{self, Decompose, Simultaneous};
+use normal_form::{Normalize, ReversibleColoring};
+
+/// Undirected graph over the vertices `0..vertex_count`.
+struct Graph {
+	vertex_count: usize,
+	neighbors: Vec<Vec<usize>>,
+}
+
+impl Graph {
+	fn new(vertex_count: usize, edges: &[(usize, usize)]) -> Self {
+		let mut neighbors = vec![Vec::new(); vertex_count];
+		for &(a, b) in edges {
+			neighbors[a].push(b);
+			neighbors[b].push(a)
+		}
+
+		Self {
+			vertex_count,
+			neighbors,
+		}
+	}
+}
+
+impl Normalize for Graph {
+	type Elements = usize;
+	type Color = ();
+	type Cache = ();
+	type Morphed = Vec<(usize, usize)>;
+
+	fn elements(&self) -> &usize {
+		&self.vertex_count
+	}
+
+	fn initialize_cache(&self) {}
+
+	fn initial_coloring(&self) -> Vec<()> {
+		vec![(); self.vertex_count]
+	}
+
+	fn refine_coloring(&self, _cache: &mut (), coloring: &mut ReversibleColoring<usize>) {
+		coloring.make_equitable(&self.vertex_count, |x| &self.neighbors[*x])
+	}
+
+	fn apply_morphism<F>(&self, f: F) -> Self::Morphed
+	where
+		F: Fn(&usize) -> usize,
+	{
+		let mut edges = Vec::new();
+		for (x, neighbors) in self.neighbors.iter().enumerate() {
+			for y in neighbors.iter().filter(|y| x < **y) {
+				let (a, b) = (f(&x), f(y));
+				edges.push((a.min(b), a.max(b)))
+			}
+		}
+		edges.sort_unstable();
+		edges
+	}
+}
+
+impl Decompose for Graph {
+	fn neighbors(&self, x: &usize) -> Vec<usize> {
+		self.neighbors[*x].clone()
+	}
+
+	fn component(&self, items: &[usize]) -> Self {
+		let edges: Vec<_> = items
+			.iter()
+			.enumerate()
+			.flat_map(|(a, x)| {
+				self.neighbors[*x]
+					.iter()
+					.filter(move |y| x < *y)
+					.map(move |y| (a, items.binary_search(y).unwrap()))
+			})
+			.collect();
+		Self::new(items.len(), &edges)
+	}
+}
+
+fn triangle() -> Graph {
+	Graph::new(3, &[(0, 1), (1, 2), (2, 0)])
+}
+
+fn path(first: usize) -> Graph {
+	Graph::new(
+		3,
+		&[(first, (first + 1) % 3), ((first + 1) % 3, (first + 2) % 3)],
+	)
+}
+
+#[test]
+fn multiset() {
+	let a = [triangle(), path(0), triangle()];
+	let b = [path(1), triangle(), triangle()];
+	assert_eq!(collection::normal_form(&a), collection::normal_form(&b));
+	assert_ne!(
+		collection::normal_form(&a),
+		collection::normal_form(&[path(0), path(1), triangle()])
+	);
+
+	let (normal_forms, permutations) = collection::normalize(&b);
+	for (item, (index, permutation)) in b.iter().zip(&permutations) {
+		assert_eq!(
+			item.apply_morphism(|x| permutation[*x]),
+			normal_forms[*index]
+		)
+	}
+
+	// Isomorphic structures keep their order.
+	assert!(permutations[1].0 < permutations[2].0);
+
+	// Each triangle has 6 automorphisms, each path 2, and both triangles can
+	// be swapped.
+	assert_eq!(
+		collection::automorphism_group_order(&a).to_u128(),
+		Some(6 * 2 * 6 * 2)
+	)
+}
+
+#[test]
+fn overridden_normal_forms() {
+	use normal_form::matrix::Matrix;
+
+	// Transposable matrices are isomorphic to their transpose, which is only
+	// taken into account by the `Normalize` methods of `Matrix`.
+	let row = Matrix::new(1, 2, vec![0, 1]).with_transposition();
+	let column = Matrix::new(2, 1, vec![0, 1]).with_transposition();
+	assert_eq!(
+		collection::automorphism_group_order(&[row.clone(), column]).to_u128(),
+		Some(2)
+	);
+
+	// Automorphisms do not transpose the matrices.
+	let triangular = Matrix::new(3, 3, vec![1, 1, 1, 0, 1, 1, 0, 0, 1]).with_transposition();
+	assert_eq!(
+		collection::automorphism_group_order(&[triangular.clone(), row]).to_u128(),
+		Some(1)
+	);
+	assert_eq!(
+		collection::automorphism_group_order(&[triangular.transpose(), triangular]).to_u128(),
+		Some(2)
+	)
+}
+
+#[test]
+fn simultaneous() {
+	let cycle = |perm: [usize; 4]| {
+		Graph::new(
+			4,
+			&[
+				(perm[0], perm[1]),
+				(perm[1], perm[2]),
+				(perm[2], perm[3]),
+				(perm[3], perm[0]),
+			],
+		)
+	};
+	let edge = |perm: [usize; 4]| Graph::new(4, &[(perm[0], perm[1])]);
+
+	let a = Simultaneous::new(4, vec![cycle([0, 1, 2, 3]), edge([0, 1, 2, 3])]);
+	let b = Simultaneous::new(4, vec![cycle([2, 0, 3, 1]), edge([2, 0, 3, 1])]);
+	let c = Simultaneous::new(4, vec![cycle([0, 1, 2, 3]), edge([0, 2, 1, 3])]);
+	assert_eq!(a.normal_form(), b.normal_form());
+	assert_ne!(a.normal_form(), c.normal_form());
+
+	// Automorphisms of the cycle fixing the edge.
+	assert_eq!(a.automorphism_group_order().to_u128(), Some(2));
+	assert_eq!(c.automorphism_group_order().to_u128(), Some(4))
+}
+
+#[test]
+fn decompose() {
+	// Two triangles, a path and an isolated vertex, interleaved.
+	let edges = [
+		(0, 3),
+		(3, 6),
+		(6, 0),
+		(1, 4),
+		(4, 7),
+		(2, 5),
+		(5, 8),
+		(8, 2),
+	];
+	let a = Graph::new(10, &edges);
+	assert_eq!(
+		a.components(),
+		[vec![0, 3, 6], vec![1, 4, 7], vec![2, 5, 8], vec![9]]
+	);
+
+	let permutation = [4, 9, 0, 7, 2, 5, 1, 8, 6, 3];
+	let b = Graph::new(10, &edges.map(|(x, y)| (permutation[x], permutation[y])));
+
+	let (normal_forms, labeling) = a.normalize_decomposed();
+	assert_eq!(normal_forms, b.decomposed_normal_form());
+
+	let mut component_forms: Vec<_> = a
+		.component_structures()
+		.iter()
+		.map(Normalize::normal_form)
+		.collect();
+	component_forms.sort_unstable();
+	assert_eq!(normal_forms, component_forms);
+
+	// The permutation labels the components in canonical order.
+	let mut expected = Vec::new();
+	let mut offset = 0;
+	for (form, len) in normal_forms.iter().zip([1, 3, 3, 3]) {
+		expected.extend(form.iter().map(|(x, y)| (x + offset, y + offset)));
+		offset += len
+	}
+	expected.sort_unstable();
+	assert_eq!(a.apply_morphism(|x| labeling[*x]), expected);
+
+	assert_eq!(
+		b.decomposed_automorphism_group_order().to_u128(),
+		Some(2 * 6 * 6 * 2)
+	);
+	assert_eq!(
+		a.decomposed_automorphism_group_order().to_u128(),
+		a.automorphism_group_order().to_u128()
+	)
+}
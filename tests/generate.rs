@@ -0,0 +1,136 @@
+use normal_form::generate::{generate, Augment};
+use normal_form::{Normalize, ReversibleColoring};
+use std::collections::BTreeSet;
+
+/// Undirected simple graph over the vertices `0..vertex_count`.
+#[derive(Clone)]
+struct Graph {
+	vertex_count: usize,
+	neighbors: Vec<Vec<usize>>,
+
+	/// Only generate connected graphs.
+	connected: bool,
+}
+
+impl Graph {
+	fn new(connected: bool) -> Self {
+		Self {
+			vertex_count: 1,
+			neighbors: vec![Vec::new()],
+			connected,
+		}
+	}
+
+	/// Checks if the graph without `removed` is connected.
+	fn is_connected_without(&self, removed: usize) -> bool {
+		let start = match (0..self.vertex_count).find(|&x| x != removed) {
+			Some(start) => start,
+			None => return true,
+		};
+
+		let mut visited = vec![false; self.vertex_count];
+		visited[removed] = true;
+		visited[start] = true;
+		let mut stack = vec![start];
+		while let Some(x) = stack.pop() {
+			for &y in &self.neighbors[x] {
+				if !visited[y] {
+					visited[y] = true;
+					stack.push(y)
+				}
+			}
+		}
+
+		visited.into_iter().all(|v| v)
+	}
+}
+
+impl Normalize for Graph {
+	type Elements = usize;
+	type Color = usize;
+	type Cache = ();
+	type Morphed = Vec<(usize, usize)>;
+
+	fn elements(&self) -> &usize {
+		&self.vertex_count
+	}
+
+	fn initialize_cache(&self) {}
+
+	fn initial_coloring(&self) -> Vec<usize> {
+		self.neighbors.iter().map(Vec::len).collect()
+	}
+
+	fn refine_coloring(&self, _cache: &mut (), coloring: &mut ReversibleColoring<usize>) {
+		coloring.make_equitable(&self.vertex_count, |x| &self.neighbors[*x])
+	}
+
+	fn apply_morphism<F>(&self, f: F) -> Self::Morphed
+	where
+		F: Fn(&usize) -> usize,
+	{
+		let mut edges = Vec::new();
+		for (x, neighbors) in self.neighbors.iter().enumerate() {
+			for y in neighbors.iter().filter(|y| x < **y) {
+				let (a, b) = (f(&x), f(y));
+				edges.push((a.min(b), a.max(b)))
+			}
+		}
+		edges.sort_unstable();
+		edges
+	}
+}
+
+impl Augment for Graph {
+	/// Adds a vertex connected to any subset of the vertices (non-empty for
+	/// connected graphs).
+	fn augment(&self) -> Vec<(Self, usize)> {
+		let n = self.vertex_count;
+		let first = if self.connected { 1 } else { 0 };
+
+		(first..1 << n)
+			.map(|subset: usize| {
+				let mut child = self.clone();
+				child.vertex_count += 1;
+				child.neighbors.push(Vec::new());
+				for x in (0..n).filter(|x| subset & (1 << x) != 0) {
+					child.neighbors[x].push(n);
+					child.neighbors[n].push(x)
+				}
+
+				(child, n)
+			})
+			.collect()
+	}
+
+	fn is_removable(&self, x: &usize) -> bool {
+		!self.connected || self.is_connected_without(*x)
+	}
+}
+
+/// Counts the generated graphs by number of vertices, checking that they
+/// are pairwise non-isomorphic.
+fn count(connected: bool, max_vertex_count: usize) -> Vec<usize> {
+	let mut counts = vec![0; max_vertex_count + 1];
+	let mut normal_forms = BTreeSet::new();
+
+	generate(Graph::new(connected), |graph| {
+		counts[graph.vertex_count] += 1;
+		assert!(normal_forms.insert((graph.vertex_count, graph.normal_form())));
+		graph.vertex_count < max_vertex_count
+	});
+
+	counts
+}
+
+#[test]
+fn graphs() {
+	// OEIS A000088.
+	assert_eq!(count(false, 7), [0, 1, 2, 4, 11, 34, 156, 1044])
+}
+
+#[test]
+fn connected_graphs() {
+	// OEIS A001349.
+	assert_eq!(count(true, 7), [0, 1, 1, 2, 6, 21, 112, 853])
+}
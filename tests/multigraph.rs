@@ -0,0 +1,101 @@
+use normal_form::{multigraph::Multigraph, Normalize};
+use rand::{seq::SliceRandom, Rng};
+
+/// Random multigraph with up to `max_len` edges, including parallel edges
+/// and self-loops.
+fn make_random_multigraph(vertex_count: usize, max_len: usize) -> Multigraph<bool, u8> {
+	let mut rng = rand::thread_rng();
+	let vertices = (0..vertex_count).map(|_| rng.gen()).collect();
+	let len = rng.gen_range(0..=max_len);
+	let edges: Vec<_> = (0..len)
+		.map(|_| {
+			(
+				rng.gen_range(0..vertex_count),
+				rng.gen_range(0..vertex_count),
+				rng.gen_range(0..2),
+			)
+		})
+		.collect();
+	Multigraph::new(vertices, edges)
+}
+
+/// Randomly renames the vertices of the given multigraph, and shuffles its
+/// edges and their endpoints.
+fn random_morphism(graph: &Multigraph<bool, u8>) -> Multigraph<bool, u8> {
+	let mut rng = rand::thread_rng();
+	let mut morphism: Vec<_> = (0..graph.len()).collect();
+	morphism.shuffle(&mut rng);
+
+	let mut vertices = vec![false; graph.len()];
+	for (x, &v) in graph.vertices().iter().enumerate() {
+		vertices[morphism[x]] = v
+	}
+
+	let mut edges: Vec<_> = graph
+		.edges()
+		.iter()
+		.map(|&(a, b, e)| {
+			if rng.gen() {
+				(morphism[b], morphism[a], e)
+			} else {
+				(morphism[a], morphism[b], e)
+			}
+		})
+		.collect();
+	edges.shuffle(&mut rng);
+
+	Multigraph::new(vertices, edges)
+}
+
+/// Test that isomorphic multigraphs have the same normal form.
+fn test_random(vertex_count: usize, max_len: usize) {
+	for _ in 0..100 {
+		let a = make_random_multigraph(vertex_count, max_len);
+		let canonized_a = a.normalize().0;
+
+		for _ in 0..10 {
+			let b = random_morphism(&a);
+			let (canonized_b, permutation) = b.normalize();
+			assert_eq!(canonized_a, canonized_b);
+			assert_eq!(b.apply_morphism(|x| permutation[*x]), canonized_b)
+		}
+	}
+}
+
+/// Test that two random multigraphs do not have the same normal form in
+/// general.
+///
+/// In theory, this test may fail even if the code is correct,
+/// but with a very low probability.
+fn test_random_negative(vertex_count: usize, max_len: usize) {
+	for _ in 0..100 {
+		let a = make_random_multigraph(vertex_count, max_len);
+		let b = make_random_multigraph(vertex_count, max_len);
+
+		if a.normalize().0 != b.normalize().0 {
+			return; // success
+		}
+	}
+
+	panic!("all random multigraphs have the same normal form")
+}
+
+#[test]
+fn random_3_10() {
+	test_random(3, 10)
+}
+
+#[test]
+fn random_8_20() {
+	test_random(8, 20)
+}
+
+#[test]
+fn random_16_40() {
+	test_random(16, 40)
+}
+
+#[test]
+fn random_negative_8_20() {
+	test_random_negative(8, 20)
+}
@@ -1,4 +1,4 @@
-use normal_form::Normalize;
+use normal_form::{GroupOrder, Normalize};
 use std::collections::BTreeSet;
 use std::fmt;
 use std::hash::Hash;
@@ -94,8 +94,7 @@ impl<T: Value> Normalize for Graph<T> {
 			}
 		}
 
-		let mut map = Vec::new();
-		map.resize(self.variable_count, 0);
+		let map = vec![0; self.variable_count];
 
 		Cache {
 			stack: Vec::new(),
@@ -314,6 +313,160 @@ fn simple_automorphism() {
 	assert_eq!(a.normalize().0, b.normalize().0)
 }
 
+#[test]
+fn simple_group_order() {
+	use rdf_types::Triple;
+	use Term::*;
+
+	let a: Graph<bool> = make_graph(3, [Triple(Var(0), Var(1), Var(2))]);
+	assert!(a.automorphism_group_order().is_trivial());
+
+	let b: Graph<bool> = make_graph(
+		3,
+		[
+			Triple(Var(0), Var(1), Var(2)),
+			Triple(Var(1), Var(0), Var(2)),
+		],
+	);
+	assert_eq!(b.automorphism_group_order().to_u128(), Some(2))
+}
+
+#[test]
+fn symmetric_group_order() {
+	use rdf_types::Triple;
+	use Term::*;
+
+	// Every variable is linked to every other variable.
+	let a: Graph<bool> = make_graph(
+		5,
+		(0..5).flat_map(|x| {
+			(0..5)
+				.filter(move |y| *y != x)
+				.map(move |y| Triple(Var(x), Value(true), Var(y)))
+		}),
+	);
+
+	assert_eq!(a.automorphism_group_order().to_u128(), Some(120));
+	assert_eq!(a.automorphism_group_order().to_string(), "120")
+}
+
+/// Builds the disjoint union of undirected cycles of the given lengths.
+fn cycles(lengths: &[usize]) -> Graph<bool> {
+	use rdf_types::Triple;
+	use Term::*;
+
+	let mut triples = Vec::new();
+	let mut first = 0;
+	for &len in lengths {
+		for i in 0..len {
+			let x = first + i;
+			let y = first + (i + 1) % len;
+			triples.push(Triple(Var(x), Value(true), Var(y)));
+			triples.push(Triple(Var(y), Value(true), Var(x)));
+		}
+
+		first += len
+	}
+
+	make_graph(first, triples)
+}
+
+#[test]
+fn group_order_equality() {
+	let a = cycles(&[3, 4]);
+	let b = cycles(&[4, 3]);
+
+	let order_a = a.automorphism_group_order();
+	let order_b = b.automorphism_group_order();
+	assert_eq!(order_a.to_u128(), Some(48));
+	assert_eq!(order_a, order_b);
+	assert_eq!(order_a, GroupOrder::from_factors([6, 8]));
+	assert_ne!(order_a, GroupOrder::from_factors([6, 4]))
+}
+
+/// Counts the automorphisms of the given graph by enumerating every
+/// permutation of its variables.
+fn brute_force_group_order<T: Value>(graph: &Graph<T>) -> u128 {
+	fn count<T: Value>(graph: &Graph<T>, permutation: &mut Vec<usize>, k: usize) -> u128 {
+		if k == permutation.len() {
+			(graph.apply_morphism(|i| permutation[*i]) == *graph) as u128
+		} else {
+			let mut result = 0;
+			for i in k..permutation.len() {
+				permutation.swap(k, i);
+				result += count(graph, permutation, k + 1);
+				permutation.swap(k, i);
+			}
+			result
+		}
+	}
+
+	count(graph, &mut (0..graph.variable_count).collect(), 0)
+}
+
+/// Computes the automorphism orbits of the given graph by enumerating every
+/// permutation of its variables.
+fn brute_force_orbits<T: Value>(graph: &Graph<T>) -> Vec<usize> {
+	fn visit<T: Value>(
+		graph: &Graph<T>,
+		permutation: &mut Vec<usize>,
+		k: usize,
+		orbits: &mut [usize],
+	) {
+		if k == permutation.len() {
+			if graph.apply_morphism(|i| permutation[*i]) == *graph {
+				for (x, y) in permutation.iter().enumerate() {
+					orbits[x] = orbits[x].min(*y)
+				}
+			}
+		} else {
+			for i in k..permutation.len() {
+				permutation.swap(k, i);
+				visit(graph, permutation, k + 1, orbits);
+				permutation.swap(k, i);
+			}
+		}
+	}
+
+	let mut orbits: Vec<_> = (0..graph.variable_count).collect();
+	visit(
+		graph,
+		&mut (0..graph.variable_count).collect(),
+		0,
+		&mut orbits,
+	);
+	orbits
+}
+
+/// Test that isomorphic graphs have the same automorphism group order.
+fn test_random_group_order(variable_count: usize, max_len: usize) {
+	for _ in 0..100 {
+		let a = make_random_graph(variable_count, max_len);
+		let order = a.automorphism_group_order();
+		assert_eq!(order.to_u128(), Some(brute_force_group_order(&a)));
+		assert_eq!(a.automorphism_orbits(), brute_force_orbits(&a));
+
+		let group = a.automorphism_group();
+		assert_eq!(group.order().to_u128(), order.to_u128());
+		assert!(group.elements().all(|g| a.apply_morphism(|x| g[*x]) == a));
+
+		for _ in 0..10 {
+			let b = random_morphism(&a);
+			assert_eq!(order.to_u128(), b.automorphism_group_order().to_u128())
+		}
+	}
+}
+
+#[test]
+fn random_group_order_5_10() {
+	test_random_group_order(5, 10)
+}
+
+#[test]
+fn random_group_order_6_5() {
+	test_random_group_order(6, 5)
+}
+
 fn test_random(variable_count: usize, max_len: usize) {
 	for _ in 0..100 {
 		let a = make_random_graph(variable_count, max_len);
@@ -340,7 +493,7 @@ fn test_random_negative(variable_count: usize, max_len: usize) {
 		}
 	}
 
-	assert!(false)
+	panic!("all random graphs have the same normal form")
 }
 
 #[test]
@@ -0,0 +1,220 @@
+use normal_form::{
+	set::{Interned, VecSet},
+	Map, Normalize, Set,
+};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// Undirected graph whose nodes are identified by strings.
+struct Graph<S> {
+	nodes: S,
+	edges: Vec<(String, String)>,
+}
+
+impl<S: Set<Item = String>> Normalize for Graph<S> {
+	type Elements = S;
+	type Color = usize;
+	type Cache = BTreeMap<String, Vec<String>>;
+	type Morphed = Vec<(usize, usize)>;
+
+	fn elements(&self) -> &S {
+		&self.nodes
+	}
+
+	fn initialize_cache(&self) -> Self::Cache {
+		let mut neighbors: BTreeMap<String, Vec<String>> = BTreeMap::new();
+		for (a, b) in &self.edges {
+			neighbors.entry(a.clone()).or_default().push(b.clone());
+			neighbors.entry(b.clone()).or_default().push(a.clone());
+		}
+
+		neighbors
+	}
+
+	fn initial_coloring(&self) -> S::Map<usize> {
+		self.nodes.map(|node| {
+			self.edges
+				.iter()
+				.filter(|(a, b)| a == node || b == node)
+				.count()
+		})
+	}
+
+	fn refine_coloring(
+		&self,
+		cache: &mut Self::Cache,
+		coloring: &mut normal_form::ReversibleColoring<S>,
+	) {
+		coloring.make_equitable(&self.nodes, |node| {
+			cache.get(node).map(Vec::as_slice).unwrap_or_default()
+		})
+	}
+
+	fn apply_morphism<F>(&self, f: F) -> Self::Morphed
+	where
+		F: Fn(&String) -> usize,
+	{
+		let mut edges: Vec<_> = self
+			.edges
+			.iter()
+			.map(|(a, b)| {
+				let (a, b) = (f(a), f(b));
+				(a.min(b), a.max(b))
+			})
+			.collect();
+		edges.sort_unstable();
+		edges
+	}
+}
+
+const NAMES: [&str; 6] = ["a", "b", "c", "d", "e", "f"];
+
+/// A triangle `a, b, c` with a tail `c, d, e` and isolated node `f`, whose
+/// nodes are renamed by the given function.
+fn make_graph<S: FromIterator<String>>(rename: impl Fn(&str) -> String) -> Graph<S> {
+	let edges = [("a", "b"), ("b", "c"), ("c", "a"), ("c", "d"), ("d", "e")];
+	Graph {
+		nodes: NAMES.iter().map(|n| rename(n)).collect(),
+		edges: edges.iter().map(|(a, b)| (rename(a), rename(b))).collect(),
+	}
+}
+
+fn test_renaming<S: Set<Item = String> + FromIterator<String>>()
+where
+	S::Map<usize>: Clone,
+{
+	let renamings: [fn(&str) -> String; 3] = [
+		|n| n.to_string(),
+		|n| format!("{}{}", 6 - (n.as_bytes()[0] - b'a'), n),
+		|n| match n {
+			"a" => "e".to_string(),
+			"e" => "a".to_string(),
+			"b" => "f".to_string(),
+			"f" => "b".to_string(),
+			n => n.to_string(),
+		},
+	];
+
+	let (expected, _) = make_graph::<S>(renamings[0]).normalize();
+	for rename in renamings {
+		let graph = make_graph::<S>(rename);
+		let (normal_form, permutation) = graph.normalize();
+		assert_eq!(normal_form, expected);
+
+		let mut image: Vec<_> = Set::iter(&graph.nodes)
+			.map(|n| *permutation.get(&n).unwrap())
+			.collect();
+		image.sort_unstable();
+		assert_eq!(image, (0..NAMES.len()).collect::<Vec<_>>())
+	}
+}
+
+#[test]
+fn btree_set() {
+	test_renaming::<BTreeSet<String>>()
+}
+
+#[test]
+fn hash_set() {
+	test_renaming::<HashSet<String>>()
+}
+
+#[test]
+fn vec_set() {
+	test_renaming::<VecSet<String>>()
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "duplicate items")]
+fn vec_set_duplicates() {
+	VecSet::new(vec![1, 2, 1]);
+}
+
+/// Undirected graph whose nodes are identified by strings, resolved once to
+/// the indexes of an interned set.
+struct InternedGraph {
+	nodes: Interned<String>,
+	edges: Vec<(usize, usize)>,
+}
+
+impl InternedGraph {
+	fn new(graph: &Graph<BTreeSet<String>>) -> Self {
+		let nodes: Interned<String> = graph.nodes.iter().cloned().collect();
+		let index = |n: &String| nodes.index_of(n).unwrap();
+		let edges = graph
+			.edges
+			.iter()
+			.map(|(a, b)| (index(a), index(b)))
+			.collect();
+		Self { nodes, edges }
+	}
+}
+
+impl Normalize for InternedGraph {
+	type Elements = Interned<String>;
+	type Color = usize;
+	type Cache = Vec<Vec<usize>>;
+	type Morphed = Vec<(usize, usize)>;
+
+	fn elements(&self) -> &Interned<String> {
+		&self.nodes
+	}
+
+	fn initialize_cache(&self) -> Self::Cache {
+		let mut neighbors = vec![Vec::new(); self.nodes.len()];
+		for &(a, b) in &self.edges {
+			neighbors[a].push(b);
+			neighbors[b].push(a)
+		}
+
+		neighbors
+	}
+
+	fn initial_coloring(&self) -> Vec<usize> {
+		self.initialize_cache().iter().map(Vec::len).collect()
+	}
+
+	fn refine_coloring(
+		&self,
+		cache: &mut Self::Cache,
+		coloring: &mut normal_form::ReversibleColoring<Interned<String>>,
+	) {
+		coloring.make_equitable(&self.nodes, |&i| &cache[i])
+	}
+
+	fn apply_morphism<F>(&self, f: F) -> Self::Morphed
+	where
+		F: Fn(&usize) -> usize,
+	{
+		let mut edges: Vec<_> = self
+			.edges
+			.iter()
+			.map(|(a, b)| {
+				let (a, b) = (f(a), f(b));
+				(a.min(b), a.max(b))
+			})
+			.collect();
+		edges.sort_unstable();
+		edges
+	}
+}
+
+#[test]
+fn interned() {
+	let renamings: [fn(&str) -> String; 2] = [
+		|n| n.to_string(),
+		|n| format!("{}{}", 6 - (n.as_bytes()[0] - b'a'), n),
+	];
+	let expected = make_graph::<BTreeSet<String>>(renamings[0]).normal_form();
+	for rename in renamings {
+		let graph = InternedGraph::new(&make_graph(rename));
+		let (normal_form, permutation) = graph.normalize();
+		assert_eq!(normal_form, expected);
+
+		// The canonical index of each identifier.
+		let canonical = |n: &str| permutation[graph.nodes.index_of(&rename(n)).unwrap()];
+		let mut image: Vec<_> = NAMES.iter().map(|n| canonical(n)).collect();
+		image.sort_unstable();
+		assert_eq!(image, (0..NAMES.len()).collect::<Vec<_>>())
+	}
+}
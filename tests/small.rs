@@ -0,0 +1,182 @@
+use normal_form::set::{BitSet256, BitSet64};
+use normal_form::{Normalize, ReversibleColoring, Set};
+use rand::seq::SliceRandom;
+
+/// Undirected graph over a small set of vertices.
+struct Graph<S> {
+	vertices: S,
+	edges: Vec<(u8, u8)>,
+}
+
+impl<S: Set<Item = u8>> Normalize for Graph<S> {
+	type Elements = S;
+	type Color = ();
+	type Cache = S::Map<Vec<u8>>;
+	type Morphed = Vec<(usize, usize)>;
+
+	fn elements(&self) -> &S {
+		&self.vertices
+	}
+
+	fn initialize_cache(&self) -> Self::Cache {
+		self.vertices.map(|v| {
+			self.edges
+				.iter()
+				.filter_map(|&(a, b)| {
+					if a == *v {
+						Some(b)
+					} else if b == *v {
+						Some(a)
+					} else {
+						None
+					}
+				})
+				.collect()
+		})
+	}
+
+	fn initial_coloring(&self) -> S::Map<()> {
+		self.vertices.map(|_| ())
+	}
+
+	fn refine_coloring(&self, cache: &mut Self::Cache, coloring: &mut ReversibleColoring<S>) {
+		use normal_form::Map;
+		coloring.make_equitable(&self.vertices, |v| cache.get(v).unwrap())
+	}
+
+	fn apply_morphism<F>(&self, f: F) -> Self::Morphed
+	where
+		F: Fn(&u8) -> usize,
+	{
+		let mut edges: Vec<_> = self
+			.edges
+			.iter()
+			.map(|(a, b)| {
+				let (a, b) = (f(a), f(b));
+				(a.min(b), a.max(b))
+			})
+			.collect();
+		edges.sort_unstable();
+		edges
+	}
+}
+
+/// Builds a random graph over the given vertices.
+fn random_graph<S: Set<Item = u8>>(vertices: S, edge_count: usize) -> Graph<S> {
+	let items: Vec<u8> = vertices.iter().collect();
+	let edges = (0..edge_count)
+		.map(|_| {
+			let mut rng = rand::thread_rng();
+			(
+				*items.choose(&mut rng).unwrap(),
+				*items.choose(&mut rng).unwrap(),
+			)
+		})
+		.collect();
+
+	Graph { vertices, edges }
+}
+
+/// Randomly permutes the vertices of the given graph inside `target`.
+fn random_morphism<S: Set<Item = u8>>(graph: &Graph<S>, target: S) -> Graph<S> {
+	let from: Vec<u8> = graph.vertices.iter().collect();
+	let mut to: Vec<u8> = target.iter().collect();
+	to.shuffle(&mut rand::thread_rng());
+	let f = |v: u8| to[from.binary_search(&v).unwrap()];
+
+	Graph {
+		vertices: target,
+		edges: graph.edges.iter().map(|&(a, b)| (f(a), f(b))).collect(),
+	}
+}
+
+fn test_random<S: Set<Item = u8> + Clone>(vertices: S, targets: &[S], edge_count: usize)
+where
+	S::Map<usize>: Clone,
+{
+	for _ in 0..50 {
+		let a = random_graph(vertices.clone(), edge_count);
+		let normal_form = a.normal_form();
+
+		for target in targets {
+			let b = random_morphism(&a, target.clone());
+			assert_eq!(normal_form, b.normal_form())
+		}
+	}
+}
+
+#[test]
+fn natural_u8() {
+	test_random(10u8, &[10u8; 10], 15)
+}
+
+#[test]
+fn bit_set_64() {
+	let vertices: BitSet64 = [1, 5, 8, 13, 21, 34, 55, 63].into_iter().collect();
+	let targets: Vec<BitSet64> = vec![
+		(0..8).collect(),
+		(56..64).collect(),
+		[0, 2, 4, 6, 8, 10, 12, 14].into_iter().collect(),
+	];
+	test_random(vertices, &targets, 12)
+}
+
+#[test]
+fn bit_set_256() {
+	let vertices: BitSet256 = [0, 63, 64, 127, 128, 200, 255].into_iter().collect();
+	let targets: Vec<BitSet256> = vec![(0..7).collect(), (249..=255).collect()];
+	test_random(vertices, &targets, 10)
+}
+
+#[test]
+fn regular_graphs() {
+	// 3-regular graphs on 8 vertices that equitable refinement alone cannot
+	// tell apart: the cube and two disjoint copies of `K_4`.
+	let cube = Graph {
+		vertices: 8u8,
+		edges: vec![
+			(0, 1),
+			(1, 3),
+			(3, 2),
+			(2, 0),
+			(4, 5),
+			(5, 7),
+			(7, 6),
+			(6, 4),
+			(0, 4),
+			(1, 5),
+			(2, 6),
+			(3, 7),
+		],
+	};
+	let k4s = Graph {
+		vertices: 8u8,
+		edges: (0..4u8)
+			.flat_map(|i| ((i + 1)..4).flat_map(move |j| [(i, j), (i + 4, j + 4)]))
+			.collect(),
+	};
+
+	let cube_form = cube.normal_form();
+	let k4s_form = k4s.normal_form();
+	assert_ne!(cube_form, k4s_form);
+
+	for _ in 0..20 {
+		assert_eq!(random_morphism(&cube, 8u8).normal_form(), cube_form);
+		assert_eq!(random_morphism(&k4s, 8u8).normal_form(), k4s_form)
+	}
+
+	assert_eq!(cube.automorphism_group_order().to_u128(), Some(48));
+	assert_eq!(k4s.automorphism_group_order().to_u128(), Some(1152))
+}
+
+#[test]
+fn empty_graph() {
+	// The search tree has `10!` leaves, all equivalent.
+	let graph = Graph {
+		vertices: 10u8,
+		edges: Vec::new(),
+	};
+
+	assert_eq!(graph.automorphism_group_order().to_u128(), Some(3628800));
+	assert_eq!(graph.normal_form(), Vec::new())
+}